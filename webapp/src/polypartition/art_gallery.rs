@@ -0,0 +1,269 @@
+use std::collections::{HashSet, VecDeque};
+
+use visioncortex::PointF64;
+
+use super::{DualGraph, Polygon, PolygonInterface, intersects, is_convex, remove_holes, triangulate_ec_indices};
+
+/// Ear-clips the polygons after joining the holes to the outlines with `remove_holes`.
+///
+/// Unlike `triangulate_ec_vec_with_adjacency`, both ends of each bridge appear twice in `points`,
+/// once for each side of the bridge. So the bridges are boundary edges, and the dual graph is a tree
+/// for each outline, even with holes.
+pub fn triangulate_bridged_with_adjacency(polys: &[Polygon]) -> Result<DualGraph, &'static str> {
+    let mut points = vec![];
+    let mut pieces = vec![];
+    for poly in remove_holes(polys)?.iter() {
+        let start = points.len();
+        points.extend(poly.props().points.iter().copied());
+        for triangle in triangulate_ec_indices(poly)? {
+            pieces.push(triangle.iter().map(|&i| start + i).collect());
+        }
+    }
+    Ok(DualGraph::from_pieces(points, pieces))
+}
+
+/// 3-colours the vertices of a triangulation by traversing its dual graph.
+///
+/// Returns the colour (0, 1 or 2) of each point of `graph.points`, such that every triangle has
+/// one corner of each colour; points that are not a corner of any triangle get colour 0.
+/// A triangulation whose dual graph is a forest can always be coloured, such as that of polygons
+/// without holes, or `triangulate_bridged_with_adjacency`. Triangulations around a hole with shared
+/// points close cycles that may force two colours on a vertex; those are rejected.
+pub fn three_colour_triangulation(graph: &DualGraph) -> Result<Vec<usize>, &'static str> {
+    if graph.pieces.iter().any(|piece| piece.len() != 3) {
+        return Err("Input is not a triangulation.");
+    }
+
+    let mut colours: Vec<Option<usize>> = vec![None; graph.points.len()];
+    let mut is_visited = vec![false; graph.num_pieces()];
+    for root in 0..graph.num_pieces() {
+        if is_visited[root] {
+            continue;
+        }
+        for (colour, &v) in graph.pieces[root].iter().enumerate() {
+            colours[v] = Some(colour);
+        }
+        is_visited[root] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(root);
+        while let Some(piece) = queue.pop_front() {
+            for edge in graph.neighbors(piece).iter() {
+                if is_visited[edge.piece] {
+                    continue;
+                }
+                // The two shared corners keep their colours, the third corner gets the remaining one
+                let (colour_1, colour_2) = (colours[edge.index_1].unwrap(), colours[edge.index_2].unwrap());
+                let third = graph.pieces[edge.piece].iter()
+                    .find(|&&v| v != edge.index_1 && v != edge.index_2)
+                    .copied()
+                    .unwrap();
+                let colour = 3 - colour_1 - colour_2;
+                if matches!(colours[third], Some(other) if other != colour) {
+                    return Err("Triangulation cannot be 3-coloured.");
+                }
+                colours[third] = Some(colour);
+                is_visited[edge.piece] = true;
+                queue.push_back(edge.piece);
+            }
+        }
+    }
+    // A vertex reached around a hole may already have taken the colour of another corner
+    for piece in graph.pieces.iter() {
+        let (c1, c2, c3) = (colours[piece[0]], colours[piece[1]], colours[piece[2]]);
+        if c1 == c2 || c2 == c3 || c3 == c1 {
+            return Err("Triangulation cannot be 3-coloured.");
+        }
+    }
+
+    Ok(colours.into_iter().map(|colour| colour.unwrap_or(0)).collect())
+}
+
+/// Places guards that together see the whole of the polygons (Fisk's proof of the art gallery theorem).
+///
+/// Colours `triangulate_bridged_with_adjacency` of the polygons and returns the smallest colour class,
+/// which has at most floor((n+2h)/3) guards for n vertices and h holes. With `reduce`, guards are then
+/// removed greedily as long as every triangle stays fully visible to some guard.
+pub fn art_gallery_guards(polys: &[Polygon], reduce: bool) -> Result<Vec<PointF64>, &'static str> {
+    let graph = &triangulate_bridged_with_adjacency(polys)?;
+    let colours = three_colour_triangulation(graph)?;
+
+    let mut classes = vec![HashSet::new(); 3];
+    for &v in graph.pieces.iter().flatten() {
+        classes[colours[v]].insert(v);
+    }
+    let mut guards: Vec<usize> = classes.into_iter()
+        .min_by_key(|class| class.len())
+        .unwrap()
+        .into_iter()
+        .collect();
+    guards.sort_unstable();
+
+    if reduce {
+        guards = reduce_guards(graph, guards);
+    }
+
+    // Both ends of a bridge may be guards
+    let mut points: Vec<PointF64> = vec![];
+    for v in guards {
+        if !points.contains(&graph.points[v]) {
+            points.push(graph.points[v]);
+        }
+    }
+    Ok(points)
+}
+
+/// Greedily drop the guards seeing the fewest triangles, while every triangle remains covered
+fn reduce_guards(graph: &DualGraph, guards: Vec<usize>) -> Vec<usize> {
+    let boundary = boundary_edges(graph);
+    let coverage: Vec<HashSet<usize>> = guards.iter().map(|&guard| {
+        (0..graph.num_pieces()).filter(|&piece| sees_triangle(graph, &boundary, guard, piece)).collect()
+    }).collect();
+
+    let mut num_guards_seeing = vec![0; graph.num_pieces()];
+    for covered in coverage.iter() {
+        for &piece in covered.iter() {
+            num_guards_seeing[piece] += 1;
+        }
+    }
+
+    let mut order: Vec<usize> = (0..guards.len()).collect();
+    order.sort_by_key(|&i| coverage[i].len());
+    let mut is_kept = vec![true; guards.len()];
+    for i in order {
+        if coverage[i].iter().all(|&piece| num_guards_seeing[piece] > 1) {
+            is_kept[i] = false;
+            for &piece in coverage[i].iter() {
+                num_guards_seeing[piece] -= 1;
+            }
+        }
+    }
+
+    guards.into_iter().zip(is_kept).filter(|(_, kept)| *kept).map(|(guard, _)| guard).collect()
+}
+
+/// The edges of the triangulation that are not shared by two triangles, except the bridges
+/// (boundary edges running both ways between the same coordinates)
+fn boundary_edges(graph: &DualGraph) -> Vec<(usize, usize)> {
+    let mut boundary = vec![];
+    for (piece, indices) in graph.pieces.iter().enumerate() {
+        let len = indices.len();
+        for curr in 0..len {
+            let (index_1, index_2) = (indices[curr], indices[(curr+1) % len]);
+            if !graph.neighbors(piece).iter().any(|edge| edge.index_1 == index_1 && edge.index_2 == index_2) {
+                boundary.push((index_1, index_2));
+            }
+        }
+    }
+    let points = &graph.points;
+    boundary.iter().copied().filter(|&(i1, i2)| {
+        !boundary.iter().any(|&(j1, j2)| points[j1] == points[i2] && points[j2] == points[i1])
+    }).collect()
+}
+
+/// A triangle is fully visible from the guard if the guard is one of its corners, or if the
+/// triangles fanning from the guard to its edges are crossed by no boundary edge and contain no boundary vertex.
+fn sees_triangle(graph: &DualGraph, boundary: &[(usize, usize)], guard: usize, piece: usize) -> bool {
+    let corners = &graph.pieces[piece];
+    if corners.contains(&guard) {
+        return true;
+    }
+    let g = graph.points[guard];
+    let sight_lines_clear = corners.iter().all(|&corner| {
+        let p = graph.points[corner];
+        boundary.iter().all(|&(i1, i2)| !intersects(&g, &p, &graph.points[i1], &graph.points[i2]))
+    });
+    if !sight_lines_clear {
+        return false;
+    }
+    (0..3).all(|curr| {
+        let (a, b) = (corners[curr], corners[(curr+1) % 3]);
+        let (pa, pb) = (graph.points[a], graph.points[b]);
+        // By coordinates, as the ends of a bridge appear twice
+        boundary.iter().flat_map(|&(i1, i2)| vec![graph.points[i1], graph.points[i2]]).all(|p| {
+            p == g || p == pa || p == pb || !is_in_closed_triangle(&g, &pa, &pb, &p)
+        })
+    })
+}
+
+/// Returns true iff p lies inside or on the triangle, whatever its orientation
+fn is_in_closed_triangle(p1: &PointF64, p2: &PointF64, p3: &PointF64, p: &PointF64) -> bool {
+    let (p1, p2) = if is_convex(p1, p2, p3) { (p1, p2) } else { (p2, p1) };
+    let cross = |a: &PointF64, b: &PointF64| (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    cross(p1, p2) >= 0.0 && cross(p2, p3) >= 0.0 && cross(p3, p1) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{GeneratorOptions, PolygonGenerator, generate_polygons, triangulate_ec_vec_with_adjacency};
+    use crate::polypartition::fixtures::{hexagon_with_hole, square};
+
+    /// A comb with the given number of teeth, each tooth needing its own guard
+    fn comb(teeth: usize) -> Polygon {
+        let mut points = vec![PointF64::new(0.0, 0.0)];
+        for tooth in 0..teeth {
+            let x = tooth as f64 * 30.0;
+            points.push(PointF64::new(x + 10.0, 100.0));
+            points.push(PointF64::new(x + 20.0, 0.0));
+            points.push(PointF64::new(x + 30.0, 0.0));
+        }
+        points.pop();
+        points.push(PointF64::new(teeth as f64 * 30.0, -20.0));
+        points.push(PointF64::new(0.0, -20.0));
+        points.reverse();
+        Polygon::from_points_and_is_hole(points, false)
+    }
+
+    #[test]
+    fn art_gallery_colouring() {
+        let graph = triangulate_ec_vec_with_adjacency(vec![comb(4)]).unwrap();
+        let colours = three_colour_triangulation(&graph).unwrap();
+        assert_eq!(colours.len(), graph.points.len());
+        assert!(colours.iter().all(|&colour| colour < 3));
+        for piece in graph.pieces.iter() {
+            let mut corner_colours: Vec<usize> = piece.iter().map(|&v| colours[v]).collect();
+            corner_colours.sort_unstable();
+            assert_eq!(corner_colours, [0, 1, 2]);
+        }
+
+        // Around a square hole, the 8 triangles between the rings force two colours on a vertex
+        let room = vec![square(0.0, 0.0, 100.0, false), square(30.0, 30.0, 40.0, true)];
+        let graph = triangulate_ec_vec_with_adjacency(room.clone()).unwrap();
+        assert!(three_colour_triangulation(&graph).is_err());
+        // Unless the bridge is cut open
+        let graph = triangulate_bridged_with_adjacency(&room).unwrap();
+        assert_eq!(graph.points.len(), 10);
+        assert_eq!(graph.adjacency.iter().map(|edges| edges.len()).sum::<usize>(), 2 * (graph.num_pieces() - 1));
+        assert!(three_colour_triangulation(&graph).is_ok());
+    }
+
+    #[test]
+    fn art_gallery_guard_counts() {
+        let polys = hexagon_with_hole();
+        assert!(art_gallery_guards(&polys, false).unwrap().len() <= 4);
+        let guards = art_gallery_guards(&polys, true).unwrap();
+        assert!(!guards.is_empty() && guards.len() <= 3);
+        assert!(guards.iter().all(|guard| polys.iter().any(|poly| poly.props().points.contains(guard))));
+
+        let comb = comb(4);
+        let n = comb.props().num_points();
+        let guards = art_gallery_guards(&[comb], true).unwrap();
+        assert!(guards.len() >= 4 && guards.len() <= n / 3);
+    }
+
+    #[test]
+    fn art_gallery_guards_with_holes() {
+        for &generator in PolygonGenerator::ALL.iter() {
+            for seed in 0..10 {
+                let options = GeneratorOptions { generator, num_vertices: 20, num_holes: 1 + seed as usize % 3, seed, ..Default::default() };
+                let polys = generate_polygons(&options).unwrap();
+                let n: usize = polys.iter().map(|poly| poly.num_points()).sum();
+                let h = polys.len() - 1;
+                for &reduce in [false, true].iter() {
+                    let guards = art_gallery_guards(&polys, reduce).unwrap();
+                    assert!(!guards.is_empty() && guards.len() <= (n + 2 * h) / 3, "{:?}", options);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::{cmp::{Ordering, Reverse}, collections::{BinaryHeap, HashMap}};
+
+use visioncortex::PointF64;
+
+use super::{BooleanOp, FillRule, Polygon, PolygonInterface, distance, f64_approximately, orient2d, polygons_extent, signed_area};
+
+/// An edge of the arrangement, shared by all input edges running over it.
+///
+/// Stored from the end met first by the sweep (smaller x, then smaller y) to the other;
+/// `winding` counts the input edges of each set running in that direction, minus those running the other way.
+struct Segment {
+    index_1: usize,
+    index_2: usize,
+    winding: [i32; 2],
+    /// The winding numbers of both sets on the right of the segment, set when the sweep reaches it
+    below: [i32; 2],
+}
+
+/// The input edges of both sets, split at every intersection
+struct Arrangement {
+    points: Vec<PointF64>,
+    point_indices: HashMap<(u64, u64), usize>,
+    segments: Vec<Segment>,
+    /// The segments starting at each point
+    starting: Vec<Vec<usize>>,
+    /// How far apart points must be to be told apart, relative to the size of the input
+    tolerance: f64,
+}
+
+/// How two neighbouring segments meet away from their shared ends
+enum Meeting {
+    /// Inside both, at a point to add
+    Crossing(PointF64),
+    /// At this end of one of them, which lies on the other
+    Touching(usize),
+}
+
+/// A point the sweep stops at
+#[derive(Clone, Copy, Debug)]
+struct SweepEvent {
+    p: PointF64,
+    index: usize,
+}
+
+impl PartialEq for SweepEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl Eq for SweepEvent {}
+
+impl PartialOrd for SweepEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SweepEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.p.x.total_cmp(&other.p.x)
+            .then(self.p.y.total_cmp(&other.p.y))
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+/// Whether the sweep meets p1 before p2
+fn is_before(p1: &PointF64, p2: &PointF64) -> bool {
+    p1.x < p2.x || (p1.x == p2.x && p1.y < p2.y)
+}
+
+impl Arrangement {
+    fn new(sets: [&[Polygon]; 2]) -> Self {
+        let mut arrangement = Self {
+            points: vec![],
+            point_indices: HashMap::new(),
+            segments: vec![],
+            starting: vec![],
+            tolerance: 1e-9 * polygons_extent(sets.iter().flat_map(|polys| polys.iter())),
+        };
+
+        for (set, polys) in sets.iter().enumerate() {
+            for poly in polys.iter() {
+                let points = &poly.props().points;
+                let len = points.len();
+                for curr in 0..len {
+                    let (p1, p2) = (points[curr], points[(curr+1) % len]);
+                    if p1 != p2 {
+                        let (from, to) = (arrangement.add_point(p1), arrangement.add_point(p2));
+                        let (index_1, index_2, direction) = if is_before(&p1, &p2) { (from, to, 1) } else { (to, from, -1) };
+                        let mut winding = [0, 0];
+                        winding[set] = direction;
+                        arrangement.starting[index_1].push(arrangement.segments.len());
+                        arrangement.segments.push(Segment { index_1, index_2, winding, below: [0, 0] });
+                    }
+                }
+            }
+        }
+
+        arrangement.sweep();
+        arrangement
+    }
+
+    fn add_point(&mut self, p: PointF64) -> usize {
+        // Adding 0.0 turns -0.0 into 0.0, so that equal points have equal bits
+        let p = PointF64::new(p.x + 0.0, p.y + 0.0);
+        let len = self.points.len();
+        let index = *self.point_indices.entry((p.x.to_bits(), p.y.to_bits())).or_insert(len);
+        if index == len {
+            self.points.push(p);
+            self.starting.push(vec![]);
+        }
+        index
+    }
+
+    /// Signed distance of p from the line through the segment, positive on its left
+    fn distance(&self, segment: usize, p: &PointF64) -> f64 {
+        let (a, b) = (self.points[self.segments[segment].index_1], self.points[self.segments[segment].index_2]);
+        orient2d(&a, &b, p) / (b - a).norm()
+    }
+
+    /// Sweeps a line from left to right over the segments (Bentley-Ottmann), keeping those it crosses
+    /// from bottom to top. At each point, the segments through it are split there, overlapping segments
+    /// starting there are merged, and the winding numbers below each new segment are taken from the
+    /// segment under it. Crossings of segments that become neighbours are added as points to stop at.
+    fn sweep(&mut self) {
+        let mut events: BinaryHeap<Reverse<SweepEvent>> = (0..self.points.len())
+            .map(|index| Reverse(SweepEvent { p: self.points[index], index }))
+            .collect();
+        let mut status: Vec<usize> = vec![];
+        let mut last = None;
+        while let Some(Reverse(SweepEvent { p, index })) = events.pop() {
+            if last == Some(index) {
+                continue;
+            }
+            last = Some(index);
+
+            // The segments touching p end here; those passing through go on from p as new segments
+            let lo = status.partition_point(|&segment| self.distance(segment, &p) > self.tolerance);
+            let hi = lo + status[lo..].iter().take_while(|&&segment| self.distance(segment, &p) >= -self.tolerance).count();
+            for segment in status.drain(lo..hi).collect::<Vec<usize>>() {
+                let index_2 = self.segments[segment].index_2;
+                if index_2 != index {
+                    self.segments[segment].index_2 = index;
+                    self.starting[index].push(self.segments.len());
+                    self.segments.push(Segment { index_1: index, index_2, winding: self.segments[segment].winding, below: [0, 0] });
+                }
+            }
+
+            // The segments starting here from bottom to top, with overlapping ones merged
+            let mut starting = std::mem::take(&mut self.starting[index]);
+            starting.sort_by(|&s1, &s2| {
+                let (q1, q2) = (self.points[self.segments[s1].index_2], self.points[self.segments[s2].index_2]);
+                0.0_f64.total_cmp(&(orient2d(&p, &q1, &q2) + 0.0))
+            });
+            let mut merged: Vec<usize> = vec![];
+            for segment in starting.into_iter() {
+                match merged.last_mut() {
+                    Some(previous) if orient2d(&p, &self.points[self.segments[*previous].index_2], &self.points[self.segments[segment].index_2]) == 0.0 => {
+                        *previous = self.merge(*previous, segment);
+                    },
+                    _ => merged.push(segment),
+                }
+            }
+
+            let mut winding = if lo > 0 { self.above(status[lo - 1]) } else { [0, 0] };
+            for &segment in merged.iter() {
+                self.segments[segment].below = winding;
+                winding = self.above(segment);
+            }
+            let num_merged = merged.len();
+            status.splice(lo..lo, merged);
+
+            // The new neighbours: below and above the new segments, or around p if there are none
+            let mut neighbours = vec![lo];
+            if num_merged > 0 {
+                neighbours.push(lo + num_merged);
+            }
+            for upper in neighbours.into_iter() {
+                if upper == 0 || upper >= status.len() {
+                    continue;
+                }
+                match self.meeting(status[upper - 1], status[upper]) {
+                    Some(Meeting::Crossing(crossing)) if is_before(&p, &crossing) => {
+                        let crossing = self.add_point(crossing);
+                        events.push(Reverse(SweepEvent { p: self.points[crossing], index: crossing }));
+                    },
+                    Some(Meeting::Touching(end)) => {
+                        self.bend(status[upper - 1], end);
+                        self.bend(status[upper], end);
+                    },
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    /// Merges two segments starting at the same point in the same direction into the shorter one.
+    /// The rest of the longer one starts anew where the shorter one ends. Returns the shorter one.
+    fn merge(&mut self, segment_1: usize, segment_2: usize) -> usize {
+        let (end_1, end_2) = (self.segments[segment_1].index_2, self.segments[segment_2].index_2);
+        let (short, long) = if end_1 == end_2 || is_before(&self.points[end_1], &self.points[end_2]) {
+            (segment_1, segment_2)
+        } else {
+            (segment_2, segment_1)
+        };
+        let long_winding = self.segments[long].winding;
+        for (count, other) in self.segments[short].winding.iter_mut().zip(long_winding.iter()) {
+            *count += other;
+        }
+        if end_1 == end_2 {
+            // Nothing is left of the other one
+            self.segments[long].winding = [0, 0];
+        } else {
+            let end = self.segments[short].index_2;
+            self.segments[long].index_1 = end;
+            self.starting[end].push(long);
+        }
+        short
+    }
+
+    /// Where two segments meet inside at least one of them, if they do
+    fn meeting(&self, segment_1: usize, segment_2: usize) -> Option<Meeting> {
+        let (Segment { index_1: from_1, index_2: to_1, .. }, Segment { index_1: from_2, index_2: to_2, .. }) =
+            (&self.segments[segment_1], &self.segments[segment_2]);
+        let (p, q) = (self.points[*from_1], self.points[*from_2]);
+        let (r, s) = (self.points[*to_1] - p, self.points[*to_2] - q);
+        let cross = |a: PointF64, b: PointF64| a.x * b.y - a.y * b.x;
+        let denominator = cross(r, s);
+        // Parallel segments only ever overlap from an end of one, which is a point of the sweep already
+        if f64_approximately(denominator / (r.norm() * s.norm()), 0.0) {
+            return None;
+        }
+        let t = cross(q - p, s) / denominator;
+        let u = cross(q - p, r) / denominator;
+        if t <= 0.0 || t >= 1.0 || u <= 0.0 || u >= 1.0 {
+            return None;
+        }
+        let crossing = PointF64::new(p.x + r.x * t, p.y + r.y * t);
+        let ends = [from_1, to_1, from_2, to_2];
+        match ends.iter().find(|&&&index| distance(&self.points[index], &crossing) <= self.tolerance) {
+            Some(&&end) => Some(Meeting::Touching(end)),
+            None => Some(Meeting::Crossing(crossing)),
+        }
+    }
+
+    /// Makes the segment pass through the end of another segment lying on it, if the sweep would not
+    /// get there before the segment ends. This happens to (nearly) vertical segments, as the end may
+    /// be to the right of them within the tolerance. The rest from the old end is turned around.
+    fn bend(&mut self, segment: usize, end: usize) {
+        let Segment { index_1, index_2, winding, .. } = self.segments[segment];
+        let is_near = |index: usize| distance(&self.points[index], &self.points[end]) <= self.tolerance;
+        if is_near(index_1) || is_near(index_2) || !is_before(&self.points[index_2], &self.points[end]) {
+            // Touching at an end is found when the sweep gets there
+            return;
+        }
+        self.segments[segment].index_2 = end;
+        self.starting[index_2].push(self.segments.len());
+        self.segments.push(Segment { index_1: index_2, index_2: end, winding: [-winding[0], -winding[1]], below: [0, 0] });
+    }
+
+    /// The winding numbers of both sets on the left of the segment
+    fn above(&self, segment: usize) -> [i32; 2] {
+        let Segment { winding, below, .. } = self.segments[segment];
+        [below[0] + winding[0], below[1] + winding[1]]
+    }
+
+    /// The winding numbers of both sets on the (left, right) of the segment, walking from index_1 to index_2
+    fn windings(&self, segment: usize) -> ([i32; 2], [i32; 2]) {
+        (self.above(segment), self.segments[segment].below)
+    }
+}
+
+/// Computes the boolean operation of two polygon sets, with the even-odd fill rule.
+///
+/// Each set may contain holes, which need not be marked. Returns the rings of the result,
+/// solids wound counter-clockwise and holes clockwise (by `Orientation`), with `is_hole` set,
+/// ready for `remove_holes`.
+pub fn boolean_op(subject: &[Polygon], clip: &[Polygon], op: BooleanOp) -> Result<Vec<Polygon>, &'static str> {
+    boolean_op_with_fill_rule(subject, clip, op, FillRule::EvenOdd)
+}
+
+/// Same as `boolean_op`, with a choice of how the rings of each set determine its interior
+pub fn boolean_op_with_fill_rule(subject: &[Polygon], clip: &[Polygon], op: BooleanOp, fill_rule: FillRule) -> Result<Vec<Polygon>, &'static str> {
+    if subject.iter().chain(clip.iter()).any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+    let arrangement = Arrangement::new([subject, clip]);
+
+    let is_filled = |winding: i32| match fill_rule {
+        FillRule::EvenOdd => winding & 1 == 1,
+        FillRule::NonZero => winding != 0,
+    };
+    let is_in_result = |winding: [i32; 2]| {
+        let (in_subject, in_clip) = (is_filled(winding[0]), is_filled(winding[1]));
+        match op {
+            BooleanOp::Union => in_subject || in_clip,
+            BooleanOp::Intersection => in_subject && in_clip,
+            BooleanOp::Difference => in_subject && !in_clip,
+            BooleanOp::Xor => in_subject != in_clip,
+        }
+    };
+
+    // Keep the segments separating the result from the rest, directed with the result on their left
+    let mut edges = vec![];
+    for (i, segment) in arrangement.segments.iter().enumerate() {
+        let (left, right) = arrangement.windings(i);
+        match (is_in_result(left), is_in_result(right)) {
+            (true, false) => edges.push((segment.index_1, segment.index_2)),
+            (false, true) => edges.push((segment.index_2, segment.index_1)),
+            _ => {},
+        }
+    }
+
+    Ok(connect_rings(&arrangement.points, &edges, arrangement.tolerance))
+}
+
+
+/// Joins directed edges into rings. At a vertex with several ways out, the sharpest left turn
+/// is taken, so rings touching at a vertex come out separately. Rings thinner than the tolerance are dropped.
+fn connect_rings(points: &[PointF64], edges: &[(usize, usize)], tolerance: f64) -> Vec<Polygon> {
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (e, &(from, _)) in edges.iter().enumerate() {
+        outgoing.entry(from).or_default().push(e);
+    }
+    let next = |e: usize| -> usize {
+        let (from, to) = edges[e];
+        let back = points[from] - points[to];
+        let clockwise_angle = |candidate: &usize| {
+            let out = points[edges[*candidate].1] - points[to];
+            let angle = -(back.x * out.y - back.y * out.x).atan2(back.dot(out));
+            if angle <= 0.0 { angle + 2.0 * std::f64::consts::PI } else { angle }
+        };
+        *outgoing[&to].iter()
+            .min_by(|c1, c2| clockwise_angle(c1).total_cmp(&clockwise_angle(c2)))
+            .unwrap()
+    };
+
+    let mut is_used = vec![false; edges.len()];
+    let mut rings = vec![];
+    for start in 0..edges.len() {
+        if is_used[start] {
+            continue;
+        }
+        let mut ring = vec![];
+        let mut e = start;
+        while !is_used[e] {
+            is_used[e] = true;
+            ring.push(points[edges[e].0]);
+            e = next(e);
+        }
+        let ring = remove_collinear(ring, tolerance);
+        let area = signed_area(&ring);
+        let perimeter: f64 = (0..ring.len()).map(|i| distance(&ring[i], &ring[(i+1) % ring.len()])).sum();
+        if ring.len() >= 3 && area.abs() > tolerance * perimeter {
+            rings.push(Polygon::from_points_and_is_hole(ring, area < 0.0));
+        }
+    }
+    rings
+}
+
+/// Removes the points within the tolerance of the line through their neighbours
+fn remove_collinear(mut ring: Vec<PointF64>, tolerance: f64) -> Vec<PointF64> {
+    loop {
+        let len = ring.len();
+        let collinear = (0..len).find(|&i| {
+            let (p1, p2, p3) = (ring[(i+len-1) % len], ring[i], ring[(i+1) % len]);
+            orient2d(&p1, &p3, &p2).abs() <= tolerance * distance(&p1, &p3)
+        });
+        match collinear {
+            Some(i) if len > 3 => { ring.remove(i); },
+            _ => return ring,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{GeneratorOptions, PolygonGenerator, generate_polygons, polygons_area};
+    use crate::polypartition::fixtures::square;
+
+    #[test]
+    fn boolean_op_overlapping_squares() {
+        let (subject, clip) = (vec![square(0.0, 0.0, 20.0, false)], vec![square(10.0, 10.0, 20.0, false)]);
+        let area = |op| polygons_area(&boolean_op(&subject, &clip, op).unwrap());
+        assert!(f64_approximately(area(BooleanOp::Union), 700.0));
+        assert!(f64_approximately(area(BooleanOp::Intersection), 100.0));
+        assert!(f64_approximately(area(BooleanOp::Difference), 300.0));
+        assert!(f64_approximately(area(BooleanOp::Xor), 600.0));
+
+        let intersection = boolean_op(&subject, &clip, BooleanOp::Intersection).unwrap();
+        assert_eq!(intersection.len(), 1);
+        assert_eq!(intersection[0].num_points(), 4);
+        let xor = boolean_op(&subject, &clip, BooleanOp::Xor).unwrap();
+        // The two L shapes touch at two corners
+        assert_eq!(xor.len(), 2);
+        assert!(xor.iter().all(|poly| !poly.is_hole()));
+    }
+
+    #[test]
+    fn boolean_op_holes() {
+        // Subtracting an obstacle footprint from a room gives a hole
+        let room = vec![square(0.0, 0.0, 100.0, false)];
+        let obstacle = vec![square(40.0, 40.0, 20.0, false)];
+        let result = boolean_op(&room, &obstacle, BooleanOp::Difference).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result.iter().filter(|poly| poly.is_hole()).count(), 1);
+        assert!(f64_approximately(polygons_area(&result), 9600.0));
+
+        // The obstacle overlapping the wall cuts a notch instead
+        let obstacle = vec![square(90.0, 40.0, 20.0, false)];
+        let result = boolean_op(&room, &obstacle, BooleanOp::Difference).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].num_points(), 8);
+
+        // Shared edges and a hole in the input
+        let frame = vec![square(0.0, 0.0, 30.0, false), square(10.0, 10.0, 10.0, true)];
+        let right = vec![square(30.0, 0.0, 30.0, false)];
+        let union = boolean_op(&frame, &right, BooleanOp::Union).unwrap();
+        assert_eq!(union.len(), 2);
+        assert!(f64_approximately(polygons_area(&union), 1700.0));
+        assert!(f64_approximately(polygons_area(&boolean_op(&frame, &right, BooleanOp::Intersection).unwrap()), 0.0));
+    }
+
+    #[test]
+    fn boolean_op_small_coordinates() {
+        // Lon/lat parcels are this small
+        for &size in [1e-4, 1e-9].iter() {
+            let (subject, clip) = (vec![square(0.0, 0.0, size, false)], vec![square(size / 2.0, 0.0, size, false)]);
+            let union = boolean_op(&subject, &clip, BooleanOp::Union).unwrap();
+            assert_eq!(union.len(), 1);
+            assert_eq!(union[0].num_points(), 4);
+            assert!((polygons_area(&union) - 1.5 * size * size).abs() < 1e-9 * size * size);
+            let intersection = boolean_op(&subject, &clip, BooleanOp::Intersection).unwrap();
+            assert_eq!(intersection.len(), 1);
+            assert!((polygons_area(&intersection) - 0.5 * size * size).abs() < 1e-9 * size * size);
+        }
+    }
+
+    #[test]
+    fn boolean_op_area_identities() {
+        for &generator in PolygonGenerator::ALL.iter() {
+            for (seed, &size) in [100.0, 100.0, 1e-4, 1e-4].iter().enumerate() {
+                let options = GeneratorOptions { generator, num_vertices: 24, num_holes: 2, seed: seed as u64, size };
+                let subject = generate_polygons(&options).unwrap();
+                let clip = generate_polygons(&GeneratorOptions { seed: options.seed + 100, ..options.clone() }).unwrap();
+                let area = |op| polygons_area(&boolean_op(&subject, &clip, op).unwrap());
+                let (union, intersection) = (area(BooleanOp::Union), area(BooleanOp::Intersection));
+                let tolerance = 1e-6 * polygons_area(&subject);
+                assert!((union + intersection - polygons_area(&subject) - polygons_area(&clip)).abs() < tolerance, "{:?}", options);
+                assert!((area(BooleanOp::Difference) + intersection - polygons_area(&subject)).abs() < tolerance, "{:?}", options);
+                assert!((area(BooleanOp::Xor) + intersection - union).abs() < tolerance, "{:?}", options);
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use visioncortex::{PathF64, PointF64};
+
+use super::{DualGraph, Polygon, TriangleKind, distance, triangulate_opt_vec_with_adjacency};
+
+/// A point on the chordal axis, with the width of the shape there
+#[derive(Clone, Debug)]
+pub struct AxisNode {
+    pub point: PointF64,
+    pub width: f64,
+}
+
+/// An approximate medial axis, as a graph
+#[derive(Clone, Debug, Default)]
+pub struct ChordalAxis {
+    pub nodes: Vec<AxisNode>,
+    pub edges: Vec<(usize, usize)>,
+    /// The kind of each triangle of the triangulation
+    pub kinds: Vec<TriangleKind>,
+}
+
+impl ChordalAxis {
+    /// Splits the graph into paths between its ends and junctions (and closed loops, if any)
+    pub fn centreline_paths(&self) -> Vec<PathF64> {
+        let mut neighbors = vec![vec![]; self.nodes.len()];
+        for (e, &(node_1, node_2)) in self.edges.iter().enumerate() {
+            neighbors[node_1].push((node_2, e));
+            neighbors[node_2].push((node_1, e));
+        }
+
+        let mut is_used = vec![false; self.edges.len()];
+        let mut paths = vec![];
+        let mut walk = |start: usize, first: (usize, usize), is_used: &mut Vec<bool>| {
+            let mut path = PathF64::new();
+            path.add(self.nodes[start].point);
+            let (mut node, mut edge) = first;
+            loop {
+                is_used[edge] = true;
+                path.add(self.nodes[node].point);
+                if node == start || neighbors[node].len() != 2 {
+                    break;
+                }
+                match neighbors[node].iter().find(|&&(_, next_edge)| !is_used[next_edge]) {
+                    Some(&next) => { node = next.0; edge = next.1; },
+                    None => break,
+                }
+            }
+            paths.push(path);
+        };
+
+        // Branches start at ends and junctions; what is left are loops
+        let starts = (0..self.nodes.len()).filter(|&node| neighbors[node].len() != 2)
+            .chain((0..self.nodes.len()).filter(|&node| neighbors[node].len() == 2));
+        for start in starts.collect::<Vec<usize>>() {
+            for &first in neighbors[start].iter() {
+                if !is_used[first.1] {
+                    walk(start, first, &mut is_used);
+                }
+            }
+        }
+        paths
+    }
+}
+
+/// Extracts the chordal axis of a triangulation with adjacency (e.g. from `triangulate_mono_vec_with_adjacency`).
+///
+/// Sleeve triangles join the midpoints of their two diagonals, junction triangles join their
+/// centroid to the midpoints of their three diagonals. With `extend_terminals`, terminal
+/// triangles join the midpoint of their diagonal to their opposite corner; otherwise the
+/// branch stops at the diagonal. The width at a midpoint is the length of its diagonal.
+pub fn chordal_axis(graph: &DualGraph, extend_terminals: bool) -> Result<ChordalAxis, &'static str> {
+    if graph.pieces.iter().any(|piece| piece.len() != 3) {
+        return Err("Input is not a triangulation.");
+    }
+
+    let mut axis = ChordalAxis::default();
+    let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut midpoint = |axis: &mut ChordalAxis, index_1: usize, index_2: usize| {
+        let key = if index_1 < index_2 { (index_1, index_2) } else { (index_2, index_1) };
+        *midpoints.entry(key).or_insert_with(|| {
+            let (p1, p2) = (graph.points[index_1], graph.points[index_2]);
+            axis.nodes.push(AxisNode {
+                point: PointF64::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0),
+                width: distance(&p1, &p2),
+            });
+            axis.nodes.len() - 1
+        })
+    };
+
+    for (piece, corners) in graph.pieces.iter().enumerate() {
+        let diagonals = graph.neighbors(piece);
+        let kind = match diagonals.len() {
+            0 => TriangleKind::Isolated,
+            1 => TriangleKind::Terminal,
+            2 => TriangleKind::Sleeve,
+            _ => TriangleKind::Junction,
+        };
+        axis.kinds.push(kind);
+        // Each diagonal is seen from both triangles; the midpoints are shared
+        let ends: Vec<usize> = diagonals.iter().map(|edge| midpoint(&mut axis, edge.index_1, edge.index_2)).collect();
+
+        match kind {
+            TriangleKind::Isolated => {},
+            TriangleKind::Terminal => {
+                if extend_terminals {
+                    let edge = &diagonals[0];
+                    let tip = *corners.iter().find(|&&i| i != edge.index_1 && i != edge.index_2).unwrap();
+                    axis.nodes.push(AxisNode { point: graph.points[tip], width: 0.0 });
+                    axis.edges.push((ends[0], axis.nodes.len() - 1));
+                }
+            },
+            TriangleKind::Sleeve => axis.edges.push((ends[0], ends[1])),
+            TriangleKind::Junction => {
+                let points: Vec<PointF64> = corners.iter().map(|&i| graph.points[i]).collect();
+                let centroid = PointF64::new(
+                    (points[0].x + points[1].x + points[2].x) / 3.0,
+                    (points[0].y + points[1].y + points[2].y) / 3.0,
+                );
+                let width = ends.iter().map(|&end| axis.nodes[end].width).sum::<f64>() / 3.0;
+                axis.nodes.push(AxisNode { point: centroid, width });
+                let centre = axis.nodes.len() - 1;
+                for &end in ends.iter() {
+                    axis.edges.push((centre, end));
+                }
+            },
+        }
+    }
+    Ok(axis)
+}
+
+/// Triangulates the polygons (holes allowed) and extracts their chordal axis.
+///
+/// The triangulation minimizes the total length of the diagonals, so that they cut across the shape.
+pub fn chordal_axis_from_polygons(polys: &[Polygon], extend_terminals: bool) -> Result<ChordalAxis, String> {
+    let graph = triangulate_opt_vec_with_adjacency(polys.to_vec())?;
+    Ok(chordal_axis(&graph, extend_terminals)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::f64_approximately;
+
+    #[test]
+    fn chordal_axis_of_a_stroke() {
+        // A horizontal bar 10 wide: the centreline runs along y = 5
+        let bar = Polygon::from_points_and_is_hole(vec![
+            PointF64::new(0.0, 0.0), PointF64::new(30.0, 0.0), PointF64::new(60.0, 0.0), PointF64::new(90.0, 0.0),
+            PointF64::new(90.0, 10.0), PointF64::new(60.0, 10.0), PointF64::new(30.0, 10.0), PointF64::new(0.0, 10.0),
+        ], false);
+        let axis = chordal_axis_from_polygons(&[bar], false).unwrap();
+        assert_eq!(axis.kinds.iter().filter(|&&kind| kind == TriangleKind::Terminal).count(), 2);
+        assert!(axis.kinds.iter().all(|&kind| kind != TriangleKind::Junction));
+        assert!(axis.nodes.iter().all(|node| f64_approximately(node.point.y, 5.0)));
+        let paths = axis.centreline_paths();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), axis.nodes.len());
+    }
+
+    #[test]
+    fn chordal_axis_junction() {
+        // A T shape has one junction, where three branches meet
+        let t_shape = Polygon::from_points_and_is_hole(vec![
+            PointF64::new(0.0, 0.0), PointF64::new(100.0, 0.0), PointF64::new(100.0, 10.0),
+            PointF64::new(55.0, 10.0), PointF64::new(55.0, 60.0), PointF64::new(45.0, 60.0),
+            PointF64::new(45.0, 10.0), PointF64::new(0.0, 10.0),
+        ], false);
+        let axis = chordal_axis_from_polygons(&[t_shape], true).unwrap();
+        let num_junctions = axis.kinds.iter().filter(|&&kind| kind == TriangleKind::Junction).count();
+        let num_terminals = axis.kinds.iter().filter(|&&kind| kind == TriangleKind::Terminal).count();
+        // In a tree of triangles, the leaves are two more than the branching nodes
+        assert_eq!(num_terminals, num_junctions + 2);
+        assert!(num_junctions >= 1);
+        let paths = axis.centreline_paths();
+        let num_edges: usize = paths.iter().map(|path| path.len() - 1).sum();
+        assert_eq!(num_edges, axis.edges.len());
+    }
+}
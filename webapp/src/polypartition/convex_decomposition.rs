@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+
+use visioncortex::PointF64;
+
+use super::{Orientation, Polygon, PolygonInterface, distance, distance_to_segment, intersects, is_in_cone, is_inside_segment, is_reflex, orient2d, remove_holes, signed_area, triangulate_mono_vec_with_adjacency};
+
+/// A piece of an approximate convex decomposition, with its most concave notch
+struct ConcavePiece {
+    points: Vec<PointF64>,
+    /// Concavity of every vertex of the piece
+    concavities: Vec<f64>,
+    /// The reflex vertex of highest concavity, if any
+    notch: Option<usize>,
+}
+
+impl ConcavePiece {
+    fn new(points: Vec<PointF64>) -> Self {
+        let concavities = concavities(&points);
+        let len = points.len();
+        let notch = (0..len)
+            .filter(|&i| is_reflex(&points[(i+len-1) % len], &points[i], &points[(i+1) % len]))
+            .max_by(|&i, &j| concavities[i].partial_cmp(&concavities[j]).unwrap());
+        Self {
+            points,
+            concavities,
+            notch,
+        }
+    }
+
+    fn concavity(&self) -> f64 {
+        self.notch.map_or(0.0, |notch| self.concavities[notch])
+    }
+
+    /// Splits the piece by the best diagonal from its notch.
+    ///
+    /// A diagonal scores higher the shorter it is and the more concave the vertex it ends at,
+    /// so that a cut resolving two notches at once is preferred.
+    fn split(&self, notch: usize) -> Option<(Vec<PointF64>, Vec<PointF64>)> {
+        let len = self.points.len();
+        let score = |k: usize| (self.concavities[k] + 1.0) / distance(&self.points[notch], &self.points[k]);
+        let best = (0..len)
+            .filter(|&k| k != notch && k != (notch+1) % len && k != (notch+len-1) % len)
+            .filter(|&k| is_valid_diagonal(&self.points, notch, k))
+            .max_by(|&k1, &k2| score(k1).partial_cmp(&score(k2)).unwrap())?;
+
+        let (from, to) = if notch < best { (notch, best) } else { (best, notch) };
+        let piece_1 = self.points[from..=to].to_vec();
+        let piece_2 = self.points[to..].iter().chain(self.points[..=from].iter()).copied().collect();
+        Some((piece_1, piece_2))
+    }
+}
+
+/// Returns true iff the segment between points i and j is a diagonal of the ring:
+/// it leaves both ends into the interior, crosses no edge and passes through no vertex
+fn is_valid_diagonal(points: &[PointF64], i: usize, j: usize) -> bool {
+    let len = points.len();
+    let (p1, p2) = (points[i], points[j]);
+    if !is_in_cone(&points[(i+len-1) % len], &p1, &points[(i+1) % len], &p2) {
+        return false;
+    }
+    if !is_in_cone(&points[(j+len-1) % len], &p2, &points[(j+1) % len], &p1) {
+        return false;
+    }
+    // A diagonal through another vertex would cut off a degenerate piece
+    (0..len).all(|k| !intersects(&p1, &p2, &points[k], &points[(k+1) % len]) && !is_inside_segment(&p1, &p2, &points[k]))
+}
+
+/// Concavity of each vertex of a counter-clockwise ring: its distance to the
+/// convex hull edge bridging the pocket it lies in (0 for vertices on the hull)
+fn concavities(points: &[PointF64]) -> Vec<f64> {
+    let len = points.len();
+    // The hull vertices of a simple polygon are met in the same cyclic order along its boundary,
+    // so sorting their indices orders them along the ring
+    let hull = convex_hull_indices(points);
+
+    let mut concavities = vec![0.0; len];
+    for (h, &start) in hull.iter().enumerate() {
+        let end = hull[(h+1) % hull.len()];
+        let (p1, p2) = (points[start], points[end]);
+        let mut i = (start + 1) % len;
+        while i != end {
+            concavities[i] = distance_to_segment(&points[i], &p1, &p2);
+            i = (i + 1) % len;
+        }
+    }
+    concavities
+}
+
+/// Indices of the convex hull vertices in ascending order, by the monotone chain algorithm
+fn convex_hull_indices(points: &[PointF64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&i, &j| {
+        points[i].x.partial_cmp(&points[j].x).unwrap().then(points[i].y.partial_cmp(&points[j].y).unwrap())
+    });
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (points[o], points[a], points[b]);
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+
+    let mut hull: Vec<usize> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        for &i in order.iter() {
+            while hull.len() >= start + 2 && cross(hull[hull.len()-2], hull[hull.len()-1], i) <= 0.0 {
+                hull.pop();
+            }
+            hull.push(i);
+        }
+        hull.pop();
+        if pass == 0 {
+            order.reverse();
+        }
+    }
+    hull.sort_unstable();
+    hull.dedup();
+    hull
+}
+
+/// Decomposes polygons (holes allowed) into nearly convex pieces.
+///
+/// The concavity of a piece is the largest distance from one of its vertices to its convex hull.
+/// The most concave piece is repeatedly split at its deepest notch, until every piece has a
+/// concavity of at most `tolerance`, or there are `max_pieces` pieces.
+/// With a tolerance of 0, the result is an exact convex partition.
+pub fn approximate_convex_decomposition(polys: &[Polygon], tolerance: f64, max_pieces: usize) -> Result<Vec<Polygon>, String> {
+    let mut pieces = vec![];
+    for poly in remove_holes(polys)?.into_iter() {
+        let mut props = poly.props().clone();
+        props.set_orientation(Orientation::CounterClockwise);
+        pieces.push(ConcavePiece::new(props.points));
+    }
+
+    while pieces.len() < max_pieces {
+        let worst = (0..pieces.len())
+            .max_by(|&i, &j| pieces[i].concavity().partial_cmp(&pieces[j].concavity()).unwrap());
+        let worst = match worst {
+            Some(worst) if pieces[worst].concavity() > tolerance => worst,
+            _ => break,
+        };
+        let notch = pieces[worst].notch.unwrap();
+        let (piece_1, piece_2) = if let Some(split) = pieces[worst].split(notch) { split } else {
+            return Err("No diagonal found from a notch.".into());
+        };
+        pieces[worst] = ConcavePiece::new(piece_1);
+        pieces.push(ConcavePiece::new(piece_2));
+    }
+
+    Ok(pieces.into_iter().map(|piece| Polygon::from_points_and_is_hole(piece.points, false)).collect())
+}
+
+/// Constraints on the convex pieces handed to a physics engine
+#[derive(Clone, Copy, Debug)]
+pub struct ConvexPieceLimits {
+    /// Maximum number of vertices of a piece (e.g. 8 for Box2D)
+    pub max_vertices: usize,
+    /// Pieces smaller than this are merged into a neighbour where possible
+    pub min_area: f64,
+}
+
+impl Default for ConvexPieceLimits {
+    fn default() -> Self {
+        Self {
+            max_vertices: 8,
+            min_area: 0.0,
+        }
+    }
+}
+
+/// Partitions polygons (holes allowed) into convex pieces within the limits.
+///
+/// The polygons are triangulated and triangles below `min_area` are merged into a neighbour.
+/// Then the remaining diagonals are removed, longest first, as long as the merged piece stays
+/// convex and within `max_vertices` (Hertel-Mehlhorn). Slivers that cannot be merged
+/// within the limits are kept.
+///
+/// Pieces are wound counter-clockwise (by `Orientation`) without collinear vertices.
+pub fn convex_partition_limited(polys: Vec<Polygon>, limits: ConvexPieceLimits) -> Result<Vec<Polygon>, String> {
+    if limits.max_vertices < 3 {
+        return Err("A piece needs at least 3 vertices.".into());
+    }
+    let graph = triangulate_mono_vec_with_adjacency(polys)?;
+    let mut merger = PieceMerger::new(&graph.points, graph.pieces.clone(), limits.max_vertices);
+    // Slivers first, while their neighbours are still small enough to take them
+    merger.merge_slivers(limits.min_area);
+
+    let mut diagonals: Vec<(usize, usize)> = graph.adjacency.iter()
+        .flat_map(|edges| edges.iter().map(|edge| (edge.index_1, edge.index_2)))
+        .filter(|(index_1, index_2)| index_1 < index_2)
+        .collect();
+    diagonals.sort_by(|&(a1, b1), &(a2, b2)| merger.length(a2, b2).partial_cmp(&merger.length(a1, b1)).unwrap());
+    for (index_1, index_2) in diagonals.into_iter() {
+        merger.try_merge(index_1, index_2);
+    }
+
+    merger.merge_slivers(limits.min_area);
+
+    Ok(merger.pieces.iter().flatten().map(|piece| {
+        Polygon::from_points_and_is_hole(merger.corners(piece).into_iter().map(|i| graph.points[i]).collect(), false)
+    }).collect())
+}
+
+/// Counter-clockwise pieces, as vertex indices, that can be merged across shared edges
+struct PieceMerger<'a> {
+    points: &'a [PointF64],
+    pieces: Vec<Option<Vec<usize>>>,
+    /// The piece owning each directed edge
+    owners: HashMap<(usize, usize), usize>,
+    max_vertices: usize,
+}
+
+impl<'a> PieceMerger<'a> {
+    fn new(points: &'a [PointF64], pieces: Vec<Vec<usize>>, max_vertices: usize) -> Self {
+        let mut merger = Self {
+            points,
+            pieces: vec![],
+            owners: HashMap::new(),
+            max_vertices,
+        };
+        for mut piece in pieces.into_iter() {
+            if merger.area(&piece) < 0.0 {
+                piece.reverse();
+            }
+            merger.insert(merger.pieces.len(), piece);
+        }
+        merger
+    }
+
+    fn insert(&mut self, index: usize, piece: Vec<usize>) {
+        let len = piece.len();
+        for i in 0..len {
+            self.owners.insert((piece[i], piece[(i+1) % len]), index);
+        }
+        if index == self.pieces.len() {
+            self.pieces.push(Some(piece));
+        } else {
+            self.pieces[index] = Some(piece);
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Vec<usize> {
+        let piece = self.pieces[index].take().unwrap();
+        let len = piece.len();
+        for i in 0..len {
+            self.owners.remove(&(piece[i], piece[(i+1) % len]));
+        }
+        piece
+    }
+
+    /// Merges pieces below the area, smallest first, into the neighbour they share the longest edge with
+    fn merge_slivers(&mut self, min_area: f64) {
+        loop {
+            let mut slivers: Vec<(usize, f64)> = self.pieces.iter().enumerate()
+                .filter_map(|(i, piece)| piece.as_ref().map(|piece| (i, self.area(piece))))
+                .filter(|&(_, area)| area < min_area)
+                .collect();
+            slivers.sort_by(|(_, area1), (_, area2)| area1.partial_cmp(area2).unwrap());
+            let merged = slivers.into_iter().any(|(sliver, _)| {
+                let piece = self.pieces[sliver].clone().unwrap();
+                let len = piece.len();
+                let mut edges: Vec<(usize, usize)> = (0..len).map(|i| (piece[i], piece[(i+1) % len])).collect();
+                edges.sort_by(|&(a1, b1), &(a2, b2)| self.length(a2, b2).partial_cmp(&self.length(a1, b1)).unwrap());
+                edges.into_iter().any(|(a, b)| self.try_merge(a, b))
+            });
+            if !merged {
+                break;
+            }
+        }
+    }
+
+    fn length(&self, index_1: usize, index_2: usize) -> f64 {
+        distance(&self.points[index_1], &self.points[index_2])
+    }
+
+    fn area(&self, piece: &[usize]) -> f64 {
+        signed_area(&piece.iter().map(|&i| self.points[i]).collect::<Vec<PointF64>>())
+    }
+
+    /// Turn at each vertex of the piece, exact in sign; positive if convex
+    fn turn(&self, piece: &[usize], i: usize) -> f64 {
+        let len = piece.len();
+        orient2d(&self.points[piece[(i+len-1) % len]], &self.points[piece[i]], &self.points[piece[(i+1) % len]])
+    }
+
+    /// The vertices of the piece that are not collinear with their neighbours
+    fn corners(&self, piece: &[usize]) -> Vec<usize> {
+        (0..piece.len()).filter(|&i| self.turn(piece, i) != 0.0).map(|i| piece[i]).collect()
+    }
+
+    /// Merges the two pieces on either side of the edge, if the result is convex and within the vertex limit
+    fn try_merge(&mut self, index_1: usize, index_2: usize) -> bool {
+        let (piece_1, piece_2) = match (self.owners.get(&(index_1, index_2)), self.owners.get(&(index_2, index_1))) {
+            (Some(&piece_1), Some(&piece_2)) if piece_1 != piece_2 => (piece_1, piece_2),
+            _ => return false,
+        };
+        let rotated = |piece: &Vec<usize>, start: usize| {
+            let at = piece.iter().position(|&i| i == start).unwrap();
+            piece[at..].iter().chain(piece[..at].iter()).copied().collect::<Vec<usize>>()
+        };
+        // piece_1 runs index_1 -> index_2, so starting from index_2 it ends at index_1, where piece_2 takes over
+        let first = rotated(self.pieces[piece_1].as_ref().unwrap(), index_2);
+        let second = rotated(self.pieces[piece_2].as_ref().unwrap(), index_1);
+        let mut merged = first;
+        merged.extend_from_slice(&second[1..second.len()-1]);
+
+        let mut distinct = merged.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() != merged.len() {
+            return false;
+        }
+        if (0..merged.len()).any(|i| self.turn(&merged, i) < 0.0) {
+            return false;
+        }
+        if self.corners(&merged).len() > self.max_vertices {
+            return false;
+        }
+
+        self.remove(piece_1);
+        self.remove(piece_2);
+        self.insert(piece_1, merged);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{PieceShape, is_convex_ring, verify_partition};
+    use crate::polypartition::fixtures::{ring, room_with_pillar};
+
+    #[test]
+    fn approximate_convex_decomposition_noisy_square() {
+        // A square whose bottom edge has small dents, and a deep notch on top
+        let mut points = vec![];
+        for i in 0..10 {
+            points.push(PointF64::new(i as f64 * 10.0, 0.0));
+            points.push(PointF64::new(i as f64 * 10.0 + 5.0, if i & 1 == 1 { 0.5 } else { 0.0 }));
+        }
+        points.extend(vec![
+            PointF64::new(100.0, 0.0), PointF64::new(100.0, 100.0), PointF64::new(55.0, 100.0),
+            PointF64::new(50.0, 40.0), PointF64::new(45.0, 100.0), PointF64::new(0.0, 100.0),
+        ]);
+        let area = signed_area(&points);
+        let polys = vec![Polygon::from_points_and_is_hole(points, false)];
+
+        let pieces = approximate_convex_decomposition(&polys, 2.0, usize::MAX).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert!((pieces.iter().map(|piece| signed_area(&piece.props().points)).sum::<f64>() - area).abs() < 1e-7);
+
+        let exact = approximate_convex_decomposition(&polys, 0.0, usize::MAX).unwrap();
+        assert!(exact.len() > 2);
+        assert!(exact.iter().all(is_convex_ring));
+
+        let capped = approximate_convex_decomposition(&polys, 0.0, 3).unwrap();
+        assert_eq!(capped.len(), 3);
+    }
+
+    #[test]
+    fn approximate_convex_decomposition_with_hole() {
+        let polys = room_with_pillar();
+        let pieces = approximate_convex_decomposition(&polys, 0.0, usize::MAX).unwrap();
+        assert!(pieces.iter().all(is_convex_ring));
+        let area: f64 = pieces.iter().map(|piece| signed_area(&piece.props().points)).sum();
+        assert!((area - 9600.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn convex_partition_limited_vertex_cap() {
+        // A regular 16-gon needs 3 pieces of at most 8 vertices
+        let circle: Vec<PointF64> = (0..16).map(|i| {
+            let angle = i as f64 * std::f64::consts::PI / 8.0;
+            PointF64::new(100.0 * angle.cos(), 100.0 * angle.sin())
+        }).collect();
+        let area = signed_area(&circle);
+        let polys = vec![Polygon::from_points_and_is_hole(circle, false)];
+
+        let pieces = convex_partition_limited(polys.clone(), ConvexPieceLimits::default()).unwrap();
+        assert!(pieces.iter().all(|piece| piece.num_points() <= 8 && is_convex_ring(piece)));
+        assert!((pieces.iter().map(|piece| signed_area(&piece.props().points)).sum::<f64>() - area).abs() < 1e-6);
+
+        let unlimited = ConvexPieceLimits { max_vertices: usize::MAX, min_area: 0.0 };
+        assert_eq!(convex_partition_limited(polys.clone(), unlimited).unwrap().len(), 1);
+        let triangles = ConvexPieceLimits { max_vertices: 3, min_area: 0.0 };
+        assert_eq!(convex_partition_limited(polys, triangles).unwrap().len(), 14);
+    }
+
+    #[test]
+    fn convex_partition_limited_merges_slivers() {
+        // The triangle under the slightly raised top vertex is a sliver of area 0.5
+        let polys = vec![Polygon::from_points_and_is_hole(vec![
+            PointF64::new(0.0, 0.0), PointF64::new(100.0, 0.0), PointF64::new(100.0, 10.0),
+            PointF64::new(50.0, 10.01), PointF64::new(0.0, 10.0),
+        ], false)];
+        let limits = ConvexPieceLimits { max_vertices: 4, min_area: 1.0 };
+        let pieces = convex_partition_limited(polys, limits).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().all(|piece| piece.num_points() <= 4 && is_convex_ring(piece)));
+        assert!(pieces.iter().all(|piece| signed_area(&piece.props().points) >= 1.0));
+    }
+
+    #[test]
+    fn convex_partition_limited_small_coordinates() {
+        // An L shape at the scale of lon/lat parcels
+        let l_shape = vec![ring(&[(0.0, 0.0), (2e-4, 0.0), (2e-4, 1e-4), (1e-4, 1e-4), (1e-4, 2e-4), (0.0, 2e-4)], false)];
+        let pieces = convex_partition_limited(l_shape.clone(), ConvexPieceLimits::default()).unwrap();
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(verify_partition(&l_shape, &pieces, PieceShape::Convex), Ok(()));
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+
+use visioncortex::{BoundingRect, PointF64};
+
+use super::{BooleanOp, DualGraph, Polygon, PolygonInterface, boolean_op, incircle, orient2d};
+
+/// A Delaunay triangulation of a point set
+#[derive(Clone, Debug, Default)]
+pub struct Delaunay {
+    pub points: Vec<PointF64>,
+    /// Indices into `points`, wound counter-clockwise (by `Orientation`)
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// A triangle under construction; `neighbors[i]` is across the edge opposite `vertices[i]`
+#[derive(Clone)]
+struct BowyerWatsonTriangle {
+    vertices: [usize; 3],
+    neighbors: [Option<usize>; 3],
+    is_alive: bool,
+}
+
+impl Delaunay {
+    /// The triangles with their adjacency
+    pub fn to_dual_graph(&self) -> DualGraph {
+        DualGraph::from_pieces(self.points.clone(), self.triangles.iter().map(|triangle| triangle.to_vec()).collect())
+    }
+
+    /// The indices of the points joined to each point by a Delaunay edge
+    pub fn neighbors(&self) -> Vec<Vec<usize>> {
+        let mut neighbors = vec![vec![]; self.points.len()];
+        for triangle in self.triangles.iter() {
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i+1) % 3]);
+                if !neighbors[a].contains(&b) {
+                    neighbors[a].push(b);
+                    neighbors[b].push(a);
+                }
+            }
+        }
+        neighbors
+    }
+}
+
+/// The vertex at infinity, shared by the ghost triangles outside each edge of the convex hull
+const INFINITE: usize = usize::MAX;
+
+/// Triangulates a point set by Bowyer-Watson insertion, with exact `orient2d` and `incircle` predicates.
+///
+/// The convex hull is closed by ghost triangles joining each hull edge to a vertex at infinity,
+/// so points outside the hull are inserted like any other. Repeated points are triangulated once,
+/// at their first index. If all points are collinear, there are no triangles.
+pub fn delaunay_triangulation(points: &[PointF64]) -> Delaunay {
+    let mut delaunay = Delaunay { points: points.to_vec(), triangles: vec![] };
+
+    // Start from the first three points that are not collinear
+    let first = 0;
+    let second = (1..points.len()).find(|&i| points[i] != points[first]);
+    let third = second.and_then(|second| {
+        (second+1..points.len()).find(|&i| orient2d(&points[first], &points[second], &points[i]) != 0.0)
+    });
+    let (second, third) = match (second, third) {
+        (Some(second), Some(third)) => (second, third),
+        _ => return delaunay,
+    };
+    let (second, third) = if orient2d(&points[first], &points[second], &points[third]) > 0.0 {
+        (second, third)
+    } else {
+        (third, second)
+    };
+
+    let mut triangles = vec![BowyerWatsonTriangle { vertices: [first, second, third], neighbors: [None; 3], is_alive: true }];
+    for &(a, b) in [(first, second), (second, third), (third, first)].iter() {
+        triangles.push(BowyerWatsonTriangle { vertices: [b, a, INFINITE], neighbors: [None; 3], is_alive: true });
+    }
+    // Link the four triangles across their shared edges
+    for t in 0..4 {
+        for i in 0..3 {
+            let (a, b) = (triangles[t].vertices[(i+1) % 3], triangles[t].vertices[(i+2) % 3]);
+            triangles[t].neighbors[i] = (0..4).find(|&other| {
+                let vertices = triangles[other].vertices;
+                (0..3).any(|j| vertices[j] == b && vertices[(j+1) % 3] == a)
+            });
+        }
+    }
+
+    let mut last = 0;
+    let mut seen: HashMap<(u64, u64), usize> = HashMap::new();
+    for (index, p) in points.iter().enumerate() {
+        if seen.insert((p.x.to_bits(), p.y.to_bits()), index).is_some() || index == first || index == second || index == third {
+            continue;
+        }
+        last = insert_point(points, &mut triangles, index, last);
+    }
+
+    delaunay.triangles = triangles.into_iter()
+        .filter(|triangle| triangle.is_alive && !triangle.vertices.contains(&INFINITE))
+        .map(|triangle| triangle.vertices)
+        .collect();
+    delaunay
+}
+
+/// The finite edge of a ghost triangle, and the position of its infinite vertex
+fn ghost_edge(vertices: &[usize; 3]) -> Option<(usize, usize, usize)> {
+    let k = vertices.iter().position(|&v| v == INFINITE)?;
+    Some((vertices[(k+1) % 3], vertices[(k+2) % 3], k))
+}
+
+/// Whether p lies inside the circumcircle of the triangle. The "circle" of a ghost triangle is
+/// the open half-plane beyond its hull edge, plus the open edge itself.
+fn in_circumcircle(points: &[PointF64], vertices: &[usize; 3], p: &PointF64) -> bool {
+    match ghost_edge(vertices) {
+        Some((u, w, _)) => {
+            let (u, w) = (points[u], points[w]);
+            let orientation = orient2d(&u, &w, p);
+            orientation > 0.0 || (orientation == 0.0 && (*p - u).dot(w - u) > 0.0 && (*p - w).dot(u - w) > 0.0)
+        },
+        None => {
+            let [a, b, c] = *vertices;
+            incircle(&points[a], &points[b], &points[c], p) > 0.0
+        },
+    }
+}
+
+/// Inserts point `index`, starting the search from triangle `start`. Returns a triangle at the point.
+fn insert_point(points: &[PointF64], triangles: &mut Vec<BowyerWatsonTriangle>, index: usize, start: usize) -> usize {
+    let p = points[index];
+
+    // Walk towards the point, crossing any edge it lies beyond, until inside a triangle or beyond the hull
+    let mut current = start;
+    'walk: loop {
+        let vertices = triangles[current].vertices;
+        if let Some((u, w, k)) = ghost_edge(&vertices) {
+            if orient2d(&points[u], &points[w], &p) > 0.0 {
+                break;
+            }
+            current = triangles[current].neighbors[k].unwrap();
+            continue;
+        }
+        for i in 0..3 {
+            let (a, b) = (points[vertices[(i+1) % 3]], points[vertices[(i+2) % 3]]);
+            if orient2d(&a, &b, &p) < 0.0 {
+                current = triangles[current].neighbors[i].unwrap();
+                continue 'walk;
+            }
+        }
+        break;
+    }
+
+    // The cavity: every triangle whose circumcircle contains the point, which is connected
+    let mut cavity = vec![current];
+    let mut in_cavity: HashMap<usize, bool> = HashMap::new();
+    in_cavity.insert(current, true);
+    let mut stack = vec![current];
+    while let Some(t) = stack.pop() {
+        for neighbor in triangles[t].neighbors.iter().flatten() {
+            if in_cavity.contains_key(neighbor) {
+                continue;
+            }
+            let inside = in_circumcircle(points, &triangles[*neighbor].vertices, &p);
+            in_cavity.insert(*neighbor, inside);
+            if inside {
+                cavity.push(*neighbor);
+                stack.push(*neighbor);
+            }
+        }
+    }
+
+    // Fan the boundary of the cavity from the point
+    let mut by_start: HashMap<usize, usize> = HashMap::new();
+    let mut by_end: HashMap<usize, usize> = HashMap::new();
+    let mut created = vec![];
+    for &t in cavity.iter() {
+        triangles[t].is_alive = false;
+        for i in 0..3 {
+            let neighbor = triangles[t].neighbors[i];
+            if matches!(neighbor, Some(neighbor) if in_cavity.get(&neighbor) == Some(&true)) {
+                continue;
+            }
+            let (a, b) = (triangles[t].vertices[(i+1) % 3], triangles[t].vertices[(i+2) % 3]);
+            let new = triangles.len();
+            triangles.push(BowyerWatsonTriangle { vertices: [a, b, index], neighbors: [None, None, neighbor], is_alive: true });
+            if let Some(neighbor) = neighbor {
+                let slot = triangles[neighbor].neighbors.iter().position(|&n| n == Some(t)).unwrap();
+                triangles[neighbor].neighbors[slot] = Some(new);
+            }
+            by_start.insert(a, new);
+            by_end.insert(b, new);
+            created.push(new);
+        }
+    }
+    for &t in created.iter() {
+        let [a, b, _] = triangles[t].vertices;
+        triangles[t].neighbors[0] = by_start.get(&b).copied();
+        triangles[t].neighbors[1] = by_end.get(&a).copied();
+    }
+    created[0]
+}
+
+/// Clips a convex polygon to the side of the line through p with normal n where (x - p).n <= 0
+fn clip_half_plane(polygon: &[PointF64], p: &PointF64, n: &PointF64) -> Vec<PointF64> {
+    let side = |x: &PointF64| (*x - *p).dot(*n);
+    let mut clipped = vec![];
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i+1) % polygon.len()]);
+        let (side_a, side_b) = (side(&a), side(&b));
+        if side_a <= 0.0 {
+            clipped.push(a);
+        }
+        if (side_a < 0.0 && side_b > 0.0) || (side_a > 0.0 && side_b < 0.0) {
+            let t = side_a / (side_a - side_b);
+            clipped.push(PointF64::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t));
+        }
+    }
+    clipped
+}
+
+/// The Voronoi cell of each point, clipped to the rectangle.
+///
+/// Each cell is the rectangle cut by the perpendicular bisectors to the Delaunay neighbours
+/// of its point. Repeated points, and points whose cell misses the rectangle, get `None`.
+pub fn voronoi_cells(delaunay: &Delaunay, rect: &BoundingRect) -> Vec<Option<Polygon>> {
+    let (left, top, right, bottom) = (rect.left as f64, rect.top as f64, rect.right as f64, rect.bottom as f64);
+    let bounds = [PointF64::new(left, top), PointF64::new(right, top), PointF64::new(right, bottom), PointF64::new(left, bottom)];
+    voronoi_cells_within(delaunay, &bounds)
+}
+
+/// The Voronoi cell of each point, clipped to polygons (holes allowed).
+///
+/// A cell may fall apart into several pieces, with holes, by the convention of `boolean_op`.
+pub fn voronoi_cells_in_polygons(delaunay: &Delaunay, polys: &[Polygon]) -> Result<Vec<Vec<Polygon>>, &'static str> {
+    let points: Vec<PointF64> = polys.iter().flat_map(|poly| poly.props().points.iter().copied()).collect();
+    if points.is_empty() {
+        return Err("No polygons to clip to.");
+    }
+    let (mut min, mut max) = (points[0], points[0]);
+    for p in points.iter() {
+        min = PointF64::new(min.x.min(p.x), min.y.min(p.y));
+        max = PointF64::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    let bounds = [min, PointF64::new(max.x, min.y), max, PointF64::new(min.x, max.y)];
+
+    voronoi_cells_within(delaunay, &bounds).into_iter().map(|cell| match cell {
+        Some(cell) => boolean_op(&[cell], polys, BooleanOp::Intersection),
+        None => Ok(vec![]),
+    }).collect()
+}
+
+fn voronoi_cells_within(delaunay: &Delaunay, bounds: &[PointF64]) -> Vec<Option<Polygon>> {
+    let neighbors = delaunay.neighbors();
+    let mut is_triangulated = vec![false; delaunay.points.len()];
+    for &v in delaunay.triangles.iter().flatten() {
+        is_triangulated[v] = true;
+    }
+
+    (0..delaunay.points.len()).map(|i| {
+        if !is_triangulated[i] {
+            return None;
+        }
+        let site = delaunay.points[i];
+        let cell = neighbors[i].iter().fold(bounds.to_vec(), |cell, &j| {
+            let other = delaunay.points[j];
+            let midpoint = PointF64::new((site.x + other.x) / 2.0, (site.y + other.y) / 2.0);
+            clip_half_plane(&cell, &midpoint, &(other - site))
+        });
+        if cell.len() < 3 {
+            return None;
+        }
+        let mut cell = Polygon::from_points_and_is_hole(cell, false);
+        cell.props_mut().set_orientation(super::Orientation::CounterClockwise);
+        Some(cell)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{distance, signed_area};
+
+    fn grid_with_jitter() -> Vec<PointF64> {
+        let mut points = vec![];
+        for i in 0..8 {
+            for j in 0..8 {
+                let jitter = ((i * 7 + j * 13) % 5) as f64 * 0.1;
+                points.push(PointF64::new(i as f64 * 10.0 + jitter, j as f64 * 10.0 - jitter));
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn delaunay_empty_circumcircles() {
+        let points = grid_with_jitter();
+        let delaunay = delaunay_triangulation(&points);
+        // A triangulation of n points with h on its boundary has 2n - h - 2 triangles,
+        // and the boundary of a Delaunay triangulation is the convex hull
+        let graph = delaunay.to_dual_graph();
+        let boundary: Vec<(usize, usize)> = delaunay.triangles.iter().enumerate().flat_map(|(t, triangle)| {
+            let graph = &graph;
+            (0..3).map(move |i| (triangle[i], triangle[(i+1) % 3]))
+                .filter(move |&(a, b)| !graph.neighbors(t).iter().any(|edge| edge.index_1 == a && edge.index_2 == b))
+        }).collect();
+        assert_eq!(delaunay.triangles.len(), 2 * 64 - boundary.len() - 2);
+        for &(a, b) in boundary.iter() {
+            assert!(points.iter().all(|p| orient2d(&points[a], &points[b], p) >= 0.0));
+        }
+        for &[a, b, c] in delaunay.triangles.iter() {
+            assert!(orient2d(&points[a], &points[b], &points[c]) > 0.0);
+            for (d, p) in points.iter().enumerate() {
+                if d != a && d != b && d != c {
+                    assert!(incircle(&points[a], &points[b], &points[c], p) <= 0.0);
+                }
+            }
+        }
+
+        // Cocircular and repeated points
+        let square = vec![
+            PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0),
+            PointF64::new(0.0, 1.0), PointF64::new(1.0, 1.0),
+        ];
+        assert_eq!(delaunay_triangulation(&square).triangles.len(), 2);
+        assert!(delaunay_triangulation(&square[..2]).triangles.is_empty());
+        let line: Vec<PointF64> = (0..5).map(|i| PointF64::new(i as f64, 2.0 * i as f64)).collect();
+        assert!(delaunay_triangulation(&line).triangles.is_empty());
+    }
+
+    #[test]
+    fn voronoi_cells_tile_the_rectangle() {
+        let points = grid_with_jitter();
+        let delaunay = delaunay_triangulation(&points);
+        let rect = BoundingRect::new_x_y_w_h(-5, -5, 80, 80);
+        let cells = voronoi_cells(&delaunay, &rect);
+        let area: f64 = cells.iter().flatten().map(|cell| signed_area(&cell.props().points)).sum();
+        assert!((area - 6400.0).abs() < 1e-6);
+        // Every cell contains its own point
+        for (cell, site) in cells.iter().zip(points.iter()) {
+            let cell = cell.as_ref().unwrap();
+            let nearest = cell.props().points.iter().all(|corner| {
+                points.iter().all(|other| distance(corner, site) <= distance(corner, other) + 1e-7)
+            });
+            assert!(nearest);
+        }
+
+        let room = vec![
+            Polygon::from_points_and_is_hole(vec![
+                PointF64::new(0.0, 0.0), PointF64::new(70.0, 0.0), PointF64::new(70.0, 70.0), PointF64::new(0.0, 70.0),
+            ], false),
+            Polygon::from_points_and_is_hole(vec![
+                PointF64::new(20.0, 20.0), PointF64::new(20.0, 50.0), PointF64::new(50.0, 50.0), PointF64::new(50.0, 20.0),
+            ], true),
+        ];
+        let cells = voronoi_cells_in_polygons(&delaunay, &room).unwrap();
+        let area: f64 = cells.iter().flatten().map(|cell| signed_area(&cell.props().points)).sum();
+        assert!((area - 4000.0).abs() < 1e-6);
+    }
+}
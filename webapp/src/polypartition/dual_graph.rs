@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use visioncortex::PointF64;
+
+use super::{HalfEdgeMesh, Polygon};
+
+/// A partition result together with the adjacency between its pieces.
+///
+/// Pieces refer to their vertices by index, so shared diagonals are
+/// detected exactly instead of by comparing coordinates.
+#[derive(Clone, Debug, Default)]
+pub struct DualGraph {
+    /// The vertices shared by all pieces
+    pub points: Vec<PointF64>,
+    /// Each piece as indices into `points`, in the winding order of the output polygons
+    pub pieces: Vec<Vec<usize>>,
+    /// For each piece, the pieces sharing a diagonal with it
+    pub adjacency: Vec<Vec<DualEdge>>,
+}
+
+/// A diagonal shared by two pieces of a partition
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DualEdge {
+    /// Index of the neighbouring piece
+    pub piece: usize,
+    /// Indices (into `points`) of the endpoints of the diagonal,
+    /// in the winding order of the piece owning this edge
+    pub index_1: usize,
+    pub index_2: usize,
+}
+
+/// (piece, index_1, index_2)
+type EdgeOwner = (usize, usize, usize);
+
+impl DualGraph {
+    /// Builds the adjacency of the given pieces.
+    ///
+    /// An edge is considered a diagonal when exactly two pieces contain it.
+    pub fn from_pieces(points: Vec<PointF64>, pieces: Vec<Vec<usize>>) -> Self {
+        // Undirected edge -> (piece, directed edge) for every piece containing it
+        let mut edges: HashMap<(usize, usize), Vec<EdgeOwner>> = HashMap::new();
+        for (piece_index, piece) in pieces.iter().enumerate() {
+            let len = piece.len();
+            for curr in 0..len {
+                let (index_1, index_2) = (piece[curr], piece[(curr+1) % len]);
+                let key = if index_1 < index_2 { (index_1, index_2) } else { (index_2, index_1) };
+                edges.entry(key).or_default().push((piece_index, index_1, index_2));
+            }
+        }
+
+        let mut adjacency = vec![vec![]; pieces.len()];
+        for sharing in edges.values() {
+            if let [(piece_1, index_11, index_12), (piece_2, index_21, index_22)] = sharing[..] {
+                adjacency[piece_1].push(DualEdge { piece: piece_2, index_1: index_11, index_2: index_12 });
+                adjacency[piece_2].push(DualEdge { piece: piece_1, index_1: index_21, index_2: index_22 });
+            }
+        }
+        // HashMap iteration order is arbitrary, keep the output deterministic
+        for (piece, neighbors) in adjacency.iter_mut().enumerate() {
+            let piece = &pieces[piece];
+            neighbors.sort_by_key(|edge| piece.iter().position(|&i| i == edge.index_1));
+        }
+
+        Self {
+            points,
+            pieces,
+            adjacency,
+        }
+    }
+
+    /// The active faces of a mesh, with the edges each shares with another face.
+    ///
+    /// Pieces refer to the mesh vertices by index, so both sides of an edge are found from its twins.
+    pub fn from_mesh(mesh: &HalfEdgeMesh) -> Self {
+        let faces: Vec<usize> = mesh.active_faces().collect();
+        let mut piece_of = vec![0; mesh.faces.len()];
+        for (piece, &face) in faces.iter().enumerate() {
+            piece_of[face] = piece;
+        }
+        let adjacency = faces.iter().map(|&face| {
+            mesh.face_half_edges(face).into_iter().filter_map(|h| match mesh.half_edges[mesh.twin(h)].face {
+                Some(other) if other != face => Some(DualEdge {
+                    piece: piece_of[other],
+                    index_1: mesh.half_edges[h].origin,
+                    index_2: mesh.destination(h),
+                }),
+                _ => None,
+            }).collect()
+        }).collect();
+
+        Self {
+            points: mesh.vertices.iter().map(|vertex| vertex.p).collect(),
+            pieces: faces.iter().map(|&face| mesh.face_vertices(face)).collect(),
+            adjacency,
+        }
+    }
+
+    pub fn num_pieces(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// The pieces sharing a diagonal with the given piece
+    pub fn neighbors(&self, piece: usize) -> &[DualEdge] {
+        &self.adjacency[piece]
+    }
+
+    pub fn get_piece(&self, piece: usize) -> Polygon {
+        Polygon::from_points_and_is_hole(
+            self.pieces[piece].iter().map(|&i| self.points[i]).collect(),
+            false
+        )
+    }
+
+    /// The pieces as polygons, in the same order as `pieces`
+    pub fn polygons(&self) -> Vec<Polygon> {
+        (0..self.num_pieces()).map(|piece| self.get_piece(piece)).collect()
+    }
+
+    /// The endpoints of a shared diagonal
+    pub fn get_diagonal(&self, edge: &DualEdge) -> (PointF64, PointF64) {
+        (self.points[edge.index_1], self.points[edge.index_2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{triangulate_ec_vec_with_adjacency, triangulate_mono_vec_with_adjacency, triangulate_opt_vec_with_adjacency};
+    use crate::polypartition::fixtures::hexagon_with_hole;
+
+    fn num_diagonals(graph: &DualGraph) -> usize {
+        graph.adjacency.iter().map(|neighbors| neighbors.len()).sum::<usize>() / 2
+    }
+
+    #[test]
+    fn dual_graph_is_symmetric() {
+        let graph = triangulate_mono_vec_with_adjacency(hexagon_with_hole()).unwrap();
+        for (piece, neighbors) in graph.adjacency.iter().enumerate() {
+            for edge in neighbors.iter() {
+                assert!(graph.neighbors(edge.piece).iter().any(|other|
+                    other.piece == piece && other.index_1 == edge.index_2 && other.index_2 == edge.index_1
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn dual_graph_counts_diagonals() {
+        // A triangulation of n vertices and h holes has n + 3h - 3 diagonals
+        let graph = triangulate_mono_vec_with_adjacency(hexagon_with_hole()).unwrap();
+        assert_eq!(graph.num_pieces(), 10);
+        assert_eq!(num_diagonals(&graph), 10);
+
+        // The bridge between the hole and the outer ring is a diagonal as well
+        let graph = triangulate_ec_vec_with_adjacency(hexagon_with_hole()).unwrap();
+        assert_eq!(graph.num_pieces(), 10);
+        assert_eq!(graph.points.len(), 10);
+        assert_eq!(num_diagonals(&graph), 10);
+
+        let graph = triangulate_opt_vec_with_adjacency(vec![hexagon_with_hole()[0].clone()]).unwrap();
+        assert_eq!(graph.num_pieces(), 4);
+        assert_eq!(num_diagonals(&graph), 3);
+    }
+
+    #[test]
+    fn dual_graph_from_pieces() {
+        // A square fanned around its centre
+        let points = vec![
+            PointF64::new(0.0, 0.0), PointF64::new(2.0, 0.0), PointF64::new(2.0, 2.0), PointF64::new(0.0, 2.0),
+            PointF64::new(1.0, 1.0), PointF64::new(0.0, 0.0),
+        ];
+        let fan = vec![vec![0, 1, 4], vec![1, 2, 4], vec![2, 3, 4], vec![3, 0, 4]];
+        let graph = DualGraph::from_pieces(points.clone(), fan);
+        assert_eq!(num_diagonals(&graph), 4);
+        assert_eq!(graph.neighbors(0), [
+            DualEdge { piece: 1, index_1: 1, index_2: 4 },
+            DualEdge { piece: 3, index_1: 4, index_2: 0 },
+        ]);
+        assert_eq!(graph.get_diagonal(&graph.neighbors(2)[0]), (points[3], points[4]));
+
+        // Edges are matched by index, so a copy of a point does not join the pieces at it
+        let graph = DualGraph::from_pieces(points, vec![vec![0, 1, 2], vec![2, 3, 5]]);
+        assert_eq!(num_diagonals(&graph), 0);
+        let graph = DualGraph::from_pieces(graph.points, vec![vec![0, 1, 2], vec![2, 3, 0]]);
+        assert_eq!(num_diagonals(&graph), 1);
+    }
+}
@@ -13,4 +13,81 @@ pub enum VertexType {
     End = 2,
     Split = 3,
     Merge = 4,
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+/// How the rings of a polygon set determine its interior
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillRule {
+    /// Inside iff a ray from the point crosses the rings an odd number of times
+    EvenOdd,
+    /// Inside iff the rings wind around the point a non-zero number of times
+    NonZero,
+}
+
+/// How offset edges are joined around a corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JoinType {
+    /// Extend the edges until they meet, up to the miter limit
+    Miter,
+    /// Connect the edges with a circular arc
+    Round,
+    /// Cut the corner off at the offset distance
+    Square,
+}
+
+/// The role of a triangle in the chordal axis transform, by how many of its edges are diagonals
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TriangleKind {
+    /// No diagonal: the whole polygon is one triangle
+    Isolated,
+    /// One diagonal: the end of a branch
+    Terminal,
+    /// Two diagonals: part of a branch
+    Sleeve,
+    /// Three diagonals: where branches meet
+    Junction,
+}
+
+/// What every piece of a partition should be
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceShape {
+    Any,
+    Convex,
+    Triangle,
+}
+
+/// How `generate_polygons` shapes its outer ring
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PolygonGenerator {
+    /// Random radii at increasing angles around a centre
+    Star,
+    /// Random points joined in random order, then untangled by reversing crossing edges
+    TwoOpt,
+    /// Random points joined by recursively splitting them with random lines
+    SpacePartition,
+    /// A triangle with edges repeatedly split at displaced midpoints
+    FractalCoastline,
+    /// A row of long, thin teeth
+    Comb,
+    /// A thick arm winding several times around a centre
+    Spiral,
+    /// Columns of few distinct heights, so many vertices are collinear or share a y coordinate
+    Collinear,
+}
+
+/// A partition algorithm, as checked by `check_algorithm`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PartitionAlgorithm {
+    RemoveHoles,
+    EarClipping,
+    Monotone,
+    OptimalDp,
+}
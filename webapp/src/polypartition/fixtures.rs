@@ -0,0 +1,33 @@
+use visioncortex::PointF64;
+
+use super::Polygon;
+
+/// A ring through the given coordinates, in order
+pub fn ring(coords: &[(f64, f64)], is_hole: bool) -> Polygon {
+    Polygon::from_points_and_is_hole(coords.iter().map(|&(x, y)| PointF64::new(x, y)).collect(), is_hole)
+}
+
+/// The square from (x, y) to (x + size, y + size), wound clockwise (by `Orientation`) if it is a hole
+pub fn square(x: f64, y: f64, size: f64, is_hole: bool) -> Polygon {
+    let mut points = vec![
+        PointF64::new(x, y), PointF64::new(x + size, y),
+        PointF64::new(x + size, y + size), PointF64::new(x, y + size),
+    ];
+    if is_hole {
+        points.reverse();
+    }
+    Polygon::from_points_and_is_hole(points, is_hole)
+}
+
+/// A 100 by 100 room with a 20 by 20 pillar in the middle
+pub fn room_with_pillar() -> Vec<Polygon> {
+    vec![square(0.0, 0.0, 100.0, false), square(40.0, 40.0, 20.0, true)]
+}
+
+/// A hexagon with a quadrilateral hole
+pub fn hexagon_with_hole() -> Vec<Polygon> {
+    vec![
+        ring(&[(60.0, 40.0), (200.0, 40.0), (220.0, 110.0), (200.0, 180.0), (60.0, 180.0), (40.0, 110.0)], false),
+        ring(&[(110.0, 80.0), (90.0, 140.0), (140.0, 130.0), (170.0, 80.0)], true),
+    ]
+}
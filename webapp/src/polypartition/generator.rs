@@ -0,0 +1,317 @@
+use std::f64::consts::PI;
+
+use visioncortex::PointF64;
+
+use super::{Orientation, Polygon, PolygonGenerator, PolygonInterface, SplitMix64, orient2d, segments_cross, segments_meet, signed_area, triangulate_constrained};
+
+/// Options for `generate_polygons`
+#[derive(Clone, Debug)]
+pub struct GeneratorOptions {
+    pub generator: PolygonGenerator,
+    /// The number of vertices of the outer ring
+    pub num_vertices: usize,
+    /// The number of triangular holes
+    pub num_holes: usize,
+    pub seed: u64,
+    /// The polygons fit in the square from the origin to (size, size)
+    pub size: f64,
+}
+
+impl PolygonGenerator {
+    pub const ALL: [PolygonGenerator; 7] = [
+        PolygonGenerator::Star, PolygonGenerator::TwoOpt, PolygonGenerator::SpacePartition,
+        PolygonGenerator::FractalCoastline, PolygonGenerator::Comb, PolygonGenerator::Spiral,
+        PolygonGenerator::Collinear,
+    ];
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            generator: PolygonGenerator::Star,
+            num_vertices: 16,
+            num_holes: 0,
+            seed: 0,
+            size: 1000.0,
+        }
+    }
+}
+
+/// Generates a random simple polygon with random holes, reproducibly from the seed.
+///
+/// Returns the outer ring (counter-clockwise by `Orientation`), followed by the holes (clockwise).
+/// Each hole is a triangle inside a triangle of the polygon, so the holes never touch each other
+/// or the outer ring. `Comb`, `Spiral` and `Collinear` need at least 4 vertices, the others 3.
+pub fn generate_polygons(options: &GeneratorOptions) -> Result<Vec<Polygon>, String> {
+    let mut rng = SplitMix64::new(options.seed);
+    let n = options.num_vertices;
+    let min_vertices = match options.generator {
+        PolygonGenerator::Comb | PolygonGenerator::Spiral | PolygonGenerator::Collinear => 4,
+        _ => 3,
+    };
+    if n < min_vertices {
+        return Err("Too few vertices for the generator!".into());
+    }
+
+    let ring = match options.generator {
+        PolygonGenerator::Star => star(&mut rng, n),
+        PolygonGenerator::TwoOpt => two_opt(&mut rng, n),
+        PolygonGenerator::SpacePartition => space_partition(&mut rng, n),
+        PolygonGenerator::FractalCoastline => fractal_coastline(&mut rng, n),
+        PolygonGenerator::Comb => comb(&mut rng, n),
+        PolygonGenerator::Spiral => spiral(&mut rng, n),
+        PolygonGenerator::Collinear => collinear(&mut rng, n),
+    };
+    let mut outer = Polygon::from_points_and_is_hole(fit_to_size(&ring, options.size), false);
+    outer.props_mut().set_orientation(Orientation::CounterClockwise);
+
+    let mut polys = vec![outer];
+    for _ in 0..options.num_holes {
+        let hole = random_hole(&mut rng, &polys)?;
+        polys.push(hole);
+    }
+    Ok(polys)
+}
+
+/// Scales and translates the points to fit in the square from the origin to (size, size)
+fn fit_to_size(points: &[PointF64], size: f64) -> Vec<PointF64> {
+    let (mut min, mut max) = (points[0], points[0]);
+    for p in points.iter() {
+        min = PointF64::new(min.x.min(p.x), min.y.min(p.y));
+        max = PointF64::new(max.x.max(p.x), max.y.max(p.y));
+    }
+    let scale = size / (max.x - min.x).max(max.y - min.y);
+    points.iter().map(|&p| PointF64::new((p.x - min.x) * scale, (p.y - min.y) * scale)).collect()
+}
+
+/// A triangle half the size of a random triangle of the polygons, picked by area
+fn random_hole(rng: &mut SplitMix64, polys: &[Polygon]) -> Result<Polygon, String> {
+    let triangles = triangulate_constrained(polys, &[], &[])?;
+    let areas: Vec<f64> = triangles.iter().map(|triangle| signed_area(&triangle.props().points)).collect();
+    let mut target = rng.range(0.0, areas.iter().sum());
+    let picked = areas.iter().position(|&area| {
+        target -= area;
+        target < 0.0
+    }).unwrap_or(triangles.len() - 1);
+
+    let points = &triangles[picked].props().points;
+    let centroid = PointF64::new(
+        (points[0].x + points[1].x + points[2].x) / 3.0,
+        (points[0].y + points[1].y + points[2].y) / 3.0,
+    );
+    let hole_points = points.iter().rev().map(|&p| PointF64::new((centroid.x + p.x) / 2.0, (centroid.y + p.y) / 2.0)).collect();
+    Ok(Polygon::from_points_and_is_hole(hole_points, true))
+}
+
+fn random_points(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    (0..n).map(|_| PointF64::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0))).collect()
+}
+
+fn star(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    (0..n).map(|i| {
+        let angle = (i as f64 + rng.range(0.0, 0.8)) / n as f64 * 2.0 * PI;
+        let radius = rng.range(0.2, 1.0);
+        PointF64::new(radius * angle.cos(), radius * angle.sin())
+    }).collect()
+}
+
+/// Reversing the path between two crossing edges uncrosses them and shortens the ring,
+/// so this ends with no crossings
+fn two_opt(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let mut points = random_points(rng, n);
+    rng.shuffle(&mut points);
+    let mut is_tangled = true;
+    while is_tangled {
+        is_tangled = false;
+        for i in 0..n {
+            for j in i+2..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                if segments_cross(&points[i], &points[i+1], &points[j], &points[(j+1) % n]) {
+                    points[i+1..=j].reverse();
+                    is_tangled = true;
+                }
+            }
+        }
+    }
+    points
+}
+
+/// The points are split by the line through two of them into two chains. Each chain is built by
+/// picking a point, and splitting the rest by a random line through it that separates the chain's ends,
+/// so every sub-chain stays in its own convex region.
+fn space_partition(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let points = random_points(rng, n);
+    let (p, q) = (points[0], points[1]);
+    let (left, right): (Vec<PointF64>, Vec<PointF64>) = points[2..].iter().partition(|x| orient2d(&p, &q, x) > 0.0);
+    let mut ring = vec![p];
+    ring.extend(partition_chain(rng, &p, &q, right));
+    ring.push(q);
+    ring.extend(partition_chain(rng, &q, &p, left));
+    ring
+}
+
+/// The points in order along a chain from p to q
+fn partition_chain(rng: &mut SplitMix64, p: &PointF64, q: &PointF64, mut points: Vec<PointF64>) -> Vec<PointF64> {
+    if points.is_empty() {
+        return points;
+    }
+    let r = points.swap_remove(rng.index(points.len()));
+    let t = rng.range(0.2, 0.8);
+    let s = PointF64::new(p.x + (q.x - p.x) * t, p.y + (q.y - p.y) * t);
+    let p_side = orient2d(&r, &s, p) > 0.0;
+    let (near_p, near_q): (Vec<PointF64>, Vec<PointF64>) = points.into_iter().partition(|x| (orient2d(&r, &s, x) > 0.0) == p_side);
+    let mut chain = partition_chain(rng, p, &r, near_p);
+    chain.push(r);
+    chain.extend(partition_chain(rng, &r, q, near_q));
+    chain
+}
+
+/// Splits the longest edge at its midpoint, displaced sideways if that keeps the ring simple
+fn fractal_coastline(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let rotation = rng.range(0.0, 2.0 * PI);
+    let mut ring: Vec<PointF64> = (0..3).map(|i| {
+        let angle = rotation + i as f64 * 2.0 * PI / 3.0;
+        PointF64::new(angle.cos(), angle.sin())
+    }).collect();
+    while ring.len() < n {
+        let len = ring.len();
+        let longest = (0..len).max_by(|&i, &j| {
+            let length = |k: usize| (ring[(k+1) % len] - ring[k]).norm();
+            length(i).partial_cmp(&length(j)).unwrap()
+        }).unwrap();
+        let (a, b) = (ring[longest], ring[(longest+1) % len]);
+        let midpoint = PointF64::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+        let normal = PointF64::new(b.y - a.y, a.x - b.x);
+        let mut displacement = rng.range(-0.35, 0.35);
+        let mut c = midpoint;
+        for _ in 0..4 {
+            let candidate = PointF64::new(midpoint.x + normal.x * displacement, midpoint.y + normal.y * displacement);
+            let is_simple = (0..len).filter(|&k| k != longest).all(|k| {
+                let (u, v) = (ring[k], ring[(k+1) % len]);
+                !segments_meet(&a, &candidate, &u, &v) && !segments_meet(&candidate, &b, &u, &v)
+            });
+            if is_simple {
+                c = candidate;
+                break;
+            }
+            displacement /= 2.0;
+        }
+        ring.insert(longest + 1, c);
+    }
+    ring
+}
+
+/// 4 vertices per tooth, with any left over spread along the base
+fn comb(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let num_teeth = n / 4;
+    let mut xs = vec![0.0];
+    for _ in 0..2 * num_teeth - 1 {
+        let last = xs[xs.len() - 1];
+        xs.push(last + rng.range(0.5, 1.5));
+    }
+    let width = xs[xs.len() - 1];
+    let base = rng.range(0.5, 1.5);
+
+    let mut ring = vec![PointF64::new(0.0, 0.0)];
+    let num_extra = n - 4 * num_teeth;
+    for i in 1..=num_extra {
+        ring.push(PointF64::new(width * i as f64 / (num_extra + 1) as f64, 0.0));
+    }
+    ring.push(PointF64::new(width, 0.0));
+    for tooth in (0..num_teeth).rev() {
+        let (left, right) = (xs[2 * tooth], xs[2 * tooth + 1]);
+        let length = base + rng.range(2.0, 10.0);
+        ring.push(PointF64::new(right, length));
+        ring.push(PointF64::new(left, length));
+        if tooth > 0 {
+            ring.push(PointF64::new(left, base));
+            ring.push(PointF64::new(xs[2 * tooth - 1], base));
+        }
+    }
+    ring
+}
+
+/// The arm is one unit thick, with a gap of one unit between turns. The angular step shrinks as
+/// the spiral grows, so the edges stay much shorter than the gap.
+fn spiral(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let growth = 2.0 / (2.0 * PI);
+    let mut angles = vec![0.0];
+    for _ in 1..n / 2 {
+        let angle = angles[angles.len() - 1];
+        let radius = 2.0 + growth * angle;
+        angles.push(angle + (PI / 6.0).min(0.5 / radius) * rng.range(0.7, 1.0));
+    }
+    let at = |angle: f64, radius: f64| PointF64::new(radius * angle.cos(), radius * angle.sin());
+
+    let mut ring: Vec<PointF64> = angles.iter().map(|&angle| at(angle, 2.0 + growth * angle)).collect();
+    let last = angles[angles.len() - 1];
+    if n & 1 == 1 {
+        ring.push(at(last, 1.5 + growth * last));
+    }
+    ring.extend(angles.iter().rev().map(|&angle| at(angle, 1.0 + growth * angle)));
+    ring
+}
+
+/// A histogram of columns with 3 possible heights. Neighbouring columns of the same height
+/// leave a collinear vertex between them.
+fn collinear(rng: &mut SplitMix64, n: usize) -> Vec<PointF64> {
+    let mut height = (1 + rng.index(3)) as f64;
+    let mut top = vec![PointF64::new(0.0, height)];
+    let mut x = 0.0;
+    loop {
+        x += rng.range(0.5, 1.5);
+        top.push(PointF64::new(x, height));
+        if top.len() + 2 == n {
+            break;
+        }
+        // A step takes 2 vertices, so the last column cannot have one
+        let next_height = if top.len() + 3 == n {height} else {(1 + rng.index(3)) as f64};
+        if next_height != height {
+            top.push(PointF64::new(x, next_height));
+            height = next_height;
+        }
+    }
+    let mut ring = vec![PointF64::new(0.0, 0.0), PointF64::new(x, 0.0)];
+    ring.extend(top.into_iter().rev());
+    ring
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{is_valid_polygon_set, polygons_area, remove_holes, triangulate_ec_vec, triangulate_opt_vec};
+
+    #[test]
+    fn generator_makes_simple_polygons() {
+        for &generator in PolygonGenerator::ALL.iter() {
+            for &num_vertices in [4, 7, 13, 40].iter() {
+                for seed in 0..4 {
+                    let num_holes = seed as usize;
+                    let options = GeneratorOptions { generator, num_vertices, num_holes, seed, ..Default::default() };
+                    let polys = generate_polygons(&options).unwrap();
+                    let context = format!("{:?} with {} vertices, seed {}", generator, num_vertices, seed);
+                    assert_eq!(polys.len(), 1 + num_holes, "{}", context);
+                    assert_eq!(polys[0].num_points(), num_vertices, "{}", context);
+                    assert!(is_valid_polygon_set(&polys), "{}", context);
+                    assert!(polys[0].props().bounding_rect_f64().width().max(polys[0].props().bounding_rect_f64().height()) <= 1000.0 + 1e-9);
+                    assert!(polygons_area(&polys) > 0.0);
+
+                    // A polygon with n vertices and h holes has n + 2h - 2 triangles
+                    let total_vertices = num_vertices + 3 * num_holes;
+                    let expected = total_vertices + 2 * num_holes - 2;
+                    assert_eq!(triangulate_constrained(&polys, &[], &[]).unwrap().len(), expected, "{}", context);
+                    let triangles = triangulate_ec_vec(remove_holes(&polys).unwrap()).unwrap();
+                    assert_eq!(triangles.len(), expected, "{}", context);
+                    assert_eq!(triangulate_opt_vec(polys[..1].to_vec()).unwrap().len(), num_vertices - 2, "{}", context);
+
+                    // Reproducible from the seed
+                    let again = generate_polygons(&options).unwrap();
+                    assert!(polys.iter().zip(again.iter()).all(|(a, b)| a.props().points == b.props().points));
+                }
+            }
+        }
+        assert!(generate_polygons(&GeneratorOptions { generator: PolygonGenerator::Comb, num_vertices: 3, ..Default::default() }).is_err());
+    }
+}
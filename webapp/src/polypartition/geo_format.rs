@@ -0,0 +1,428 @@
+use serde_json::Value;
+use visioncortex::PointF64;
+
+use super::{FillRule, Orientation, ParseError, Polygon, PolygonInterface};
+
+/// Deeper nesting of WKT geometry collections is rejected, to bound the recursion
+const MAX_WKT_DEPTH: usize = 256;
+
+/// Reads polygons from GeoJSON: a `Polygon` or `MultiPolygon` geometry, or a `Feature`, `FeatureCollection`
+/// or `GeometryCollection` of them. The first ring of each polygon is a solid and the others are its holes.
+///
+/// Rings may be closed or not and wound either way; solids come out counter-clockwise (by `Orientation`)
+/// and holes clockwise. Coordinates after x and y (such as altitude) are ignored.
+pub fn parse_geojson(text: &str) -> Result<Vec<Polygon>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|error| {
+        // Keep the message in the format of `ParseError`, without the position serde_json appends
+        let message = error.to_string();
+        let message = message.rsplit_once(" at line ").map_or(message.as_str(), |(message, _)| message);
+        ParseError { line: error.line(), column: error.column(), message: message.to_string() }.to_string()
+    })?;
+    let mut polygons = vec![];
+    read_geojson_object(&value, &mut polygons)?;
+    Ok(polygons)
+}
+
+/// Writes the polygons as one GeoJSON geometry: a `Polygon` if there is one solid, else a `MultiPolygon`.
+/// Each hole goes with the smallest solid around it, and every ring is closed, following RFC 7946.
+pub fn write_geojson(polygons: &[Polygon]) -> Result<String, String> {
+    let groups = group_holes(polygons)?;
+    if groups.len() == 1 {
+        return Ok(format!("{{\"type\":\"Polygon\",\"coordinates\":{}}}", geojson_rings(&groups[0])));
+    }
+    let polygons: Vec<String> = groups.iter().map(|rings| geojson_rings(rings)).collect();
+    Ok(format!("{{\"type\":\"MultiPolygon\",\"coordinates\":[{}]}}", polygons.join(",")))
+}
+
+/// Writes the polygons, such as the pieces of a partition, as a GeoJSON `FeatureCollection`
+/// with one `Polygon` feature per solid and its holes. Each feature has its index as a property.
+pub fn write_geojson_feature_collection(polygons: &[Polygon]) -> Result<String, String> {
+    let features: Vec<String> = group_holes(polygons)?.iter().enumerate().map(|(index, rings)| format!(
+        "{{\"type\":\"Feature\",\"properties\":{{\"index\":{}}},\"geometry\":{{\"type\":\"Polygon\",\"coordinates\":{}}}}}",
+        index, geojson_rings(rings)
+    )).collect();
+    Ok(format!("{{\"type\":\"FeatureCollection\",\"features\":[{}]}}", features.join(",")))
+}
+
+/// Reads polygons from WKT: `POLYGON`, `MULTIPOLYGON` or a `GEOMETRYCOLLECTION` of them, in any case.
+/// Rings are handled as by `parse_geojson`, and Z and M coordinates are ignored.
+pub fn parse_wkt(text: &str) -> Result<Vec<Polygon>, String> {
+    let mut reader = WktReader::new(text);
+    let mut polygons = vec![];
+    reader.read_geometry(&mut polygons).map_err(|error| error.to_string())?;
+    if let Some(token) = reader.tokens.get(reader.next) {
+        return Err(reader.error_at(token, "Unexpected content after the geometry").to_string());
+    }
+    Ok(polygons)
+}
+
+/// Writes the polygons as WKT: a `POLYGON` if there is one solid, else a `MULTIPOLYGON`.
+/// Holes are grouped and rings closed as by `write_geojson`.
+pub fn write_wkt(polygons: &[Polygon]) -> Result<String, String> {
+    let groups = group_holes(polygons)?;
+    if groups.is_empty() {
+        return Ok("MULTIPOLYGON EMPTY".into());
+    }
+    if groups.len() == 1 {
+        return Ok(format!("POLYGON {}", wkt_rings(&groups[0])));
+    }
+    let polygons: Vec<String> = groups.iter().map(|rings| wkt_rings(rings)).collect();
+    Ok(format!("MULTIPOLYGON ({})", polygons.join(", ")))
+}
+
+/// Adds the rings of one polygon, the first being its solid and the others its holes
+fn push_rings(rings: Vec<Vec<PointF64>>, polygons: &mut Vec<Polygon>) -> Result<(), String> {
+    for (i, mut points) in rings.into_iter().enumerate() {
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return Err("A ring needs at least 3 distinct points".into());
+        }
+        let is_hole = i > 0;
+        let mut polygon = Polygon::from_points_and_is_hole(points, is_hole);
+        if polygon.props().signed_area() == 0.0 {
+            return Err("A ring has no area".into());
+        }
+        polygon.props_mut().set_orientation(if is_hole { Orientation::Clockwise } else { Orientation::CounterClockwise });
+        polygons.push(polygon);
+    }
+    Ok(())
+}
+
+/// Each solid followed by the holes directly inside it, in the order of the solids
+fn group_holes(polygons: &[Polygon]) -> Result<Vec<Vec<Polygon>>, String> {
+    let solids: Vec<usize> = (0..polygons.len()).filter(|&i| !polygons[i].is_hole()).collect();
+    let mut groups: Vec<Vec<Polygon>> = solids.iter().map(|&i| {
+        let mut solid = polygons[i].clone();
+        solid.props_mut().set_orientation(Orientation::CounterClockwise);
+        vec![solid]
+    }).collect();
+    for hole in polygons.iter().filter(|polygon| polygon.is_hole()) {
+        let p = hole.get_point(0);
+        let group = (0..solids.len())
+            .filter(|&group| polygons[solids[group]].props().contains_point(&p, FillRule::NonZero))
+            .min_by(|&a, &b| polygons[solids[a]].props().area().total_cmp(&polygons[solids[b]].props().area()))
+            .ok_or("A hole is not inside any solid!")?;
+        let mut hole = hole.clone();
+        hole.props_mut().set_orientation(Orientation::Clockwise);
+        groups[group].push(hole);
+    }
+    Ok(groups)
+}
+
+/// The points of the ring, closed
+fn closed_ring(polygon: &Polygon) -> impl Iterator<Item = &PointF64> {
+    let points = &polygon.props().points;
+    points.iter().chain(points.first())
+}
+
+fn geojson_rings(rings: &[Polygon]) -> String {
+    let rings: Vec<String> = rings.iter().map(|ring| {
+        let points: Vec<String> = closed_ring(ring).map(|p| format!("[{},{}]", p.x, p.y)).collect();
+        format!("[{}]", points.join(","))
+    }).collect();
+    format!("[{}]", rings.join(","))
+}
+
+fn wkt_rings(rings: &[Polygon]) -> String {
+    let rings: Vec<String> = rings.iter().map(|ring| {
+        let points: Vec<String> = closed_ring(ring).map(|p| format!("{} {}", p.x, p.y)).collect();
+        format!("({})", points.join(", "))
+    }).collect();
+    format!("({})", rings.join(", "))
+}
+
+fn read_geojson_object(value: &Value, polygons: &mut Vec<Polygon>) -> Result<(), String> {
+    let kind = value.get("type").and_then(|kind| kind.as_str()).ok_or("Expected a GeoJSON object with a type")?;
+    let member = |key: &str| -> Result<&Vec<Value>, String> {
+        value.get(key).and_then(|member| member.as_array()).ok_or_else(|| format!("Expected an array of {} in the {}", key, kind))
+    };
+    match kind {
+        "Polygon" => read_geojson_rings(member("coordinates")?, polygons),
+        "MultiPolygon" => member("coordinates")?.iter().try_for_each(|rings| {
+            read_geojson_rings(rings.as_array().ok_or("Expected an array of rings in the MultiPolygon")?, polygons)
+        }),
+        "GeometryCollection" => member("geometries")?.iter().try_for_each(|geometry| read_geojson_object(geometry, polygons)),
+        "FeatureCollection" => member("features")?.iter().try_for_each(|feature| read_geojson_object(feature, polygons)),
+        "Feature" => match value.get("geometry") {
+            None | Some(Value::Null) => Ok(()),
+            Some(geometry) => read_geojson_object(geometry, polygons),
+        },
+        _ => Err(format!("Unsupported GeoJSON type '{}'", kind)),
+    }
+}
+
+fn read_geojson_rings(rings: &[Value], polygons: &mut Vec<Polygon>) -> Result<(), String> {
+    let rings = rings.iter().map(|ring| {
+        ring.as_array().ok_or("Expected a ring as an array of positions")?.iter().map(|position| {
+            match position.as_array().map(|coords| coords.iter().map(|coord| coord.as_f64()).collect::<Vec<_>>()) {
+                Some(coords) if coords.len() >= 2 && coords.iter().all(|coord| coord.is_some()) =>
+                    Ok(PointF64::new(coords[0].unwrap(), coords[1].unwrap())),
+                _ => Err("Expected a position as an array of at least 2 numbers".to_string()),
+            }
+        }).collect::<Result<Vec<_>, String>>()
+    }).collect::<Result<Vec<_>, String>>()?;
+    push_rings(rings, polygons)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum WktTokenKind {
+    Word(String),
+    Number(f64),
+    Open,
+    Close,
+    Comma,
+}
+
+#[derive(Clone, Debug)]
+struct WktToken {
+    kind: WktTokenKind,
+    line: usize,
+    column: usize,
+}
+
+struct WktReader {
+    tokens: Vec<WktToken>,
+    next: usize,
+    /// How many geometry collections the reader is in
+    depth: usize,
+    /// Where the text ends
+    end: (usize, usize),
+    /// The first character that is not part of any token
+    invalid: Option<(usize, usize)>,
+}
+
+impl WktReader {
+    fn new(text: &str) -> Self {
+        let mut tokens = vec![];
+        let mut invalid = None;
+        let (mut line, mut column) = (1, 1);
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let start = (line, column);
+            let mut literal = String::new();
+            let kind = match c {
+                '(' | ')' | ',' => {
+                    chars.next();
+                    column += 1;
+                    Some(match c { '(' => WktTokenKind::Open, ')' => WktTokenKind::Close, _ => WktTokenKind::Comma })
+                },
+                c if c.is_whitespace() => {
+                    chars.next();
+                    if c == '\n' {
+                        line += 1;
+                        column = 1;
+                    } else {
+                        column += 1;
+                    }
+                    continue;
+                },
+                c if c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.') => {
+                    while let Some(&c) = chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+                            break;
+                        }
+                        literal.push(c);
+                        chars.next();
+                        column += 1;
+                    }
+                    if literal.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                        Some(WktTokenKind::Word(literal.to_ascii_uppercase()))
+                    } else {
+                        literal.parse::<f64>().ok().filter(|n| n.is_finite()).map(WktTokenKind::Number)
+                    }
+                },
+                _ => None,
+            };
+            match kind {
+                Some(kind) => tokens.push(WktToken { kind, line: start.0, column: start.1 }),
+                None => {
+                    invalid = Some(start);
+                    break;
+                },
+            }
+        }
+        Self { tokens, next: 0, depth: 0, end: (line, column), invalid }
+    }
+
+    fn error_at(&self, token: &WktToken, message: &str) -> ParseError {
+        ParseError { line: token.line, column: token.column, message: message.to_string() }
+    }
+
+    /// The next token, or an error where the text stops being WKT
+    fn next_token(&mut self, expected: &str) -> Result<WktToken, ParseError> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token.ok_or_else(|| match self.invalid {
+            Some((line, column)) => ParseError { line, column, message: format!("Expected {}, found an invalid character", expected) },
+            None => ParseError { line: self.end.0, column: self.end.1, message: format!("Expected {}, found the end of the input", expected) },
+        })
+    }
+
+    /// Reads "(" then items separated by ",", up to ")". Returns false for EMPTY instead.
+    fn read_list(&mut self, mut read_item: impl FnMut(&mut Self) -> Result<(), ParseError>) -> Result<bool, ParseError> {
+        let token = self.next_token("'(' or EMPTY")?;
+        match token.kind {
+            WktTokenKind::Word(ref word) if word == "EMPTY" => return Ok(false),
+            WktTokenKind::Open => {},
+            _ => return Err(self.error_at(&token, "Expected '(' or EMPTY")),
+        }
+        loop {
+            read_item(self)?;
+            let token = self.next_token("',' or ')'")?;
+            match token.kind {
+                WktTokenKind::Comma => {},
+                WktTokenKind::Close => return Ok(true),
+                _ => return Err(self.error_at(&token, "Expected ',' or ')'")),
+            }
+        }
+    }
+
+    fn read_geometry(&mut self, polygons: &mut Vec<Polygon>) -> Result<(), ParseError> {
+        let token = self.next_token("a geometry type")?;
+        let kind = match &token.kind {
+            WktTokenKind::Word(word) => word.clone(),
+            _ => return Err(self.error_at(&token, "Expected a geometry type")),
+        };
+        // Z and M coordinates are read and dropped
+        if let Some(WktToken { kind: WktTokenKind::Word(word), .. }) = self.tokens.get(self.next) {
+            if matches!(word.as_str(), "Z" | "M" | "ZM") {
+                self.next += 1;
+            }
+        }
+        match kind.as_str() {
+            "POLYGON" => self.read_polygon(polygons),
+            "MULTIPOLYGON" => self.read_list(|reader| reader.read_polygon(polygons)).map(|_| ()),
+            "GEOMETRYCOLLECTION" if self.depth >= MAX_WKT_DEPTH => Err(self.error_at(&token, "Geometry collections are nested too deeply")),
+            "GEOMETRYCOLLECTION" => {
+                self.depth += 1;
+                let result = self.read_list(|reader| reader.read_geometry(polygons)).map(|_| ());
+                self.depth -= 1;
+                result
+            },
+            _ => Err(self.error_at(&token, &format!("Unsupported geometry type {}", kind))),
+        }
+    }
+
+    fn read_polygon(&mut self, polygons: &mut Vec<Polygon>) -> Result<(), ParseError> {
+        let start = self.tokens.get(self.next).cloned();
+        let mut rings = vec![];
+        self.read_list(|reader| {
+            let mut ring = vec![];
+            reader.read_list(|reader| {
+                ring.push(reader.read_point()?);
+                Ok(())
+            })?;
+            rings.push(ring);
+            Ok(())
+        })?;
+        push_rings(rings, polygons).map_err(|message| match start {
+            Some(token) => self.error_at(&token, &message),
+            None => ParseError { line: self.end.0, column: self.end.1, message },
+        })
+    }
+
+    fn read_point(&mut self) -> Result<PointF64, ParseError> {
+        let mut coords = vec![];
+        while let Some(WktToken { kind: WktTokenKind::Number(n), .. }) = self.tokens.get(self.next) {
+            coords.push(*n);
+            self.next += 1;
+        }
+        if coords.len() < 2 || coords.len() > 4 {
+            let token = self.next_token("the coordinates of a point")?;
+            return Err(self.error_at(&token, "Expected 2 to 4 coordinates for a point"));
+        }
+        Ok(PointF64::new(coords[0], coords[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{GeneratorOptions, PolygonGenerator, generate_polygons, polygons_area, triangulate_mono_vec};
+    use crate::polypartition::fixtures::ring;
+
+    fn assert_same_polygons(a: &[Polygon], b: &[Polygon]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.props().points, b.props().points);
+            assert_eq!(a.is_hole(), b.is_hole());
+        }
+    }
+
+    #[test]
+    fn geo_format_reads_geojson() {
+        // From RFC 7946, with the hole wound the wrong way and an altitude
+        let text = r#"{"type": "Feature", "properties": {"name": "parcel"}, "geometry": {
+            "type": "Polygon",
+            "coordinates": [
+                [[100.0, 0.0], [101.0, 0.0], [101.0, 1.0], [100.0, 1.0], [100.0, 0.0]],
+                [[100.8, 0.8], [100.8, 0.2], [100.2, 0.2, 5], [100.2, 0.8], [100.8, 0.8]]
+            ]
+        }}"#;
+        let polygons = parse_geojson(text).unwrap();
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[0].num_points(), 4);
+        assert!(polygons[1].is_hole());
+        assert!(polygons[1].props().signed_area() < 0.0);
+        assert!((polygons_area(&polygons) - 0.64).abs() < 1e-9);
+
+        assert!(parse_geojson(r#"{"type": "Point", "coordinates": [0, 0]}"#).unwrap_err().contains("Point"));
+        assert!(parse_geojson(r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [0, 0]]]}"#).is_err());
+        assert!(parse_geojson(r#"{"type": "Polygon", "coordinates": [[[0, 0], [1, "0"], [0, 1]]]}"#).is_err());
+        assert!(parse_geojson("{\"type\": \"Polygon\",\n \"coordinates\": [}").unwrap_err().starts_with("Line 2, column 18"));
+        assert!(parse_geojson(r#"{"type": "FeatureCollection", "features": [{"type": "Feature", "geometry": null}]}"#).unwrap().is_empty());
+    }
+
+    #[test]
+    fn geo_format_round_trips() {
+        let options = GeneratorOptions { generator: PolygonGenerator::Star, num_vertices: 12, num_holes: 2, ..Default::default() };
+        let mut polygons = generate_polygons(&options).unwrap();
+        // An island in the first hole, and a separate solid
+        let hole = &polygons[1];
+        let centroid = hole.props().centroid();
+        let island = ring(&[(-1.0, -1.0), (1.0, -1.0), (0.0, 1.0)], false);
+        let island_points = island.props().points.iter().map(|&p| PointF64::new(centroid.x + p.x * 1e-3, centroid.y + p.y * 1e-3)).collect();
+        polygons.push(Polygon::from_points_and_is_hole(island_points, false));
+        polygons.push(ring(&[(2000.0, 0.0), (2010.0, 0.0), (2010.0, 10.0)], false));
+
+        // Each solid is followed by its holes
+        let grouped = vec![polygons[0].clone(), polygons[1].clone(), polygons[2].clone(), polygons[3].clone(), polygons[4].clone()];
+        assert_same_polygons(&parse_geojson(&write_geojson(&polygons).unwrap()).unwrap(), &grouped);
+        assert_same_polygons(&parse_wkt(&write_wkt(&polygons).unwrap()).unwrap(), &grouped);
+        assert_same_polygons(&parse_geojson(&write_geojson(&polygons[..2]).unwrap()).unwrap(), &polygons[..2]);
+        assert!(write_wkt(&polygons[..2]).unwrap().starts_with("POLYGON (("));
+        assert_eq!(write_wkt(&[]).unwrap(), "MULTIPOLYGON EMPTY");
+        assert!(write_geojson(&polygons[1..2]).is_err());
+
+        let triangles = triangulate_mono_vec(polygons[..3].to_vec()).unwrap();
+        let collection = write_geojson_feature_collection(&triangles).unwrap();
+        assert_eq!(collection.matches("\"Feature\"").count(), triangles.len());
+        assert_same_polygons(&parse_geojson(&collection).unwrap(), &triangles);
+    }
+
+    #[test]
+    fn geo_format_reads_wkt() {
+        let polygons = parse_wkt("MultiPolygon Z (((0 0 1, 10 0 1, 10 10 1, 0 10 1, 0 0 1), (2 2 1, 2 8 1, 8 8 1, 8 2 1, 2 2 1)), EMPTY, ((20 0 0, 30 0 0, 30 10 0)))").unwrap();
+        assert_eq!(polygons.len(), 3);
+        assert!(polygons[1].is_hole() && !polygons[2].is_hole());
+        assert!((polygons_area(&polygons) - 114.0).abs() < 1e-9);
+        assert_eq!(parse_wkt("GEOMETRYCOLLECTION (POLYGON ((0 0, 1 0, 0 1)), POLYGON EMPTY)").unwrap().len(), 1);
+
+        let error_at = |text: &str| {
+            let error = parse_wkt(text).unwrap_err();
+            error.split(':').next().unwrap().to_string()
+        };
+        assert_eq!(error_at("POLYGON ((0 0, 1 0, 0 1)"), "Line 1, column 25");
+        assert_eq!(error_at("POLYGON ((0 0, 1, 0 1))"), "Line 1, column 17");
+        assert_eq!(error_at("POLYGON\n((0 0, 1 0, 0 1)) x"), "Line 2, column 19");
+        assert_eq!(error_at("POINT (0 0)"), "Line 1, column 1");
+        assert_eq!(error_at("POLYGON ((0 0, 1 0, 0 0))"), "Line 1, column 9");
+        assert_eq!(error_at("POLYGON ((0 0; 1 0, 0 1))"), "Line 1, column 14");
+
+        let nested = |depth: usize| format!("{}POLYGON EMPTY{}", "GEOMETRYCOLLECTION (".repeat(depth), ")".repeat(depth));
+        assert!(parse_wkt(&nested(MAX_WKT_DEPTH)).unwrap().is_empty());
+        assert!(parse_wkt(&nested(100_000)).unwrap_err().contains("nested too deeply"));
+    }
+}
@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+
+use visioncortex::PointF64;
+
+use super::{DualGraph, Polygon, PolygonInterface, intersects, is_inside_ring, signed_area};
+
+/// A doubly-connected edge list (DCEL).
+///
+/// Every edge is stored as two half-edges pointing in opposite directions.
+/// Half-edges on the outer boundary and around holes have no face.
+/// Removed elements are kept in place and marked inactive, so indices stay valid.
+///
+/// The partition algorithms write their diagonals into a mesh as they go: see `from_rings`,
+/// `add_diagonal`, `monotone_partition_mesh`, `remove_holes_mesh` and `add_ear_triangles`.
+#[derive(Clone, Debug, Default)]
+pub struct HalfEdgeMesh {
+    pub vertices: Vec<HalfEdgeVertex>,
+    pub half_edges: Vec<HalfEdge>,
+    pub faces: Vec<HalfEdgeFace>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HalfEdgeVertex {
+    pub p: PointF64,
+    /// Any half-edge starting from this vertex
+    pub half_edge: Option<usize>,
+    pub is_active: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HalfEdge {
+    /// The vertex this half-edge starts from
+    pub origin: usize,
+    /// The half-edge in the opposite direction
+    pub twin: usize,
+    pub next: usize,
+    pub previous: usize,
+    /// None if this half-edge lies on the boundary
+    pub face: Option<usize>,
+    pub is_active: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HalfEdgeFace {
+    /// Any half-edge bounding this face
+    pub half_edge: usize,
+    /// A half-edge of each hole inside this face
+    pub holes: Vec<usize>,
+    pub is_active: bool,
+}
+
+impl HalfEdgeMesh {
+    /// Builds a mesh from faces given as indices into `points`.
+    ///
+    /// All faces must have the same winding order and the result must be manifold,
+    /// so no face may visit a vertex twice.
+    pub fn from_indexed_faces(points: &[PointF64], faces: &[Vec<usize>]) -> Result<Self, &'static str> {
+        let mut mesh = Self {
+            vertices: points.iter().map(|&p| HalfEdgeVertex { p, half_edge: None, is_active: true }).collect(),
+            half_edges: vec![],
+            faces: vec![],
+        };
+
+        let mut lookup = HashMap::new();
+        for face in faces.iter() {
+            if face.len() < 3 {
+                return Err("A face has less than 3 vertices.");
+            }
+            let mut sorted = face.clone();
+            sorted.sort_unstable();
+            if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+                return Err("A face visits a vertex twice.");
+            }
+            let face_index = mesh.faces.len();
+            let first = mesh.half_edges.len();
+            let len = face.len();
+            for curr in 0..len {
+                let (from, to) = (face[curr], face[(curr+1) % len]);
+                if lookup.insert((from, to), first + curr).is_some() {
+                    return Err("An edge is used twice in the same direction.");
+                }
+                mesh.half_edges.push(HalfEdge {
+                    origin: from,
+                    twin: 0,
+                    next: first + (curr+1) % len,
+                    previous: first + (curr+len-1) % len,
+                    face: Some(face_index),
+                    is_active: true,
+                });
+                mesh.vertices[from].half_edge = Some(first + curr);
+            }
+            mesh.faces.push(HalfEdgeFace { half_edge: first, holes: vec![], is_active: true });
+        }
+
+        // Pair up twins, creating boundary half-edges where there is no neighbouring face
+        let num_inner = mesh.half_edges.len();
+        for h in 0..num_inner {
+            let from = mesh.half_edges[h].origin;
+            let to = mesh.destination(h);
+            if let Some(&twin) = lookup.get(&(to, from)) {
+                mesh.half_edges[h].twin = twin;
+            } else {
+                let twin = mesh.half_edges.len();
+                mesh.half_edges.push(HalfEdge {
+                    origin: to,
+                    twin: h,
+                    next: 0,
+                    previous: 0,
+                    face: None,
+                    is_active: true,
+                });
+                mesh.half_edges[h].twin = twin;
+            }
+        }
+
+        // Link the boundary half-edges into loops
+        for b in num_inner..mesh.half_edges.len() {
+            // Rotate around the destination of b until reaching the boundary
+            let mut h = mesh.half_edges[b].twin;
+            let mut guard = 0;
+            let next = loop {
+                let twin = mesh.half_edges[mesh.half_edges[h].previous].twin;
+                if mesh.half_edges[twin].face.is_none() {
+                    break twin;
+                }
+                h = twin;
+                guard += 1;
+                if guard > num_inner {
+                    return Err("The faces do not form a manifold.");
+                }
+            };
+            mesh.half_edges[b].next = next;
+            mesh.half_edges[next].previous = b;
+        }
+
+        Ok(mesh)
+    }
+
+    /// Builds a mesh from the pieces of a partition
+    pub fn from_dual_graph(graph: &DualGraph) -> Result<Self, &'static str> {
+        Self::from_indexed_faces(&graph.points, &graph.pieces)
+    }
+
+    /// Builds a mesh with one face per polygon, e.g. from the pieces of a partition.
+    ///
+    /// Points with identical coordinates become one vertex. So a polygon that repeats a point,
+    /// like the bridges of `remove_holes`, is rejected; use `remove_holes_mesh` for those.
+    pub fn from_polygons(polys: &[Polygon]) -> Result<Self, &'static str> {
+        let mut points = vec![];
+        let mut lookup = HashMap::new();
+        let faces: Vec<Vec<usize>> = polys.iter().map(|poly| {
+            poly.props().points.iter().map(|p| {
+                *lookup.entry((p.x.to_bits(), p.y.to_bits())).or_insert_with(|| {
+                    points.push(*p);
+                    points.len() - 1
+                })
+            }).collect()
+        }).collect();
+        Self::from_indexed_faces(&points, &faces)
+    }
+
+    /// Builds a mesh from polygons with holes. Each solid is a face, and each hole a hole of the smallest
+    /// solid around it. Rings must be oriented by the `is_hole` convention (solids counter-clockwise).
+    ///
+    /// Every point is its own vertex, numbered as the points of all polygons in order, and the half-edge
+    /// with the same index leaves it along its ring. The boundary half-edges come after those.
+    pub fn from_rings(polys: &[Polygon]) -> Result<Self, &'static str> {
+        let num_points: usize = polys.iter().map(|poly| poly.num_points()).sum();
+        let mut mesh = Self::default();
+        let mut solids = vec![];
+        let mut start = 0;
+        for poly in polys.iter() {
+            let len = poly.num_points();
+            if len < 3 {
+                return Err("A ring has less than 3 points.");
+            }
+            let face = if poly.is_hole() { None } else { Some(mesh.faces.len()) };
+            for i in 0..len {
+                let (h, next, previous) = (start + i, start + (i+1) % len, start + (i+len-1) % len);
+                mesh.vertices.push(HalfEdgeVertex { p: poly.get_point(i), half_edge: Some(h), is_active: true });
+                mesh.half_edges.push(HalfEdge { origin: h, twin: num_points + h, next, previous, face, is_active: true });
+            }
+            if let Some(face) = face {
+                mesh.faces.push(HalfEdgeFace { half_edge: start, holes: vec![], is_active: true });
+                solids.push((face, start));
+            }
+            start += len;
+        }
+        // The boundary loops run the other way around each ring
+        for h in 0..num_points {
+            let (next, previous) = (mesh.half_edges[h].next, mesh.half_edges[h].previous);
+            mesh.half_edges.push(HalfEdge {
+                origin: next,
+                twin: h,
+                next: num_points + previous,
+                previous: num_points + next,
+                face: None,
+                is_active: true,
+            });
+        }
+
+        let mut start = 0;
+        for poly in polys.iter() {
+            if poly.is_hole() {
+                let p = poly.get_point(0);
+                let face = solids.iter()
+                    .map(|&(face, h)| (face, mesh.loop_points(h)))
+                    .filter(|(_, points)| is_inside_ring(points, &p))
+                    .min_by(|(_, a), (_, b)| signed_area(a).total_cmp(&signed_area(b)))
+                    .map(|(face, _)| face)
+                    .ok_or("A hole is not inside any solid.")?;
+                for h in start..start + poly.num_points() {
+                    mesh.half_edges[h].face = Some(face);
+                }
+                mesh.faces[face].holes.push(start);
+            }
+            start += poly.num_points();
+        }
+        Ok(mesh)
+    }
+
+    /// Adds an edge from the origin of `h1` to the origin of `h2`, in the corners just before `h1` and `h2`.
+    ///
+    /// Returns the new half-edges, from the origin of `h1` and from the origin of `h2`. Depending on
+    /// whether `h1` and `h2` are in the same loop, this splits a face or joins a hole to its face;
+    /// the new half-edges take the faces of `h1` and `h2`, and `rebuild_faces` sorts them out.
+    pub fn add_diagonal(&mut self, h1: usize, h2: usize) -> (usize, usize) {
+        let (previous_1, previous_2) = (self.half_edges[h1].previous, self.half_edges[h2].previous);
+        let (d1, d2) = (self.half_edges.len(), self.half_edges.len() + 1);
+        self.half_edges.push(HalfEdge {
+            origin: self.half_edges[h1].origin,
+            twin: d2,
+            next: h2,
+            previous: previous_1,
+            face: self.half_edges[h1].face,
+            is_active: true,
+        });
+        self.half_edges.push(HalfEdge {
+            origin: self.half_edges[h2].origin,
+            twin: d1,
+            next: h1,
+            previous: previous_2,
+            face: self.half_edges[h2].face,
+            is_active: true,
+        });
+        self.half_edges[previous_1].next = d1;
+        self.half_edges[h2].previous = d1;
+        self.half_edges[previous_2].next = d2;
+        self.half_edges[h1].previous = d2;
+        (d1, d2)
+    }
+
+    /// Recomputes the faces from the loops of the half-edges that have one. A loop wound counter-clockwise
+    /// (by `Orientation`) bounds a face, in the order of its lowest half-edge; any other loop is a hole of
+    /// the smallest face around it.
+    pub fn rebuild_faces(&mut self) -> Result<(), &'static str> {
+        self.faces.clear();
+        let mut holes = vec![];
+        let mut is_visited = vec![false; self.half_edges.len()];
+        for h in 0..self.half_edges.len() {
+            if is_visited[h] || !self.half_edges[h].is_active || self.half_edges[h].face.is_none() {
+                continue;
+            }
+            let half_edges = self.loop_half_edges(h);
+            for &e in half_edges.iter() {
+                is_visited[e] = true;
+            }
+            if signed_area(&self.loop_points(h)) < 0.0 {
+                holes.push(half_edges);
+                continue;
+            }
+            for &e in half_edges.iter() {
+                self.half_edges[e].face = Some(self.faces.len());
+            }
+            self.faces.push(HalfEdgeFace { half_edge: h, holes: vec![], is_active: true });
+        }
+        for half_edges in holes {
+            let p = self.vertices[self.half_edges[half_edges[0]].origin].p;
+            let face = self.active_faces()
+                .map(|face| (face, self.loop_points(self.faces[face].half_edge)))
+                .filter(|(_, points)| is_inside_ring(points, &p))
+                .min_by(|(_, a), (_, b)| signed_area(a).total_cmp(&signed_area(b)))
+                .map(|(face, _)| face)
+                .ok_or("A hole is not inside any face.")?;
+            for &e in half_edges.iter() {
+                self.half_edges[e].face = Some(face);
+            }
+            self.faces[face].holes.push(half_edges[0]);
+        }
+        Ok(())
+    }
+
+    /// Splits a face without holes into triangles, given as positions in its loop (from `face_half_edges`)
+    /// and wound like the face. The triangles must come in the order an ear clipper cuts them off, each an ear
+    /// of what is left of the face, as from `triangulate_ec_indices` or `triangulate_mono_indices`.
+    ///
+    /// The faces are left to `rebuild_faces`.
+    pub fn add_ear_triangles(&mut self, face: usize, triangles: &[[usize; 3]]) -> Result<(), &'static str> {
+        if !self.faces[face].holes.is_empty() {
+            return Err("Cannot triangulate a face with holes.");
+        }
+        // The half-edge leaving each position, and the next position, along what is left of the face
+        let mut half_edges = self.face_half_edges(face);
+        let len = half_edges.len();
+        if triangles.len() + 2 != len {
+            return Err("A face of n vertices needs n - 2 triangles.");
+        }
+        let mut next: Vec<usize> = (0..len).map(|i| (i+1) % len).collect();
+        for (i, triangle) in triangles.iter().enumerate() {
+            let ear = (0..3).find(|&r| {
+                let (previous, ear, following) = (triangle[r], triangle[(r+1) % 3], triangle[(r+2) % 3]);
+                next[previous] == ear && next[ear] == following
+            }).ok_or("A triangle is not an ear of the face.")?;
+            let (previous, following) = (triangle[ear], triangle[(ear+2) % 3]);
+            if i + 1 == triangles.len() {
+                // The last ear closes the face
+                if next[following] != previous {
+                    return Err("A triangle is not an ear of the face.");
+                }
+                break;
+            }
+            let (d1, _) = self.add_diagonal(half_edges[previous], half_edges[following]);
+            half_edges[previous] = d1;
+            next[previous] = following;
+        }
+        Ok(())
+    }
+
+    /// The points of the loop containing `h`, starting from its origin
+    fn loop_points(&self, h: usize) -> Vec<PointF64> {
+        self.loop_half_edges(h).iter().map(|&e| self.vertices[self.half_edges[e].origin].p).collect()
+    }
+
+    /// The vertex a half-edge points to
+    pub fn destination(&self, h: usize) -> usize {
+        self.half_edges[self.half_edges[h].next].origin
+    }
+
+    pub fn twin(&self, h: usize) -> usize {
+        self.half_edges[h].twin
+    }
+
+    pub fn is_boundary(&self, h: usize) -> bool {
+        self.half_edges[h].face.is_none()
+    }
+
+    /// The half-edges around a face, in winding order
+    pub fn face_half_edges(&self, face: usize) -> Vec<usize> {
+        self.loop_half_edges(self.faces[face].half_edge)
+    }
+
+    /// The half-edges of the loop containing `h`, starting from `h`
+    pub fn loop_half_edges(&self, h: usize) -> Vec<usize> {
+        let mut half_edges = vec![h];
+        let mut curr = self.half_edges[h].next;
+        while curr != h {
+            half_edges.push(curr);
+            curr = self.half_edges[curr].next;
+        }
+        half_edges
+    }
+
+    pub fn face_vertices(&self, face: usize) -> Vec<usize> {
+        self.face_half_edges(face).iter().map(|&h| self.half_edges[h].origin).collect()
+    }
+
+    /// The faces sharing an edge with the given face
+    pub fn face_neighbors(&self, face: usize) -> Vec<usize> {
+        self.face_half_edges(face).iter()
+            .filter_map(|&h| self.half_edges[self.twin(h)].face)
+            .collect()
+    }
+
+    /// The half-edges starting from a vertex, in rotational order
+    pub fn vertex_half_edges(&self, v: usize) -> Vec<usize> {
+        let start = if let Some(h) = self.vertices[v].half_edge { h } else { return vec![]; };
+        let mut half_edges = vec![start];
+        let mut curr = self.twin(self.half_edges[start].previous);
+        while curr != start {
+            half_edges.push(curr);
+            curr = self.twin(self.half_edges[curr].previous);
+        }
+        half_edges
+    }
+
+    /// The vertices connected to a vertex by an edge
+    pub fn vertex_neighbors(&self, v: usize) -> Vec<usize> {
+        self.vertex_half_edges(v).iter().map(|&h| self.destination(h)).collect()
+    }
+
+    /// Find the half-edge going from `from` to `to`
+    pub fn find_half_edge(&self, from: usize, to: usize) -> Option<usize> {
+        self.vertex_half_edges(from).into_iter().find(|&h| self.destination(h) == to)
+    }
+
+    pub fn active_faces(&self) -> impl Iterator<Item = usize> + '_ {
+        self.faces.iter().enumerate().filter(|(_, face)| face.is_active).map(|(i, _)| i)
+    }
+
+    pub fn get_face_polygon(&self, face: usize) -> Polygon {
+        Polygon::from_points_and_is_hole(
+            self.face_vertices(face).iter().map(|&v| self.vertices[v].p).collect(),
+            false
+        )
+    }
+
+    /// All active faces as polygons
+    pub fn to_polygons(&self) -> Vec<Polygon> {
+        self.active_faces().map(|face| self.get_face_polygon(face)).collect()
+    }
+
+    /// Replace the edge of `h` by the other diagonal of the quadrilateral formed by its two triangles.
+    ///
+    /// Both faces of the edge must be triangles and the quadrilateral must be convex.
+    pub fn flip_edge(&mut self, h: usize) -> Result<(), &'static str> {
+        let t = self.twin(h);
+        let (f1, f2) = match (self.half_edges[h].face, self.half_edges[t].face) {
+            (Some(f1), Some(f2)) => (f1, f2),
+            _ => return Err("Cannot flip a boundary edge."),
+        };
+        if self.face_half_edges(f1).len() != 3 || self.face_half_edges(f2).len() != 3 {
+            return Err("Can only flip an edge between two triangles.");
+        }
+
+        // h: a->b in (a, b, c), t: b->a in (b, a, d)
+        let (hn, hp) = (self.half_edges[h].next, self.half_edges[h].previous);
+        let (tn, tp) = (self.half_edges[t].next, self.half_edges[t].previous);
+        let a = self.half_edges[h].origin;
+        let b = self.half_edges[t].origin;
+        let c = self.half_edges[hp].origin;
+        let d = self.half_edges[tp].origin;
+        let (pa, pb, pc, pd) = (self.vertices[a].p, self.vertices[b].p, self.vertices[c].p, self.vertices[d].p);
+        if !intersects(&pa, &pb, &pc, &pd) {
+            return Err("The quadrilateral is not convex.");
+        }
+
+        // h: d->c in (d, c, a), t: c->d in (c, d, b)
+        self.link(h, hp, tn, f1);
+        self.link(t, tp, hn, f2);
+        self.half_edges[h].origin = d;
+        self.half_edges[t].origin = c;
+        self.vertices[a].half_edge = Some(tn);
+        self.vertices[b].half_edge = Some(hn);
+        Ok(())
+    }
+
+    /// Insert a new vertex at `p` in the middle of the edge of `h`.
+    ///
+    /// The faces on both sides gain one vertex. Returns the index of the new vertex.
+    pub fn split_edge(&mut self, h: usize, p: PointF64) -> usize {
+        let t = self.twin(h);
+        let a = self.half_edges[h].origin;
+        let b = self.half_edges[t].origin;
+        let m = self.vertices.len();
+        let h2 = self.half_edges.len();
+        let t2 = h2 + 1;
+
+        // h: a->m, h2: m->b, t: b->m, t2: m->a
+        for &(new, old, origin, twin) in [(h2, h, m, t), (t2, t, m, h)].iter() {
+            let old_next = self.half_edges[old].next;
+            let face = self.half_edges[old].face;
+            self.half_edges.push(HalfEdge {
+                origin,
+                twin,
+                next: old_next,
+                previous: old,
+                face,
+                is_active: true,
+            });
+            self.half_edges[old_next].previous = new;
+            self.half_edges[old].next = new;
+            self.half_edges[twin].twin = new;
+        }
+        self.vertices.push(HalfEdgeVertex { p, half_edge: Some(h2), is_active: true });
+        self.vertices[a].half_edge = Some(h);
+        self.vertices[b].half_edge = Some(t);
+        m
+    }
+
+    /// Merge the destination of `h` into its origin, which is moved to `p`.
+    ///
+    /// Triangles on either side of the edge are removed.
+    /// Fails if the collapse would make the mesh non-manifold.
+    pub fn collapse_edge(&mut self, h: usize, p: PointF64) -> Result<(), &'static str> {
+        let t = self.twin(h);
+        let a = self.half_edges[h].origin;
+        let b = self.half_edges[t].origin;
+
+        // Link condition: the common neighbours of a and b must be the apexes of the faces of the edge
+        let apexes: Vec<usize> = [h, t].iter()
+            .filter(|&&e| self.loop_half_edges(e).len() == 3)
+            .map(|&e| self.half_edges[self.half_edges[e].previous].origin)
+            .collect();
+        let neighbors_b = self.vertex_neighbors(b);
+        if self.vertex_neighbors(a).iter().any(|v| *v != b && neighbors_b.contains(v) && !apexes.contains(v)) {
+            return Err("Collapsing this edge would make the mesh non-manifold.");
+        }
+
+        for e in self.vertex_half_edges(b) {
+            self.half_edges[e].origin = a;
+        }
+        for &e in [h, t].iter() {
+            let (next, previous) = (self.half_edges[e].next, self.half_edges[e].previous);
+            self.half_edges[previous].next = next;
+            self.half_edges[next].previous = previous;
+            self.half_edges[e].is_active = false;
+            if let Some(face) = self.half_edges[e].face {
+                self.faces[face].half_edge = next;
+            }
+
+            // A loop left with two half-edges is degenerate: glue their twins together
+            if self.half_edges[next].next == previous {
+                let (twin_next, twin_previous) = (self.twin(next), self.twin(previous));
+                self.half_edges[twin_next].twin = twin_previous;
+                self.half_edges[twin_previous].twin = twin_next;
+                self.half_edges[next].is_active = false;
+                self.half_edges[previous].is_active = false;
+                if let Some(face) = self.half_edges[next].face {
+                    self.faces[face].is_active = false;
+                }
+                let apex = self.half_edges[previous].origin;
+                self.vertices[apex].half_edge = Some(twin_next);
+                self.vertices[a].half_edge = Some(twin_previous);
+            } else {
+                self.vertices[a].half_edge = Some(next);
+            }
+        }
+        // The surviving outgoing half-edge of a may start from either side of the collapsed edge
+        if let Some(e) = self.vertices[a].half_edge {
+            if self.half_edges[e].origin != a {
+                self.vertices[a].half_edge = Some(self.twin(e));
+            }
+        }
+        self.vertices[a].p = p;
+        self.vertices[b].is_active = false;
+        self.vertices[b].half_edge = None;
+        Ok(())
+    }
+
+    /// Connect three half-edges into a triangular face
+    fn link(&mut self, e1: usize, e2: usize, e3: usize, face: usize) {
+        for &(e, next, previous) in [(e1, e2, e3), (e2, e3, e1), (e3, e1, e2)].iter() {
+            self.half_edges[e].next = next;
+            self.half_edges[e].previous = previous;
+            self.half_edges[e].face = Some(face);
+        }
+        self.faces[face].half_edge = e1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{remove_holes, remove_holes_mesh, triangulate_ec_mesh, triangulate_ec_vec_with_adjacency};
+    use crate::polypartition::fixtures::hexagon_with_hole;
+
+    fn square() -> HalfEdgeMesh {
+        let points = vec![
+            PointF64::new(0.0, 0.0), PointF64::new(10.0, 0.0),
+            PointF64::new(10.0, 10.0), PointF64::new(0.0, 10.0),
+        ];
+        HalfEdgeMesh::from_indexed_faces(&points, &[vec![0, 1, 2], vec![0, 2, 3]]).unwrap()
+    }
+
+    #[test]
+    fn half_edge_mesh_traversal() {
+        let mesh = square();
+        assert_eq!(mesh.half_edges.len(), 10);
+        assert_eq!(mesh.face_neighbors(0), vec![1]);
+        assert_eq!(mesh.vertex_neighbors(0).len(), 3);
+        let boundary = mesh.loop_half_edges(mesh.twin(0));
+        assert_eq!(boundary.len(), 4);
+        assert!(boundary.iter().all(|&h| mesh.is_boundary(h) && mesh.twin(mesh.twin(h)) == h));
+    }
+
+    #[test]
+    fn half_edge_mesh_flip_split_collapse() {
+        let mut mesh = square();
+        let diagonal = mesh.find_half_edge(0, 2).unwrap();
+        mesh.flip_edge(diagonal).unwrap();
+        assert!(mesh.find_half_edge(0, 2).is_none());
+        assert!(mesh.find_half_edge(1, 3).is_some() && mesh.find_half_edge(3, 1).is_some());
+        assert!(mesh.flip_edge(mesh.find_half_edge(0, 1).unwrap()).is_err());
+
+        let h = mesh.find_half_edge(1, 3).unwrap();
+        let m = mesh.split_edge(h, PointF64::new(5.0, 5.0));
+        assert_eq!(mesh.vertex_neighbors(m).len(), 2);
+        assert!(mesh.active_faces().all(|face| mesh.face_vertices(face).len() == 4));
+
+        let mut mesh = square();
+        let h = mesh.find_half_edge(0, 2).unwrap();
+        assert!(mesh.collapse_edge(h, PointF64::new(5.0, 5.0)).is_ok());
+        assert_eq!(mesh.active_faces().count(), 0);
+        assert_eq!(mesh.vertex_neighbors(0).len(), 2);
+    }
+
+    #[test]
+    fn half_edge_mesh_from_partition() {
+        let polys = hexagon_with_hole();
+        // Both sides of a bridge would be the same vertex, so plain polygons cannot take them
+        assert!(HalfEdgeMesh::from_polygons(&remove_holes(&polys).unwrap()).is_err());
+        let mesh = remove_holes_mesh(&polys).unwrap();
+        assert_eq!(mesh.active_faces().count(), 1);
+        assert_eq!(mesh.face_half_edges(0).len(), 12);
+        let bridge = (0..mesh.half_edges.len())
+            .filter(|&h| mesh.half_edges[h].face == Some(0) && mesh.half_edges[mesh.twin(h)].face == Some(0))
+            .count();
+        assert_eq!(bridge, 2);
+
+        let mesh = triangulate_ec_mesh(&polys).unwrap();
+        assert_eq!(mesh.active_faces().count(), 10);
+        assert!(mesh.active_faces().all(|face| mesh.face_vertices(face).len() == 3));
+
+        let graph = triangulate_ec_vec_with_adjacency(polys).unwrap();
+        let mesh = HalfEdgeMesh::from_dual_graph(&graph).unwrap();
+        assert_eq!(mesh.faces.len(), 10);
+        // The outer boundary and the hole form two boundary loops
+        let boundary: Vec<usize> = (0..mesh.half_edges.len()).filter(|&h| mesh.is_boundary(h)).collect();
+        assert_eq!(boundary.len(), 10);
+        let loop_sizes: Vec<usize> = boundary.iter().map(|&h| mesh.loop_half_edges(h).len()).collect();
+        assert!(loop_sizes.contains(&6) && loop_sizes.contains(&4));
+    }
+}
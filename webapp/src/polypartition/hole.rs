@@ -1,10 +1,29 @@
-use super::{Polygon, PolygonInterface, intersects, is_in_cone, normalize};
+use super::{HalfEdgeMesh, Polygon, PolygonInterface, intersects, is_in_cone, is_inside_segment, normalize};
 
-pub fn remove_holes(inpolys: &[Polygon]) -> Result<Vec<Polygon>, &str> {
+pub fn remove_holes(inpolys: &[Polygon]) -> Result<Vec<Polygon>, &'static str> {
     // Check for the trivial case of no holes
     if inpolys.iter().find(|polygon| polygon.is_hole()).is_none() {
         return Ok(inpolys.to_vec());
     }
+    Ok(bridge_holes(inpolys)?.0)
+}
+
+/// Same as `remove_holes`, but adds each bridge as an edge to the mesh of the input rings
+/// (see `HalfEdgeMesh::from_rings`). So the two sides of a bridge are the same vertices.
+///
+/// Returns the mesh with one face per output polygon.
+pub fn remove_holes_mesh(inpolys: &[Polygon]) -> Result<HalfEdgeMesh, &'static str> {
+    Ok(bridge_holes(inpolys)?.1)
+}
+
+fn bridge_holes(inpolys: &[Polygon]) -> Result<(Vec<Polygon>, HalfEdgeMesh), &'static str> {
+    let mut mesh = HalfEdgeMesh::from_rings(inpolys)?;
+    // The half-edge leaving each point of each polygon in the mesh
+    let mut start = 0;
+    let mut loops: Vec<Vec<usize>> = inpolys.iter().map(|poly| {
+        start += poly.num_points();
+        (start - poly.num_points()..start).collect()
+    }).collect();
 
     let mut polys = inpolys.to_vec();
     // Repeatedly merge hole polygon with a suitable non-hole polygon
@@ -45,8 +64,9 @@ pub fn remove_holes(inpolys: &[Polygon]) -> Result<Vec<Polygon>, &str> {
         let holepoint = hole_polygon.get_point(holepoint_index);
 
         // Now find the suitable non-hole polygon and its "polypoint"
-        let non_hole_polygons = polys.iter().filter(|poly| !poly.is_hole());
-        let (best_polygon, polypoint_index, best_polygon_index) = non_hole_polygons.into_iter().enumerate()
+        // Enumerate before filtering, so that best_polygon_index indexes into polys
+        let non_hole_polygons = polys.iter().enumerate().filter(|(_, poly)| !poly.is_hole());
+        let (best_polygon, polypoint_index, best_polygon_index) = non_hole_polygons
             .fold(
                 (None, 0, 0), // To simulate pointfound. (Option<&polygon>, polypoint_index, best_polygon_index)
                 |(mut acc_polygon, mut acc_polypoint_index, mut acc_best_polygon_index), (polygon_index, polygon)| {
@@ -85,7 +105,8 @@ pub fn remove_holes(inpolys: &[Polygon]) -> Result<Vec<Polygon>, &str> {
                             for i2 in 0..num_points {
                                 let curr = points[i2];
                                 let next = points[(i2 + 1) % num_points];
-                                if intersects(&holepoint, &polypoint, &curr, &next) {
+                                // A bridge through another vertex would make a degenerate piece
+                                if intersects(&holepoint, &polypoint, &curr, &next) || is_inside_segment(&holepoint, &polypoint, &curr) {
                                     return false;
                                 }
                             }
@@ -127,10 +148,24 @@ pub fn remove_holes(inpolys: &[Polygon]) -> Result<Vec<Polygon>, &str> {
         }
         let newpoly = Polygon::from_points_and_is_hole(newpoly_points, false);
 
-        polys.remove(hole_polygon_index);
-        polys.remove(best_polygon_index);
+        // The same loop in the mesh, where the bridge is one edge visited both ways
+        let (best_loop, hole_loop) = (&loops[best_polygon_index], &loops[hole_polygon_index]);
+        let (d1, d2) = mesh.add_diagonal(best_loop[polypoint_index], hole_loop[holepoint_index]);
+        let mut newloop = best_loop[..polypoint_index].to_vec();
+        newloop.push(d1);
+        newloop.extend((0..hole_num_points).map(|i| hole_loop[(i + holepoint_index) % hole_num_points]));
+        newloop.push(d2);
+        newloop.extend_from_slice(&best_loop[polypoint_index..]);
+
+        // Remove the later one first, so the earlier index stays valid
+        for &index in [hole_polygon_index.max(best_polygon_index), hole_polygon_index.min(best_polygon_index)].iter() {
+            polys.remove(index);
+            loops.remove(index);
+        }
         polys.push(newpoly);
+        loops.push(newloop);
     }
 
-    Ok(polys)
+    mesh.rebuild_faces()?;
+    Ok((polys, mesh))
 }
\ No newline at end of file
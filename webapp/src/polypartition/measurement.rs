@@ -0,0 +1,372 @@
+use visioncortex::{BoundingRect, PointF64};
+
+use super::{FillRule, Polygon, PolygonInterface, PolygonProps, distance_to_segment, orient2d};
+
+/// The axis-aligned rectangle that bounds a polygon, in floating point
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundingRectF64 {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl BoundingRectF64 {
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bottom - self.top
+    }
+
+    /// The smallest rectangle containing both
+    pub fn merge(&self, other: &Self) -> Self {
+        Self {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    /// The smallest integer rectangle containing this one
+    pub fn to_bounding_rect(&self) -> BoundingRect {
+        BoundingRect {
+            left: self.left.floor() as i32,
+            top: self.top.floor() as i32,
+            right: self.right.ceil() as i32,
+            bottom: self.bottom.ceil() as i32,
+        }
+    }
+}
+
+/// Second moments of area about the centroid
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SecondMoments {
+    /// The integral of (x - cx)^2 over the area
+    pub xx: f64,
+    /// The integral of (y - cy)^2 over the area
+    pub yy: f64,
+    /// The integral of (x - cx)(y - cy) over the area
+    pub xy: f64,
+}
+
+/// Integrals of 1, x, y, x^2, y^2 and xy over the area enclosed by a ring, relative to an origin.
+/// Negative if the ring is wound clockwise.
+#[derive(Clone, Copy, Default)]
+struct AreaIntegrals {
+    area: f64,
+    x: f64,
+    y: f64,
+    xx: f64,
+    yy: f64,
+    xy: f64,
+}
+
+impl AreaIntegrals {
+    fn of_ring(points: &[PointF64], origin: &PointF64) -> Self {
+        let mut integrals = Self::default();
+        let len = points.len();
+        for curr in 0..len {
+            let (p1, p2) = (points[curr] - *origin, points[(curr+1) % len] - *origin);
+            let cross = p1.x * p2.y - p2.x * p1.y;
+            integrals.area += cross / 2.0;
+            integrals.x += (p1.x + p2.x) * cross / 6.0;
+            integrals.y += (p1.y + p2.y) * cross / 6.0;
+            integrals.xx += (p1.x * p1.x + p1.x * p2.x + p2.x * p2.x) * cross / 12.0;
+            integrals.yy += (p1.y * p1.y + p1.y * p2.y + p2.y * p2.y) * cross / 12.0;
+            integrals.xy += (p1.x * p2.y + 2.0 * p1.x * p1.y + 2.0 * p2.x * p2.y + p2.x * p1.y) * cross / 24.0;
+        }
+        integrals
+    }
+
+    fn add(&mut self, other: &Self, weight: f64) {
+        self.area += other.area * weight;
+        self.x += other.x * weight;
+        self.y += other.y * weight;
+        self.xx += other.xx * weight;
+        self.yy += other.yy * weight;
+        self.xy += other.xy * weight;
+    }
+
+    fn centroid(&self, origin: &PointF64) -> PointF64 {
+        PointF64::new(origin.x + self.x / self.area, origin.y + self.y / self.area)
+    }
+
+    /// By the parallel axis theorem, with the sign of the area removed
+    fn second_moments(&self) -> SecondMoments {
+        if self.area == 0.0 {
+            return SecondMoments::default();
+        }
+        let (cx, cy) = (self.x / self.area, self.y / self.area);
+        let sign = self.area.signum();
+        SecondMoments {
+            xx: (self.xx - self.area * cx * cx) * sign,
+            yy: (self.yy - self.area * cy * cy) * sign,
+            xy: (self.xy - self.area * cx * cy) * sign,
+        }
+    }
+}
+
+/// The mean of the points, for rings that enclose no area
+fn mean_point(points: &[PointF64]) -> PointF64 {
+    if points.is_empty() {
+        return PointF64::default();
+    }
+    let sum = points.iter().fold(PointF64::default(), |sum, &p| sum + p);
+    PointF64::new(sum.x / points.len() as f64, sum.y / points.len() as f64)
+}
+
+impl PolygonProps {
+    /// Positive if wound counter-clockwise (by `Orientation`), negative if clockwise
+    pub fn signed_area(&self) -> f64 {
+        super::signed_area(&self.points)
+    }
+
+    pub fn area(&self) -> f64 {
+        self.signed_area().abs()
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        let len = self.num_points();
+        (0..len).map(|curr| super::distance(&self.points[curr], &self.points[(curr+1) % len])).sum()
+    }
+
+    /// The centroid of the enclosed area, or the mean of the points if the area is zero
+    pub fn centroid(&self) -> PointF64 {
+        let origin = match self.points.first() {
+            Some(&origin) => origin,
+            None => return PointF64::default(),
+        };
+        let integrals = AreaIntegrals::of_ring(&self.points, &origin);
+        if integrals.area == 0.0 {
+            return mean_point(&self.points);
+        }
+        integrals.centroid(&origin)
+    }
+
+    /// Second moments of the enclosed area about its centroid, whichever way the ring is wound
+    pub fn second_moments(&self) -> SecondMoments {
+        match self.points.first() {
+            Some(origin) => AreaIntegrals::of_ring(&self.points, origin).second_moments(),
+            None => SecondMoments::default(),
+        }
+    }
+
+    /// All zero if there are no points
+    pub fn bounding_rect_f64(&self) -> BoundingRectF64 {
+        let first = match self.points.first() {
+            Some(&first) => first,
+            None => return BoundingRectF64::default(),
+        };
+        let initial = BoundingRectF64 { left: first.x, top: first.y, right: first.x, bottom: first.y };
+        self.points.iter().fold(initial, |rect, p| BoundingRectF64 {
+            left: rect.left.min(p.x),
+            top: rect.top.min(p.y),
+            right: rect.right.max(p.x),
+            bottom: rect.bottom.max(p.y),
+        })
+    }
+
+    pub fn bounding_rect(&self) -> BoundingRect {
+        self.bounding_rect_f64().to_bounding_rect()
+    }
+
+    /// The number of times the ring winds counter-clockwise (by `Orientation`) around p.
+    /// Points on the boundary may count either way.
+    pub fn winding_number(&self, p: &PointF64) -> i32 {
+        let len = self.num_points();
+        let mut winding = 0;
+        for curr in 0..len {
+            let (p1, p2) = (self.points[curr], self.points[(curr+1) % len]);
+            if p1.y <= p.y {
+                if p2.y > p.y && orient2d(&p1, &p2, p) > 0.0 {
+                    winding += 1;
+                }
+            } else if p2.y <= p.y && orient2d(&p1, &p2, p) < 0.0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+
+    /// Whether p is inside the ring by the fill rule. Points on the boundary may count either way.
+    pub fn contains_point(&self, p: &PointF64, fill_rule: FillRule) -> bool {
+        let winding = self.winding_number(p);
+        match fill_rule {
+            FillRule::EvenOdd => winding & 1 == 1,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+
+    /// The distance from p to the closest point on the ring, inside or outside
+    pub fn distance_to_boundary(&self, p: &PointF64) -> f64 {
+        let len = self.num_points();
+        (0..len).map(|curr| distance_to_segment(p, &self.points[curr], &self.points[(curr+1) % len]))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// The winding number with the ring taken counter-clockwise if solid, and clockwise if a hole
+    fn hole_aware_winding_number(&self, p: &PointF64) -> i32 {
+        let winding = self.winding_number(p);
+        if (self.signed_area() < 0.0) == self.is_hole {
+            winding
+        } else {
+            -winding
+        }
+    }
+}
+
+/// The area of the solids, less the area of the holes
+pub fn polygons_area(polys: &[Polygon]) -> f64 {
+    polys.iter().map(|poly| if poly.is_hole() {-poly.props().area()} else {poly.props().area()}).sum()
+}
+
+/// The total length of all rings, holes included
+pub fn polygons_perimeter(polys: &[Polygon]) -> f64 {
+    polys.iter().map(|poly| poly.props().perimeter()).sum()
+}
+
+/// Integrals over the solids less the holes, relative to the first point
+fn polygons_integrals(polys: &[Polygon]) -> Option<(AreaIntegrals, PointF64)> {
+    let origin = *polys.iter().find_map(|poly| poly.props().points.first())?;
+    let mut integrals = AreaIntegrals::default();
+    for poly in polys.iter() {
+        let ring = AreaIntegrals::of_ring(&poly.props().points, &origin);
+        let weight = if (ring.area < 0.0) == poly.is_hole() {1.0} else {-1.0};
+        integrals.add(&ring, weight);
+    }
+    Some((integrals, origin))
+}
+
+/// The centroid of the solids less the holes, or the mean of all points if the area is zero
+pub fn polygons_centroid(polys: &[Polygon]) -> PointF64 {
+    match polygons_integrals(polys) {
+        Some((integrals, origin)) if integrals.area != 0.0 => integrals.centroid(&origin),
+        _ => mean_point(&polys.iter().flat_map(|poly| poly.props().points.iter().copied()).collect::<Vec<_>>()),
+    }
+}
+
+/// Second moments of the solids less the holes, about their centroid
+pub fn polygons_second_moments(polys: &[Polygon]) -> SecondMoments {
+    polygons_integrals(polys).map_or(SecondMoments::default(), |(integrals, _)| integrals.second_moments())
+}
+
+/// All zero if there are no points
+pub fn polygons_bounding_rect_f64(polys: &[Polygon]) -> BoundingRectF64 {
+    polys.iter()
+        .filter(|poly| poly.num_points() > 0)
+        .map(|poly| poly.props().bounding_rect_f64())
+        .reduce(|rect, other| rect.merge(&other))
+        .unwrap_or_default()
+}
+
+/// The longer side of the bounding rectangle of the polygons, or 1 if they are all one point,
+/// for tolerances that scale with the input
+pub fn polygons_extent<'a>(polys: impl IntoIterator<Item = &'a Polygon>) -> f64 {
+    let rect = polys.into_iter()
+        .filter(|poly| poly.num_points() > 0)
+        .map(|poly| poly.props().bounding_rect_f64())
+        .reduce(|rect, other| rect.merge(&other))
+        .unwrap_or_default();
+    let extent = rect.width().max(rect.height());
+    if extent > 0.0 { extent } else { 1.0 }
+}
+
+/// The winding number around p, with solids taken counter-clockwise and holes clockwise
+/// (by `Orientation`), however their points are ordered
+pub fn polygons_winding_number(polys: &[Polygon], p: &PointF64) -> i32 {
+    polys.iter().map(|poly| poly.props().hole_aware_winding_number(p)).sum()
+}
+
+/// Whether p is inside the solids and outside the holes, by the fill rule.
+/// Points on the boundary may count either way.
+pub fn polygons_contain_point(polys: &[Polygon], p: &PointF64, fill_rule: FillRule) -> bool {
+    match fill_rule {
+        FillRule::EvenOdd => polys.iter().filter(|poly| poly.props().winding_number(p) & 1 == 1).count() & 1 == 1,
+        FillRule::NonZero => polygons_winding_number(polys, p) != 0,
+    }
+}
+
+/// The distance from p to the closest point on any ring
+pub fn polygons_distance_to_boundary(polys: &[Polygon], p: &PointF64) -> f64 {
+    polys.iter().map(|poly| poly.props().distance_to_boundary(p)).fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::f64_approximately;
+    use crate::polypartition::fixtures::square;
+
+    #[test]
+    fn measurement_single_ring() {
+        let mut poly = square(10.0, 20.0, 10.0, false);
+        let props = poly.props();
+        assert!(f64_approximately(props.signed_area(), 100.0));
+        assert!(f64_approximately(props.perimeter(), 40.0));
+        assert_eq!(props.centroid(), PointF64::new(15.0, 25.0));
+        let moments = props.second_moments();
+        assert!(f64_approximately(moments.xx, 10000.0 / 12.0));
+        assert!(f64_approximately(moments.yy, 10000.0 / 12.0));
+        assert!(f64_approximately(moments.xy, 0.0));
+        assert_eq!(props.bounding_rect(), BoundingRect::new_x_y_w_h(10, 20, 10, 10));
+        assert!(props.contains_point(&PointF64::new(12.0, 21.0), FillRule::EvenOdd));
+        assert!(!props.contains_point(&PointF64::new(9.0, 21.0), FillRule::NonZero));
+        assert!(f64_approximately(props.distance_to_boundary(&PointF64::new(12.0, 21.0)), 1.0));
+        assert!(f64_approximately(props.distance_to_boundary(&PointF64::new(7.0, 16.0)), 5.0));
+
+        // Winding the other way only flips the signs
+        poly.props_mut().invert();
+        let props = poly.props();
+        assert!(f64_approximately(props.signed_area(), -100.0));
+        assert!(f64_approximately(props.area(), 100.0));
+        assert_eq!(props.winding_number(&PointF64::new(12.0, 21.0)), -1);
+        assert!(f64_approximately(props.second_moments().xx, 10000.0 / 12.0));
+
+        // A pentagram winds twice around its centre
+        let star = PolygonProps::from_points_and_is_hole((0..5).map(|i| {
+            let angle = i as f64 * 4.0 * std::f64::consts::PI / 5.0;
+            PointF64::new(angle.cos(), angle.sin())
+        }).collect(), false);
+        let centre = PointF64::new(0.0, 0.0);
+        assert_eq!(star.winding_number(&centre), 2);
+        assert!(star.contains_point(&centre, FillRule::NonZero));
+        assert!(!star.contains_point(&centre, FillRule::EvenOdd));
+    }
+
+    #[test]
+    fn measurement_polygons_with_holes() {
+        // An L shape as a square less a square hole at its corner, with the hole wound either way
+        let mut hole = square(10.0, 10.0, 10.0, true);
+        for _ in 0..2 {
+            let polys = vec![square(0.0, 0.0, 20.0, false), square(30.0, 0.0, 20.0, false), hole.clone()];
+            let l_shape = &[polys[0].clone(), hole.clone()];
+            assert!(f64_approximately(polygons_area(&polys), 700.0));
+            assert!(f64_approximately(polygons_perimeter(l_shape), 120.0));
+            // Three unit squares (in units of 10) at (5, 5), (15, 5) and (5, 15)
+            let centroid = polygons_centroid(l_shape);
+            assert!(f64_approximately(centroid.x, 25.0 / 3.0) && f64_approximately(centroid.y, 25.0 / 3.0));
+            // Each unit square has xx = 10000 / 12, offset by 5 or 20 / 3 from the centroid
+            let moments = polygons_second_moments(l_shape);
+            let expected = 3.0 * 10000.0 / 12.0 + 100.0 * (2.0 * (5.0 - 25.0 / 3.0f64).powi(2) + (15.0 - 25.0 / 3.0f64).powi(2));
+            assert!(f64_approximately(moments.xx, expected));
+            assert!(f64_approximately(moments.yy, expected));
+            assert!(moments.xy < 0.0);
+
+            let rect = polygons_bounding_rect_f64(&polys);
+            assert_eq!((rect.left, rect.top, rect.width(), rect.height()), (0.0, 0.0, 50.0, 20.0));
+            assert_eq!(polygons_extent(&polys), 50.0);
+            for &fill_rule in [FillRule::EvenOdd, FillRule::NonZero].iter() {
+                assert!(polygons_contain_point(&polys, &PointF64::new(5.0, 15.0), fill_rule));
+                assert!(!polygons_contain_point(&polys, &PointF64::new(15.0, 15.0), fill_rule));
+                assert!(polygons_contain_point(&polys, &PointF64::new(40.0, 15.0), fill_rule));
+                assert!(!polygons_contain_point(&polys, &PointF64::new(25.0, 15.0), fill_rule));
+            }
+            assert_eq!(polygons_winding_number(&polys, &PointF64::new(15.0, 15.0)), 0);
+            assert!(f64_approximately(polygons_distance_to_boundary(&polys, &PointF64::new(15.0, 16.0)), 4.0));
+            hole.props_mut().invert();
+        }
+    }
+}
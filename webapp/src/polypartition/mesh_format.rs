@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+
+use visioncortex::{Color, PointF64};
+
+use super::{Polygon, PolygonInterface};
+
+/// Triangles with their vertices shared, for the mesh formats. Points are the same vertex only if
+/// they are exactly equal, as they are between the triangles of a triangulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleMesh {
+    pub vertices: Vec<PointF64>,
+    /// Indices into `vertices`, in the order of the points of each triangle
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    pub fn from_triangles(triangles: &[Polygon]) -> Result<Self, String> {
+        let mut mesh = Self::default();
+        let mut indices = HashMap::new();
+        for (i, triangle) in triangles.iter().enumerate() {
+            if triangle.num_points() != 3 || triangle.is_hole() {
+                return Err(format!("Polygon {} is not a solid triangle", i));
+            }
+            let mut face = [0; 3];
+            for (index, p) in face.iter_mut().zip(triangle.props().points.iter()) {
+                let len = mesh.vertices.len();
+                *index = *indices.entry((p.x.to_bits(), p.y.to_bits())).or_insert(len);
+                if *index == len {
+                    mesh.vertices.push(*p);
+                }
+            }
+            mesh.faces.push(face);
+        }
+        Ok(mesh)
+    }
+
+    /// The z of the normal of the face, which is in the xy plane: 1 if its points are counter-clockwise
+    /// (by `Orientation`), -1 if clockwise, and 0 if degenerate
+    fn normal_z(&self, face: &[usize; 3]) -> f64 {
+        let [p0, p1, p2] = [self.vertices[face[0]], self.vertices[face[1]], self.vertices[face[2]]];
+        let cross = (p1.x - p0.x) * (p2.y - p0.y) - (p1.y - p0.y) * (p2.x - p0.x);
+        if cross > 0.0 { 1.0 } else if cross < 0.0 { -1.0 } else { 0.0 }
+    }
+}
+
+/// The colours, if any, must be one per triangle
+fn check_colors(triangles: &[Polygon], face_colors: Option<&[Color]>) -> Result<(), String> {
+    match face_colors {
+        Some(colors) if colors.len() != triangles.len() => Err(format!("Expected {} face colours, got {}", triangles.len(), colors.len())),
+        _ => Ok(()),
+    }
+}
+
+fn material_name(color: &Color) -> String {
+    format!("color_{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+/// Writes triangles as a Wavefront OBJ, in the z = 0 plane. With face colours, the faces use
+/// materials from `mtl_file`, which `write_mtl` writes from the same colours.
+pub fn write_obj(triangles: &[Polygon], face_colors: Option<&[Color]>, mtl_file: &str) -> Result<String, String> {
+    check_colors(triangles, face_colors)?;
+    let mesh = TriangleMesh::from_triangles(triangles)?;
+    let mut obj = vec![];
+    if face_colors.is_some() {
+        obj.push(format!("mtllib {}", mtl_file));
+    }
+    for p in mesh.vertices.iter() {
+        obj.push(format!("v {} {} 0", p.x, p.y));
+    }
+    let mut material = None;
+    for (i, face) in mesh.faces.iter().enumerate() {
+        if let Some(colors) = face_colors {
+            let name = material_name(&colors[i]);
+            if material.as_ref() != Some(&name) {
+                obj.push(format!("usemtl {}", name));
+                material = Some(name);
+            }
+        }
+        // OBJ indices count from 1
+        obj.push(format!("f {} {} {}", face[0] + 1, face[1] + 1, face[2] + 1));
+    }
+    obj.push(String::new());
+    Ok(obj.join("\n"))
+}
+
+/// Writes the material library for `write_obj`, with one diffuse material per distinct colour
+pub fn write_mtl(face_colors: &[Color]) -> String {
+    let mut mtl = vec![];
+    let mut names = vec![];
+    for color in face_colors.iter() {
+        let name = material_name(color);
+        if names.contains(&name) {
+            continue;
+        }
+        mtl.push(format!("newmtl {}", name));
+        mtl.push(format!("Kd {} {} {}", color.r as f64 / 255.0, color.g as f64 / 255.0, color.b as f64 / 255.0));
+        mtl.push(String::new());
+        names.push(name);
+    }
+    mtl.join("\n")
+}
+
+fn ply_header(mesh: &TriangleMesh, format: &str, has_colors: bool) -> String {
+    let mut header = vec![
+        "ply".to_string(),
+        format!("format {} 1.0", format),
+        format!("element vertex {}", mesh.vertices.len()),
+        "property double x".into(),
+        "property double y".into(),
+        "property double z".into(),
+        format!("element face {}", mesh.faces.len()),
+        "property list uchar int vertex_indices".into(),
+    ];
+    if has_colors {
+        header.extend(["property uchar red", "property uchar green", "property uchar blue"].iter().map(|line| line.to_string()));
+    }
+    header.push("end_header\n".into());
+    header.join("\n")
+}
+
+/// Writes triangles as an ASCII PLY, in the z = 0 plane, with optional face colours
+pub fn write_ply_ascii(triangles: &[Polygon], face_colors: Option<&[Color]>) -> Result<String, String> {
+    check_colors(triangles, face_colors)?;
+    let mesh = TriangleMesh::from_triangles(triangles)?;
+    let mut ply = ply_header(&mesh, "ascii", face_colors.is_some());
+    for p in mesh.vertices.iter() {
+        ply.push_str(&format!("{} {} 0\n", p.x, p.y));
+    }
+    for (i, face) in mesh.faces.iter().enumerate() {
+        ply.push_str(&format!("3 {} {} {}", face[0], face[1], face[2]));
+        if let Some(colors) = face_colors {
+            ply.push_str(&format!(" {} {} {}", colors[i].r, colors[i].g, colors[i].b));
+        }
+        ply.push('\n');
+    }
+    Ok(ply)
+}
+
+/// Writes triangles as a little-endian binary PLY, with the same elements as `write_ply_ascii`
+pub fn write_ply_binary(triangles: &[Polygon], face_colors: Option<&[Color]>) -> Result<Vec<u8>, String> {
+    check_colors(triangles, face_colors)?;
+    let mesh = TriangleMesh::from_triangles(triangles)?;
+    if mesh.vertices.len() > i32::MAX as usize {
+        return Err("Too many vertices for PLY".into());
+    }
+    let mut ply = ply_header(&mesh, "binary_little_endian", face_colors.is_some()).into_bytes();
+    for p in mesh.vertices.iter() {
+        for coord in [p.x, p.y, 0.0].iter() {
+            ply.extend_from_slice(&coord.to_le_bytes());
+        }
+    }
+    for (i, face) in mesh.faces.iter().enumerate() {
+        ply.push(3);
+        for &index in face.iter() {
+            ply.extend_from_slice(&(index as i32).to_le_bytes());
+        }
+        if let Some(colors) = face_colors {
+            ply.extend_from_slice(&[colors[i].r, colors[i].g, colors[i].b]);
+        }
+    }
+    Ok(ply)
+}
+
+/// Writes triangles as an ASCII STL, in the z = 0 plane. STL has no shared vertices or colours.
+pub fn write_stl_ascii(triangles: &[Polygon], name: &str) -> Result<String, String> {
+    let mesh = TriangleMesh::from_triangles(triangles)?;
+    let mut stl = vec![format!("solid {}", name)];
+    for face in mesh.faces.iter() {
+        stl.push(format!("facet normal 0 0 {}", mesh.normal_z(face)));
+        stl.push("  outer loop".into());
+        for &index in face.iter() {
+            let p = mesh.vertices[index];
+            stl.push(format!("    vertex {} {} 0", p.x as f32, p.y as f32));
+        }
+        stl.push("  endloop".into());
+        stl.push("endfacet".into());
+    }
+    stl.push(format!("endsolid {}\n", name));
+    Ok(stl.join("\n"))
+}
+
+/// Writes triangles as a binary STL. Face colours go in the attribute bytes, as 5 bits per channel
+/// with bit 15 set, in the VisCAM and SolidView convention.
+pub fn write_stl_binary(triangles: &[Polygon], face_colors: Option<&[Color]>) -> Result<Vec<u8>, String> {
+    check_colors(triangles, face_colors)?;
+    let mesh = TriangleMesh::from_triangles(triangles)?;
+    if mesh.faces.len() > u32::MAX as usize {
+        return Err("Too many triangles for STL".into());
+    }
+    let mut stl = vec![0; 80];
+    let header = b"binary STL from polypartition";
+    stl[..header.len()].copy_from_slice(header);
+    stl.extend_from_slice(&(mesh.faces.len() as u32).to_le_bytes());
+    for (i, face) in mesh.faces.iter().enumerate() {
+        let mut coords = vec![0.0, 0.0, mesh.normal_z(face) as f32];
+        for &index in face.iter() {
+            let p = mesh.vertices[index];
+            coords.extend_from_slice(&[p.x as f32, p.y as f32, 0.0]);
+        }
+        for coord in coords.iter() {
+            stl.extend_from_slice(&coord.to_le_bytes());
+        }
+        let attribute = face_colors.map_or(0, |colors| {
+            let color = colors[i];
+            0x8000 | (color.r as u16 >> 3) << 10 | (color.g as u16 >> 3) << 5 | color.b as u16 >> 3
+        });
+        stl.extend_from_slice(&attribute.to_le_bytes());
+    }
+    Ok(stl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+    use crate::polypartition::fixtures::ring;
+
+    fn square_triangles() -> Vec<Polygon> {
+        vec![ring(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.5)], false), ring(&[(0.0, 0.0), (2.0, 1.5), (0.0, 1.5)], false)]
+    }
+
+    #[test]
+    fn mesh_shares_vertices() {
+        let triangles = square_triangles();
+        let mesh = TriangleMesh::from_triangles(&triangles).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(TriangleMesh::from_triangles(&[Polygon::from_points_and_is_hole(vec![PointF64::default(); 4], false)]).is_err());
+
+        let colors = [Color::get_palette_color(0), Color::get_palette_color(1)];
+        assert_eq!(write_obj(&triangles, None, "").unwrap(), "v 0 0 0\nv 2 0 0\nv 2 1.5 0\nv 0 1.5 0\nf 1 2 3\nf 1 3 4\n");
+        let obj = write_obj(&triangles, Some(&colors), "mesh.mtl").unwrap();
+        assert!(obj.starts_with("mtllib mesh.mtl\n"));
+        assert!(obj.contains("usemtl color_D8334A\nf 1 2 3\nusemtl color_FFE860\nf 1 3 4"));
+        assert_eq!(write_mtl(&[colors[0], colors[0]]), "newmtl color_D8334A\nKd 0.8470588235294118 0.2 0.2901960784313726\n");
+        assert!(write_obj(&triangles, Some(&colors[..1]), "mesh.mtl").is_err());
+    }
+
+    #[test]
+    fn mesh_writes_ply_and_stl() {
+        let triangles = square_triangles();
+        let colors = [Color::get_palette_color(0), Color::get_palette_color(1)];
+        let ascii = write_ply_ascii(&triangles, Some(&colors)).unwrap();
+        assert!(ascii.starts_with("ply\nformat ascii 1.0\nelement vertex 4\n"));
+        assert!(ascii.ends_with("property uchar blue\nend_header\n0 0 0\n2 0 0\n2 1.5 0\n0 1.5 0\n3 0 1 2 216 51 74\n3 0 2 3 255 232 96\n"));
+
+        let binary = write_ply_binary(&triangles, Some(&colors)).unwrap();
+        let header_len = binary.windows(11).position(|window| window == b"end_header\n").unwrap() + 11;
+        assert!(binary.starts_with(b"ply\nformat binary_little_endian 1.0\n"));
+        assert_eq!(binary.len(), header_len + 4 * 3 * 8 + 2 * (1 + 3 * 4 + 3));
+        let y2 = header_len + 7 * 8;
+        assert_eq!(f64::from_le_bytes(binary[y2..y2 + 8].try_into().unwrap()), 1.5);
+        assert_eq!(&binary[binary.len() - 16..], &[3, 0, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 255, 232, 96]);
+
+        let stl = write_stl_ascii(&triangles, "square").unwrap();
+        assert!(stl.starts_with("solid square\nfacet normal 0 0 1\n  outer loop\n    vertex 0 0 0\n    vertex 2 0 0\n    vertex 2 1.5 0\n"));
+        assert!(stl.ends_with("endsolid square\n"));
+
+        let stl = write_stl_binary(&triangles, Some(&colors)).unwrap();
+        assert_eq!(stl.len(), 84 + 2 * 50);
+        assert_eq!(u32::from_le_bytes(stl[80..84].try_into().unwrap()), 2);
+        assert_eq!(f32::from_le_bytes(stl[92..96].try_into().unwrap()), 1.0);
+        assert_eq!(u16::from_le_bytes(stl[132..134].try_into().unwrap()), 0x8000 | 27 << 10 | 6 << 5 | 9);
+    }
+}
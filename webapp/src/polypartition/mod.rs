@@ -1,13 +1,59 @@
+mod art_gallery;
+mod boolean;
+mod chordal_axis;
+mod delaunay;
+mod convex_decomposition;
+mod dual_graph;
+mod generator;
+mod geo_format;
+mod half_edge_mesh;
 mod hole;
 mod enums;
+#[cfg(test)]
+mod fixtures;
+mod measurement;
+mod mesh_format;
+mod navmesh;
+mod offset;
+mod point_location;
 mod polygon;
+mod property;
+mod rectangle_partition;
+mod straight_skeleton;
+mod svg_format;
+mod text_format;
+mod trapezoidal;
 mod triangulation;
 mod util;
+mod verification;
 mod vertex;
+mod visibility;
 
+pub use art_gallery::*;
+pub use boolean::*;
+pub use chordal_axis::*;
+pub use delaunay::*;
+pub use convex_decomposition::*;
+pub use dual_graph::*;
+pub use generator::*;
+pub use geo_format::*;
+pub use half_edge_mesh::*;
 pub use hole::*;
 pub use enums::*;
+pub use measurement::*;
+pub use mesh_format::*;
+pub use navmesh::*;
+pub use offset::*;
+pub use point_location::*;
 pub use polygon::*;
+pub use property::*;
+pub use rectangle_partition::*;
+pub use straight_skeleton::*;
+pub use svg_format::*;
+pub use text_format::*;
+pub use trapezoidal::*;
 pub use triangulation::*;
 pub use util::*;
-pub use vertex::*;
\ No newline at end of file
+pub use verification::*;
+pub use vertex::*;
+pub use visibility::*;
\ No newline at end of file
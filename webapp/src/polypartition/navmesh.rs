@@ -0,0 +1,268 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use visioncortex::PointF64;
+
+use super::{DualGraph, Orientation, PointLocator, Polygon, PolygonInterface, distance, normalize, point_f64_approximately, triangulate_mono_vec_with_adjacency};
+
+/// A navigation mesh over the triangles or convex pieces of a partition.
+///
+/// Paths are searched with A* over the dual graph and straightened with the
+/// simple stupid funnel algorithm.
+pub struct NavMesh {
+    pub graph: DualGraph,
+    locator: PointLocator,
+    /// Whether each piece is wound counter-clockwise (by `Orientation`)
+    is_ccw: Vec<bool>,
+}
+
+/// A piece/portal on the open list of A*
+struct SearchNode {
+    cost: f64,
+    estimate: f64,
+    piece: usize,
+}
+
+impl PartialEq for SearchNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SearchNode {}
+
+impl PartialOrd for SearchNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SearchNode {
+    // Reversed, so that BinaryHeap pops the lowest estimate first, then the lowest piece
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then(other.piece.cmp(&self.piece))
+    }
+}
+
+impl NavMesh {
+    /// The pieces of the graph must be convex (e.g. triangles)
+    pub fn new(graph: DualGraph) -> Self {
+        let is_ccw = (0..graph.num_pieces())
+            .map(|piece| graph.get_piece(piece).props().get_orientation() == Orientation::CounterClockwise)
+            .collect();
+        Self {
+            locator: PointLocator::from_dual_graph(&graph),
+            graph,
+            is_ccw,
+        }
+    }
+
+    /// Triangulates the polygons (holes allowed) and builds a navigation mesh on the triangles
+    pub fn from_polygons(polys: Vec<Polygon>) -> Result<Self, String> {
+        Ok(Self::new(triangulate_mono_vec_with_adjacency(polys)?))
+    }
+
+    /// Find the piece containing p, if any
+    pub fn locate(&self, p: &PointF64) -> Option<usize> {
+        self.locator.locate(p)
+    }
+
+    /// Find a path from start to goal inside the mesh.
+    ///
+    /// A* picks a corridor of pieces, costing each step by the distance between portal midpoints,
+    /// and the funnel algorithm straightens the path through that corridor. So the path is the shortest
+    /// within its corridor, but another corridor may hold a shorter one.
+    ///
+    /// With a positive `agent_radius`, portals no wider than the agent are skipped and the others are
+    /// shrunk by the radius at both ends. The path then turns `agent_radius` away from each corner along
+    /// the portal, though it may pass closer to the corner between portals.
+    pub fn find_path(&self, start: PointF64, goal: PointF64, agent_radius: f64) -> Result<Vec<PointF64>, &'static str> {
+        let start_piece = if let Some(piece) = self.locate(&start) { piece } else {
+            return Err("Start point is not inside the mesh.");
+        };
+        let goal_piece = if let Some(piece) = self.locate(&goal) { piece } else {
+            return Err("Goal point is not inside the mesh.");
+        };
+
+        let corridor = self.find_corridor(start, start_piece, goal, goal_piece, agent_radius)?;
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let (left, right) = self.get_portal(pair[0], pair[1])?;
+            let direction = normalize(&(right - left));
+            let shrink = PointF64::new(direction.x * agent_radius, direction.y * agent_radius);
+            portals.push((left + shrink, right - shrink));
+        }
+        portals.push((goal, goal));
+
+        Ok(string_pull(&portals))
+    }
+
+    /// A* over the dual graph, entering each piece at the midpoint of a portal.
+    /// Returns the sequence of pieces from start to goal.
+    fn find_corridor(&self, start: PointF64, start_piece: usize, goal: PointF64, goal_piece: usize, agent_radius: f64) -> Result<Vec<usize>, &'static str> {
+        let num_pieces = self.graph.num_pieces();
+        let mut positions = vec![start; num_pieces];
+        let mut costs = vec![f64::MAX; num_pieces];
+        let mut came_from = vec![None; num_pieces];
+        let mut open = BinaryHeap::new();
+
+        costs[start_piece] = 0.0;
+        open.push(SearchNode { cost: 0.0, estimate: distance(&start, &goal), piece: start_piece });
+
+        while let Some(SearchNode { cost, piece, .. }) = open.pop() {
+            if piece == goal_piece {
+                let mut corridor = vec![goal_piece];
+                while let Some(previous) = came_from[*corridor.last().unwrap()] {
+                    corridor.push(previous);
+                }
+                corridor.reverse();
+                return Ok(corridor);
+            }
+            if cost > costs[piece] {
+                continue;
+            }
+            for edge in self.graph.neighbors(piece).iter() {
+                let (p1, p2) = self.graph.get_diagonal(edge);
+                if distance(&p1, &p2) <= 2.0 * agent_radius {
+                    continue;
+                }
+                let midpoint = PointF64::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0);
+                let neighbor_cost = cost + distance(&positions[piece], &midpoint);
+                if neighbor_cost < costs[edge.piece] {
+                    costs[edge.piece] = neighbor_cost;
+                    positions[edge.piece] = midpoint;
+                    came_from[edge.piece] = Some(piece);
+                    open.push(SearchNode {
+                        cost: neighbor_cost,
+                        estimate: neighbor_cost + distance(&midpoint, &goal),
+                        piece: edge.piece,
+                    });
+                }
+            }
+        }
+
+        Err("Goal is not reachable from start.")
+    }
+
+    /// The (left, right) endpoints of the diagonal crossed when walking from one piece to the other
+    fn get_portal(&self, from: usize, to: usize) -> Result<(PointF64, PointF64), &'static str> {
+        let edge = self.graph.neighbors(from).iter().find(|edge| edge.piece == to)
+            .ok_or("Pieces do not share a portal.")?;
+        let (p1, p2) = self.graph.get_diagonal(edge);
+        // The interior of a counter-clockwise piece is on the left of its edges,
+        // so walking out of it, the end of the edge is on the left
+        Ok(if self.is_ccw[from] { (p2, p1) } else { (p1, p2) })
+    }
+}
+
+/// Twice the signed area of the triangle, with the sign convention of the funnel algorithm
+fn triarea2(a: &PointF64, b: &PointF64, c: &PointF64) -> f64 {
+    let (ax, ay) = (b.x - a.x, b.y - a.y);
+    let (bx, by) = (c.x - a.x, c.y - a.y);
+    bx * ay - ax * by
+}
+
+/// The simple stupid funnel algorithm.
+///
+/// The first and last portals are the degenerate (start, start) and (goal, goal).
+fn string_pull(portals: &[(PointF64, PointF64)]) -> Vec<PointF64> {
+    let mut path = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_index, mut right_index) = (0, 0);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (new_left, new_right) = portals[i];
+
+        // Update the right side of the funnel
+        if triarea2(&apex, &right, &new_right) <= 0.0 {
+            if point_f64_approximately(apex, right) || triarea2(&apex, &left, &new_right) > 0.0 {
+                right = new_right;
+                right_index = i;
+            } else {
+                // Right crossed over left: left becomes the new apex
+                path.push(left);
+                apex = left;
+                right = apex;
+                right_index = left_index;
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        // Update the left side of the funnel
+        if triarea2(&apex, &left, &new_left) >= 0.0 {
+            if point_f64_approximately(apex, left) || triarea2(&apex, &right, &new_left) < 0.0 {
+                left = new_left;
+                left_index = i;
+            } else {
+                // Left crossed over right: right becomes the new apex
+                path.push(right);
+                apex = right;
+                left = apex;
+                left_index = right_index;
+                i = right_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    let goal = portals[portals.len() - 1].0;
+    if !point_f64_approximately(*path.last().unwrap(), goal) {
+        path.push(goal);
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::intersects;
+    use crate::polypartition::fixtures::hexagon_with_hole;
+
+    #[test]
+    fn navmesh_straight_line() {
+        let navmesh = NavMesh::from_polygons(vec![hexagon_with_hole()[0].clone()]).unwrap();
+        let path = navmesh.find_path(PointF64::new(50.0, 110.0), PointF64::new(210.0, 110.0), 0.0).unwrap();
+        assert_eq!(path, vec![PointF64::new(50.0, 110.0), PointF64::new(210.0, 110.0)]);
+    }
+
+    #[test]
+    fn navmesh_around_hole() {
+        let polys = hexagon_with_hole();
+        let navmesh = NavMesh::from_polygons(polys.clone()).unwrap();
+        let (start, goal) = (PointF64::new(100.0, 60.0), PointF64::new(120.0, 160.0));
+        let path = navmesh.find_path(start, goal, 0.0).unwrap();
+        // The shortest path bends around the left corner of the hole
+        assert_eq!(path, vec![start, PointF64::new(90.0, 140.0), goal]);
+
+        let path = navmesh.find_path(start, goal, 5.0).unwrap();
+        // Every turn keeps the agent radius from the corner
+        let corner = PointF64::new(90.0, 140.0);
+        assert!(path[1..path.len()-1].iter().all(|p| distance(p, &corner) >= 5.0 - 1e-7));
+        for segment in path.windows(2) {
+            let hole = &polys[1].props().points;
+            for i in 0..hole.len() {
+                assert!(!intersects(&segment[0], &segment[1], &hole[i], &hole[(i+1) % hole.len()]));
+            }
+        }
+
+        assert!(navmesh.find_path(PointF64::new(120.0, 100.0), goal, 0.0).is_err());
+        assert!(navmesh.get_portal(0, 0).is_err());
+    }
+
+    #[test]
+    fn navmesh_search_order() {
+        let node = |estimate, piece| SearchNode { cost: 0.0, estimate, piece };
+        let mut open = BinaryHeap::new();
+        for &(estimate, piece) in [(2.0, 0), (1.0, 3), (1.0, 1), (f64::NAN, 2)].iter() {
+            open.push(node(estimate, piece));
+        }
+        let order: Vec<usize> = std::iter::from_fn(|| open.pop().map(|node| node.piece)).collect();
+        assert_eq!(order, [1, 3, 0, 2]);
+        // Equal estimates of different pieces are not the same node
+        assert!(node(1.0, 1) != node(1.0, 3));
+    }
+}
@@ -0,0 +1,254 @@
+use visioncortex::{PathF64, PointF64};
+
+use super::{BooleanOp, FillRule, JoinType, Polygon, PolygonInterface, boolean_op, boolean_op_with_fill_rule, f64_approximately, normalize, signed_area};
+
+/// Parameters of `offset_polygons` and `stroke_path`
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetOptions {
+    pub join_type: JoinType,
+    /// Longest allowed miter, as a multiple of the offset; longer miters are squared off
+    pub miter_limit: f64,
+    /// Largest distance between a round join and its true arc
+    pub arc_tolerance: f64,
+}
+
+impl Default for OffsetOptions {
+    fn default() -> Self {
+        Self {
+            join_type: JoinType::Miter,
+            miter_limit: 2.0,
+            arc_tolerance: 0.25,
+        }
+    }
+}
+
+impl OffsetOptions {
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.arc_tolerance > 0.0 && self.miter_limit >= 1.0 {
+            Ok(())
+        } else {
+            Err("Arc tolerance must be positive and miter limit at least 1.")
+        }
+    }
+}
+
+/// Grows (positive `delta`) or shrinks (negative `delta`) polygons with holes.
+///
+/// Every edge is swept sideways by the offset into a band, with the corners filled by joins.
+/// The band is added to the polygons for an outset, and subtracted for an inset. So an inset
+/// splits where the shape pinches, and an outset merges polygons and closes holes that meet.
+/// The result keeps the `is_hole` convention of `boolean_op`.
+pub fn offset_polygons(polys: &[Polygon], delta: f64, options: OffsetOptions) -> Result<Vec<Polygon>, &'static str> {
+    options.validate()?;
+    // Orient the rings by the `is_hole` convention, so that joins go to the right side
+    let polys = boolean_op(polys, &[], BooleanOp::Union)?;
+    if delta == 0.0 {
+        return Ok(polys);
+    }
+
+    let mut band = vec![];
+    for poly in polys.iter() {
+        add_band(&mut band, &poly.props().points, true, delta.abs(), &options);
+    }
+    let op = if delta > 0.0 { BooleanOp::Union } else { BooleanOp::Difference };
+    boolean_op_with_fill_rule(&polys, &band, op, FillRule::NonZero)
+}
+
+/// The outline of a path stroked with the given width
+pub fn stroke_path(path: &PathF64, width: f64, closed: bool, options: OffsetOptions) -> Result<Vec<Polygon>, &'static str> {
+    options.validate()?;
+    let mut points = path.path.clone();
+    points.dedup();
+    if closed && points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    if points.len() < 2 {
+        return Err("Path has less than 2 distinct points.");
+    }
+
+    let mut band = vec![];
+    add_band(&mut band, &points, closed, width / 2.0, &options);
+    boolean_op_with_fill_rule(&band, &[], BooleanOp::Union, FillRule::NonZero)
+}
+
+/// Adds the counter-clockwise pieces sweeping each edge of the ring or path sideways by the offset,
+/// and the joins filling the gaps they leave at corners
+fn add_band(band: &mut Vec<Polygon>, points: &[PointF64], closed: bool, offset: f64, options: &OffsetOptions) {
+    let len = points.len();
+    let num_edges = if closed { len } else { len - 1 };
+    let right_normal = |p1: &PointF64, p2: &PointF64| {
+        let direction = normalize(&(*p2 - *p1));
+        PointF64::new(direction.y * offset, -direction.x * offset)
+    };
+
+    for curr in 0..num_edges {
+        let (p1, p2) = (points[curr], points[(curr+1) % len]);
+        let n = right_normal(&p1, &p2);
+        push_ccw(band, vec![p1 + n, p2 + n, p2 - n, p1 - n]);
+    }
+
+    let corners = if closed { 0..len } else { 1..len-1 };
+    for curr in corners {
+        let (prev, v, next) = (points[(curr+len-1) % len], points[curr], points[(curr+1) % len]);
+        let (d1, d2) = (normalize(&(v - prev)), normalize(&(next - v)));
+        let turn = d1.x * d2.y - d1.y * d2.x;
+        if f64_approximately(turn, 0.0) && d1.dot(d2) > 0.0 {
+            continue;
+        }
+        // The edge pieces leave a gap on the outer side of the turn
+        let (n1, n2) = if turn >= 0.0 {
+            (right_normal(&prev, &v), right_normal(&v, &next))
+        } else {
+            (PointF64::new(0.0, 0.0) - right_normal(&prev, &v), PointF64::new(0.0, 0.0) - right_normal(&v, &next))
+        };
+        push_ccw(band, join(v, (n1, n2), (d1, d2), offset, options));
+    }
+}
+
+/// The wedge at corner v between the offset edges v + n1 and v + n2, of directions d1 and d2
+fn join(v: PointF64, (n1, n2): (PointF64, PointF64), (d1, d2): (PointF64, PointF64), offset: f64, options: &OffsetOptions) -> Vec<PointF64> {
+    let bisector = normalize(&(n1 + n2));
+    let cos_half_angle = normalize(&n1).dot(bisector);
+    let scaled = |p: PointF64, scale: f64| PointF64::new(p.x * scale, p.y * scale);
+
+    match options.join_type {
+        JoinType::Miter if cos_half_angle > 0.0 && 1.0 / cos_half_angle <= options.miter_limit => {
+            vec![v, v + n1, v + scaled(bisector, offset / cos_half_angle), v + n2]
+        },
+        JoinType::Round => {
+            let angle_1 = n1.y.atan2(n1.x);
+            let mut sweep = n2.y.atan2(n2.x) - angle_1;
+            // Sweep the short way round, except for a full turnaround
+            let pi = std::f64::consts::PI;
+            if sweep > pi {
+                sweep -= 2.0 * pi;
+            } else if sweep < -pi {
+                sweep += 2.0 * pi;
+            }
+            let step = 2.0 * (1.0 - (options.arc_tolerance / offset).min(1.0)).acos();
+            let steps = ((sweep.abs() / step).ceil() as usize).max(1);
+            let mut wedge = vec![v, v + n1];
+            for i in 1..steps {
+                let angle = angle_1 + sweep * i as f64 / steps as f64;
+                wedge.push(v + PointF64::new(offset * angle.cos(), offset * angle.sin()));
+            }
+            wedge.push(v + n2);
+            wedge
+        },
+        _ => {
+            // Square off at the offset distance along the bisector (or the first edge, for a turnaround)
+            let cap_normal = if bisector.norm() > 0.0 { bisector } else { d1 };
+            let extend = |n: PointF64, direction: PointF64| {
+                let rate = direction.dot(cap_normal);
+                let s = if f64_approximately(rate, 0.0) { 0.0 } else { (offset - n.dot(cap_normal)) / rate };
+                v + n + scaled(direction, s)
+            };
+            vec![v, v + n1, extend(n1, d1), extend(n2, PointF64::new(0.0, 0.0) - d2), v + n2]
+        },
+    }
+}
+
+fn push_ccw(band: &mut Vec<Polygon>, mut points: Vec<PointF64>) {
+    points.dedup();
+    let area = signed_area(&points);
+    if points.len() < 3 || area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        points.reverse();
+    }
+    band.push(Polygon::from_points_and_is_hole(points, false));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::polygons_area;
+    use crate::polypartition::fixtures::{ring, square};
+
+    #[test]
+    fn offset_joins() {
+        let room = vec![square(0.0, 0.0, 100.0, false)];
+        let with_join = |join_type| OffsetOptions { join_type, ..OffsetOptions::default() };
+
+        let miter = offset_polygons(&room, 10.0, with_join(JoinType::Miter)).unwrap();
+        assert_eq!(miter.len(), 1);
+        assert_eq!(miter[0].num_points(), 4);
+        assert!(f64_approximately(polygons_area(&miter), 14400.0));
+
+        let round = offset_polygons(&room, 10.0, with_join(JoinType::Round)).unwrap();
+        // The arcs are approximated by chords, within the arc tolerance
+        let expected = 14000.0 + std::f64::consts::PI * 100.0;
+        assert!(polygons_area(&round) < expected && polygons_area(&round) > expected - 0.25 * 2.0 * std::f64::consts::PI * 10.0);
+
+        // Squared off at distance 10 from the corner, a bit outside the round join
+        let square_join = offset_polygons(&room, 10.0, with_join(JoinType::Square)).unwrap();
+        assert_eq!(square_join[0].num_points(), 8);
+        assert!(polygons_area(&square_join) > polygons_area(&round) && polygons_area(&square_join) < 14400.0);
+
+        // A miter limit below sqrt(2) squares off right angles
+        let limited = OffsetOptions { miter_limit: 1.2, ..OffsetOptions::default() };
+        assert_eq!(offset_polygons(&room, 10.0, limited).unwrap()[0].num_points(), 8);
+
+        let inset = offset_polygons(&room, -10.0, with_join(JoinType::Round)).unwrap();
+        assert!(f64_approximately(polygons_area(&inset), 6400.0));
+    }
+
+    #[test]
+    fn offset_splits_and_merges() {
+        // A dumbbell: two rooms joined by a corridor 4 wide, which an inset of 3 closes
+        let dumbbell = boolean_op(
+            &[square(0.0, 0.0, 40.0, false), square(60.0, 0.0, 40.0, false)],
+            &[ring(&[(30.0, 18.0), (70.0, 18.0), (70.0, 22.0), (30.0, 22.0)], false)],
+            BooleanOp::Union,
+        ).unwrap();
+        assert_eq!(dumbbell.len(), 1);
+        let eroded = offset_polygons(&dumbbell, -3.0, OffsetOptions::default()).unwrap();
+        assert_eq!(eroded.len(), 2);
+        assert!(f64_approximately(polygons_area(&eroded), 2.0 * 34.0 * 34.0));
+
+        // An outset merges the rooms, and closes a hole
+        let rooms = [square(0.0, 0.0, 40.0, false), square(46.0, 0.0, 40.0, false)];
+        assert_eq!(offset_polygons(&rooms, 2.0, OffsetOptions::default()).unwrap().len(), 2);
+        assert_eq!(offset_polygons(&rooms, 4.0, OffsetOptions::default()).unwrap().len(), 1);
+        let frame = vec![square(0.0, 0.0, 100.0, false), square(40.0, 40.0, 6.0, true)];
+        assert_eq!(offset_polygons(&frame, 2.0, OffsetOptions::default()).unwrap().len(), 2);
+        assert_eq!(offset_polygons(&frame, -4.0, OffsetOptions::default()).unwrap().len(), 2);
+        assert_eq!(offset_polygons(&frame, 4.0, OffsetOptions::default()).unwrap().len(), 1);
+        assert_eq!(offset_polygons(&rooms[..1], -25.0, OffsetOptions::default()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn offset_small_coordinates() {
+        // Lon/lat parcels are this small
+        let parcel = vec![square(0.0, 0.0, 1e-4, false)];
+        let outset = offset_polygons(&parcel, 1e-5, OffsetOptions::default()).unwrap();
+        assert_eq!(outset.len(), 1);
+        assert_eq!(outset[0].num_points(), 4);
+        assert!((polygons_area(&outset) - 1.44e-8).abs() < 1e-9 * 1.44e-8);
+        let inset = offset_polygons(&parcel, -1e-5, OffsetOptions::default()).unwrap();
+        assert!((polygons_area(&inset) - 6.4e-9).abs() < 1e-9 * 6.4e-9);
+    }
+
+    #[test]
+    fn offset_stroke_path() {
+        let path = PathF64 { path: vec![PointF64::new(0.0, 0.0), PointF64::new(100.0, 0.0), PointF64::new(100.0, 100.0)] };
+        let outline = stroke_path(&path, 10.0, false, OffsetOptions::default()).unwrap();
+        assert_eq!(outline.len(), 1);
+        // The miter fills the outer corner exactly as much as the edges overlap on the inner one
+        assert!(f64_approximately(polygons_area(&outline), 2000.0));
+
+        let ring = stroke_path(&path, 10.0, true, OffsetOptions::default()).unwrap();
+        assert_eq!(ring.iter().filter(|poly| poly.is_hole()).count(), 1);
+    }
+
+    #[test]
+    fn offset_rejects_bad_options() {
+        let round = OffsetOptions { join_type: JoinType::Round, arc_tolerance: 0.0, ..OffsetOptions::default() };
+        assert!(offset_polygons(&[square(0.0, 0.0, 10.0, false)], 1.0, round).is_err());
+        let path = PathF64 { path: vec![PointF64::new(0.0, 0.0), PointF64::new(10.0, 0.0), PointF64::new(10.0, 10.0)] };
+        assert!(stroke_path(&path, 2.0, false, round).is_err());
+        let miter = OffsetOptions { miter_limit: 0.5, ..OffsetOptions::default() };
+        assert!(offset_polygons(&[square(0.0, 0.0, 10.0, false)], 1.0, miter).is_err());
+    }
+}
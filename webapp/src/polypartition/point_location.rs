@@ -0,0 +1,203 @@
+use visioncortex::PointF64;
+
+use super::{DualGraph, Polygon, Trapezoid, trapezoid_sweep};
+
+/// Answers which piece of a partition contains a point.
+///
+/// Each piece is cut into trapezoids by `trapezoid_sweep`. The trapezoids are kept in a segment tree
+/// over the y of their tops and bottoms, where each node holds the trapezoids spanning its whole range
+/// from left to right. Building takes O(n log² n) time and O(n log n) memory for n vertices,
+/// and a query searches the O(log n) nodes above one slab, in O(log² n).
+#[derive(Clone, Debug, Default)]
+pub struct PointLocator {
+    /// Distinct y of the tops and bottoms of all trapezoids, ascending
+    ys: Vec<f64>,
+    /// The segment tree over the slabs between consecutive ys: node 1 is the root, and node i has children 2i and 2i+1
+    nodes: Vec<Vec<Span>>,
+}
+
+/// A trapezoid of a piece, between two of its edges
+#[derive(Clone, Debug)]
+struct Span {
+    piece: usize,
+    left: (PointF64, PointF64),
+    right: (PointF64, PointF64),
+}
+
+/// x of the (non-horizontal) edge at y
+fn x_at(edge: &(PointF64, PointF64), y: f64) -> f64 {
+    let (p1, p2) = edge;
+    p1.x + (y - p1.y) / (p2.y - p1.y) * (p2.x - p1.x)
+}
+
+impl PointLocator {
+    /// The pieces must not overlap. They need not be convex, and may have holes bridged in.
+    pub fn new(pieces: &[Polygon]) -> Self {
+        let trapezoids = pieces.iter().enumerate()
+            .filter(|(_, piece)| piece.is_valid())
+            .flat_map(|(piece_index, piece)| {
+                trapezoid_sweep(std::slice::from_ref(piece)).into_iter().map(move |trapezoid| (piece_index, trapezoid))
+            })
+            .collect();
+        Self::from_pieces_trapezoids(trapezoids)
+    }
+
+    pub fn from_dual_graph(graph: &DualGraph) -> Self {
+        Self::new(&graph.polygons())
+    }
+
+    /// Locates the trapezoids themselves, each being the piece of its index
+    pub fn from_trapezoids(trapezoids: &[Trapezoid]) -> Self {
+        Self::from_pieces_trapezoids(trapezoids.iter().copied().enumerate().collect())
+    }
+
+    fn from_pieces_trapezoids(trapezoids: Vec<(usize, Trapezoid)>) -> Self {
+        let mut ys: Vec<f64> = trapezoids.iter().flat_map(|(_, trapezoid)| vec![trapezoid.top, trapezoid.bottom]).collect();
+        ys.sort_by(|y1, y2| y1.partial_cmp(y2).unwrap());
+        ys.dedup();
+        if ys.len() < 2 {
+            return Self::default();
+        }
+
+        let num_slabs = ys.len() - 1;
+        let mut nodes = vec![vec![]; 4 * num_slabs];
+        for (piece, trapezoid) in trapezoids.into_iter() {
+            let first = ys.partition_point(|&y| y < trapezoid.top);
+            let last = ys.partition_point(|&y| y < trapezoid.bottom);
+            let span = Span { piece, left: trapezoid.left, right: trapezoid.right };
+            insert_span(&mut nodes, 1, 0, num_slabs, first, last, &span);
+        }
+        sort_spans(&mut nodes, &ys, 1, 0, num_slabs);
+
+        Self {
+            ys,
+            nodes,
+        }
+    }
+
+    /// Find the index of the piece containing p, if any.
+    ///
+    /// A point on the boundary between pieces belongs to any one of them.
+    pub fn locate(&self, p: &PointF64) -> Option<usize> {
+        if self.nodes.is_empty() || p.y < self.ys[0] || p.y > self.ys[self.ys.len() - 1] {
+            return None;
+        }
+        let num_slabs = self.ys.len() - 1;
+        let slab = (self.ys.partition_point(|&y| y <= p.y) - 1).min(num_slabs - 1);
+        // On the line between two slabs, the trapezoids above and below it both reach p
+        self.locate_in_slab(slab, p).or_else(|| {
+            if slab > 0 && p.y == self.ys[slab] {
+                self.locate_in_slab(slab - 1, p)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn locate_in_slab(&self, slab: usize, p: &PointF64) -> Option<usize> {
+        let (mut node, mut lo, mut hi) = (1, 0, self.ys.len() - 1);
+        loop {
+            let spans = &self.nodes[node];
+            let span = spans.partition_point(|span| x_at(&span.right, p.y) < p.x);
+            if span < spans.len() && x_at(&spans[span].left, p.y) <= p.x {
+                return Some(spans[span].piece);
+            }
+            if hi - lo == 1 {
+                return None;
+            }
+            let mid = (lo + hi) / 2;
+            if slab < mid {
+                node *= 2;
+                hi = mid;
+            } else {
+                node = 2 * node + 1;
+                lo = mid;
+            }
+        }
+    }
+}
+
+/// Adds the span to the nodes covering slabs first..last, below the node of slabs lo..hi
+fn insert_span(nodes: &mut [Vec<Span>], node: usize, lo: usize, hi: usize, first: usize, last: usize, span: &Span) {
+    if last <= lo || hi <= first {
+        return;
+    }
+    if first <= lo && hi <= last {
+        nodes[node].push(span.clone());
+        return;
+    }
+    let mid = (lo + hi) / 2;
+    insert_span(nodes, 2 * node, lo, mid, first, last, span);
+    insert_span(nodes, 2 * node + 1, mid, hi, first, last, span);
+}
+
+/// Orders the spans of each node from left to right. They all cross its slabs, so the middle of them will do.
+fn sort_spans(nodes: &mut [Vec<Span>], ys: &[f64], node: usize, lo: usize, hi: usize) {
+    let y_mid = (ys[lo] + ys[hi]) / 2.0;
+    nodes[node].sort_by(|span1, span2| x_at(&span1.left, y_mid).partial_cmp(&x_at(&span2.left, y_mid)).unwrap());
+    if hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        sort_spans(nodes, ys, 2 * node, lo, mid);
+        sort_spans(nodes, ys, 2 * node + 1, mid, hi);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{PolygonInterface, is_inside_ring, monotone_partition, orient2d, remove_holes, triangulate_ec_vec};
+    use crate::polypartition::fixtures::hexagon_with_hole;
+
+    /// Whether p is inside the piece or on one of its edges
+    fn touches(piece: &Polygon, p: &PointF64) -> bool {
+        let points = &piece.props().points;
+        is_inside_ring(points, p) || (0..points.len()).any(|i| {
+            let (p1, p2) = (points[i], points[(i+1) % points.len()]);
+            orient2d(&p1, &p2, p) == 0.0
+                && p1.x.min(p2.x) <= p.x && p.x <= p1.x.max(p2.x)
+                && p1.y.min(p2.y) <= p.y && p.y <= p1.y.max(p2.y)
+        })
+    }
+
+    #[test]
+    fn point_location_matches_linear_search() {
+        let polys = hexagon_with_hole();
+        let triangles = triangulate_ec_vec(remove_holes(&polys).unwrap()).unwrap();
+        let monotone_pieces = monotone_partition(polys).unwrap();
+
+        for pieces in [triangles, monotone_pieces].iter() {
+            let locator = PointLocator::new(pieces);
+            for x in (30..230).step_by(7) {
+                for y in (30..190).step_by(7) {
+                    let p = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+                    let expected = pieces.iter().position(|piece| is_inside_ring(&piece.props().points, &p));
+                    assert_eq!(locator.locate(&p), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn point_location_on_boundaries() {
+        let polys = hexagon_with_hole();
+        let triangles = triangulate_ec_vec(remove_holes(&polys).unwrap()).unwrap();
+        let locator = PointLocator::new(&triangles);
+        for triangle in triangles.iter() {
+            let points = &triangle.props().points;
+            for i in 0..3 {
+                let (p1, p2) = (points[i], points[(i+1) % 3]);
+                let midpoint = PointF64::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0);
+                for p in [p1, midpoint].iter() {
+                    let piece = locator.locate(p).unwrap();
+                    assert!(touches(&triangles[piece], p));
+                }
+            }
+        }
+
+        // Outside the hexagon, and inside the hole
+        for p in [PointF64::new(0.0, 0.0), PointF64::new(130.0, 20.0), PointF64::new(230.0, 110.0),
+            PointF64::new(130.0, 200.0), PointF64::new(130.0, 100.0), PointF64::new(45.0, 50.0)].iter() {
+            assert_eq!(locator.locate(p), None);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use visioncortex::PointF64;
+
+use super::{GeneratorOptions, PartitionAlgorithm, PieceShape, Polygon, PolygonGenerator, PolygonInterface, SplitMix64, generate_polygons, is_valid_polygon_set, remove_holes, triangulate_ec_vec, triangulate_mono_vec, triangulate_opt_vec, verify_partition, write_polygons};
+
+/// A random case that broke a property, with the smallest reproduction found
+#[derive(Clone, Debug)]
+pub struct PropertyFailure {
+    pub options: GeneratorOptions,
+    pub message: String,
+    pub shrunk: Vec<Polygon>,
+    /// The failure of the shrunk polygons, which may differ from the original one
+    pub shrunk_message: String,
+}
+
+impl fmt::Display for PropertyFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = &self.options;
+        writeln!(f, "{:?} with {} vertices, {} holes and seed {}: {}",
+            options.generator, options.num_vertices, options.num_holes, options.seed, self.message)?;
+        writeln!(f, "Shrunk to a case that fails with: {}", self.shrunk_message)?;
+        writeln!(f, "{}", write_polygons(&self.shrunk, true))
+    }
+}
+
+/// Runs the algorithm on valid input polygons, and checks that it neither panics nor fails, and that
+/// its output partitions the input with consistent orientation: one polygon per solid with 2 more
+/// points per hole for `RemoveHoles`, and n + 2h - 2 triangles per solid for the triangulations.
+///
+/// `OptimalDp` does not support holes, so it is checked on the solids only.
+pub fn check_algorithm(algorithm: PartitionAlgorithm, polys: &[Polygon]) -> Result<(), String> {
+    let input: Vec<Polygon> = match algorithm {
+        PartitionAlgorithm::OptimalDp => polys.iter().filter(|poly| !poly.is_hole()).cloned().collect(),
+        _ => polys.to_vec(),
+    };
+    let num_points: usize = input.iter().map(|poly| poly.num_points()).sum();
+    let num_holes = input.iter().filter(|poly| poly.is_hole()).count();
+    let num_solids = input.len() - num_holes;
+
+    let run = || -> Result<Vec<Polygon>, String> {
+        match algorithm {
+            PartitionAlgorithm::RemoveHoles => Ok(remove_holes(&input)?),
+            PartitionAlgorithm::EarClipping => triangulate_ec_vec(remove_holes(&input)?),
+            PartitionAlgorithm::Monotone => triangulate_mono_vec(input.clone()),
+            PartitionAlgorithm::OptimalDp => triangulate_opt_vec(input.clone()),
+        }
+    };
+    let output = match panic::catch_unwind(AssertUnwindSafe(run)) {
+        Ok(result) => result?,
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            return Err(format!("Panicked: {}", message));
+        },
+    };
+
+    if algorithm == PartitionAlgorithm::RemoveHoles {
+        let output_points: usize = output.iter().map(|poly| poly.num_points()).sum();
+        if output.len() != num_solids || output_points != num_points + 2 * num_holes {
+            return Err(format!("Expected {} polygons with {} points, got {} with {}",
+                num_solids, num_points + 2 * num_holes, output.len(), output_points));
+        }
+        return verify_partition(&input, &output, PieceShape::Any);
+    }
+    let expected = num_points + 2 * num_holes - 2 * num_solids;
+    if output.len() != expected {
+        return Err(format!("Expected {} triangles, got {}", expected, output.len()));
+    }
+    verify_partition(&input, &output, PieceShape::Triangle)
+}
+
+/// Repeatedly removes a hole or a vertex, or rounds the points to integers, for as long as the
+/// polygons stay valid (by `is_valid_polygon_set`) and still fail. Returns the smallest failing polygons.
+pub fn shrink_polygons(polys: &[Polygon], fails: impl Fn(&[Polygon]) -> bool) -> Vec<Polygon> {
+    let mut current = polys.to_vec();
+    while let Some(smaller) = smaller_cases(&current).into_iter().find(|case| is_valid_polygon_set(case) && fails(case)) {
+        current = smaller;
+    }
+    current
+}
+
+fn smaller_cases(polys: &[Polygon]) -> Vec<Vec<Polygon>> {
+    let mut cases = vec![];
+    let is_integral = polys.iter().flat_map(|poly| poly.props().points.iter())
+        .all(|p| p.x.fract() == 0.0 && p.y.fract() == 0.0);
+    if !is_integral {
+        cases.push(polys.iter().map(|poly| {
+            let points = poly.props().points.iter().map(|p| PointF64::new(p.x.round(), p.y.round())).collect();
+            Polygon::from_points_and_is_hole(points, poly.is_hole())
+        }).collect());
+    }
+    for (i, poly) in polys.iter().enumerate() {
+        if poly.is_hole() {
+            let mut case = polys.to_vec();
+            case.remove(i);
+            cases.push(case);
+        }
+    }
+    for (i, poly) in polys.iter().enumerate() {
+        if poly.num_points() <= 3 {
+            continue;
+        }
+        for j in 0..poly.num_points() {
+            let mut points = poly.props().points.clone();
+            points.remove(j);
+            let mut case = polys.to_vec();
+            case[i] = Polygon::from_points_and_is_hole(points, poly.is_hole());
+            cases.push(case);
+        }
+    }
+    cases
+}
+
+/// Checks the algorithm on random polygons from every generator, with 4 to 40 vertices and up to 3 holes.
+/// Stops at the first failure, shrunk to a small reproduction.
+pub fn fuzz_algorithm(algorithm: PartitionAlgorithm, num_cases: usize, seed: u64) -> Result<(), PropertyFailure> {
+    let mut rng = SplitMix64::new(seed);
+    for _ in 0..num_cases {
+        let options = GeneratorOptions {
+            generator: PolygonGenerator::ALL[rng.index(PolygonGenerator::ALL.len())],
+            num_vertices: 4 + rng.index(37),
+            num_holes: rng.index(4),
+            seed: rng.next_u64(),
+            ..Default::default()
+        };
+        let polys = match generate_polygons(&options) {
+            Ok(polys) => polys,
+            Err(message) => return Err(PropertyFailure { options, message, shrunk: vec![], shrunk_message: String::new() }),
+        };
+        if let Err(message) = check_algorithm(algorithm, &polys) {
+            let shrunk = shrink_polygons(&polys, |case| check_algorithm(algorithm, case).is_err());
+            let shrunk_message = check_algorithm(algorithm, &shrunk).unwrap_err();
+            return Err(PropertyFailure { options, message, shrunk, shrunk_message });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::fixtures::ring;
+
+    const ALGORITHMS: [PartitionAlgorithm; 4] = [
+        PartitionAlgorithm::RemoveHoles, PartitionAlgorithm::EarClipping,
+        PartitionAlgorithm::Monotone, PartitionAlgorithm::OptimalDp,
+    ];
+
+    #[test]
+    fn property_all_algorithms() {
+        // Set POLYPARTITION_FUZZ_CASES for a longer run
+        let num_cases = std::env::var("POLYPARTITION_FUZZ_CASES").ok()
+            .and_then(|cases| cases.parse().ok())
+            .unwrap_or(200);
+        for &algorithm in ALGORITHMS.iter() {
+            if let Err(failure) = fuzz_algorithm(algorithm, num_cases, 0) {
+                panic!("{:?} failed on {}", algorithm, failure);
+            }
+        }
+    }
+
+    #[test]
+    fn property_regressions() {
+        // Shrunk failures found by fuzz_algorithm
+        let collinear = vec![ring(&[(1000.0, 0.0), (152.0, 72.0), (107.0, 72.0), (0.0, 72.0)], false)];
+        let collinear_cap = vec![ring(&[(1000.0, 0.0), (369.0, 72.0), (292.0, 72.0), (239.0, 145.0), (0.0, 72.0)], false)];
+        let split_below_merge = vec![ring(&[(347.0, 57.0), (620.0, 78.0), (919.0, 27.0), (606.0, 123.0), (977.0, 53.0), (578.0, 137.0)], false)];
+        let regular_below_merge = vec![ring(&[(509.0, 590.0), (355.0, 350.0), (0.0, 738.0), (163.0, 293.0), (581.0, 120.0), (606.0, 205.0), (691.0, 87.0)], false)];
+        let aligned_holes = vec![
+            ring(&[(0.0, 0.0), (1000.0, 0.0), (584.0, 105.0)], false),
+            ring(&[(359.0, 44.0), (378.0, 44.0), (147.0, 17.0)], true),
+            ring(&[(792.0, 44.0), (818.0, 44.0), (922.0, 17.0)], true),
+        ];
+        for polys in [collinear, collinear_cap, split_below_merge, regular_below_merge, aligned_holes].iter() {
+            assert!(is_valid_polygon_set(polys));
+            for &algorithm in ALGORITHMS.iter() {
+                assert_eq!(check_algorithm(algorithm, polys), Ok(()), "{:?}", algorithm);
+            }
+        }
+    }
+
+    #[test]
+    fn property_shrinks_to_minimal_case() {
+        let options = GeneratorOptions { generator: PolygonGenerator::FractalCoastline, num_vertices: 30, num_holes: 2, ..Default::default() };
+        let polys = generate_polygons(&options).unwrap();
+        // Pretend that any solid with more than 5 points breaks something
+        let shrunk = shrink_polygons(&polys, |case| case.iter().any(|poly| !poly.is_hole() && poly.num_points() > 5));
+        assert_eq!(shrunk.len(), 1);
+        assert_eq!(shrunk[0].num_points(), 6);
+        assert!(shrunk[0].props().points.iter().all(|p| p.x.fract() == 0.0 && p.y.fract() == 0.0));
+
+        assert!(check_algorithm(PartitionAlgorithm::EarClipping, &polys).is_ok());
+    }
+}
@@ -0,0 +1,356 @@
+use visioncortex::{PointF64, disjoint_sets::Forests};
+
+use super::{Polygon, PolygonInterface, trapezoid_sweep};
+
+/// Returns true iff every edge of every polygon is horizontal or vertical
+pub fn is_rectilinear(polys: &[Polygon]) -> bool {
+    polys.iter().all(|poly| {
+        let points = &poly.props().points;
+        let len = points.len();
+        (0..len).all(|curr| {
+            let (p1, p2) = (points[curr], points[(curr+1) % len]);
+            p1.x == p2.x || p1.y == p2.y
+        })
+    })
+}
+
+/// The polygons laid over the grid formed by the x and y of all their vertices
+struct RectilinearGrid {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    /// Whether each cell (i, j) between xs[i..=i+1] and ys[j..=j+1] is inside the polygons
+    inside: Vec<Vec<bool>>,
+}
+
+/// A horizontal or vertical chord joining two concave vertices, in grid coordinates
+#[derive(Clone, Copy)]
+struct Chord {
+    /// Grid line the chord lies on
+    line: usize,
+    from: usize,
+    to: usize,
+}
+
+impl RectilinearGrid {
+    fn new(polys: &[Polygon]) -> Self {
+        let sorted = |mut values: Vec<f64>| {
+            values.sort_by(|v1, v2| v1.partial_cmp(v2).unwrap());
+            values.dedup();
+            values
+        };
+        let xs = sorted(polys.iter().flat_map(|poly| poly.props().points.iter().map(|p| p.x)).collect());
+        let ys = sorted(polys.iter().flat_map(|poly| poly.props().points.iter().map(|p| p.y)).collect());
+
+        // Scan each row from the left, crossing into or out of the inside at the vertical edges spanning it
+        let index = |values: &[f64], value: f64| values.partition_point(|&v| v < value);
+        let mut crossings = vec![vec![false; ys.len() - 1]; xs.len()];
+        for poly in polys.iter() {
+            let points = &poly.props().points;
+            let len = points.len();
+            for curr in 0..len {
+                let (p1, p2) = (points[curr], points[(curr+1) % len]);
+                if p1.x != p2.x {
+                    continue;
+                }
+                let i = index(&xs, p1.x);
+                let (j1, j2) = (index(&ys, p1.y.min(p2.y)), index(&ys, p1.y.max(p2.y)));
+                for crossing in crossings[i][j1..j2].iter_mut() {
+                    *crossing = !*crossing;
+                }
+            }
+        }
+        let mut inside = vec![vec![false; ys.len() - 1]; xs.len() - 1];
+        for j in 0..ys.len() - 1 {
+            let mut is_inside = false;
+            for i in 0..xs.len() - 1 {
+                is_inside ^= crossings[i][j];
+                inside[i][j] = is_inside;
+            }
+        }
+
+        Self {
+            xs,
+            ys,
+            inside,
+        }
+    }
+
+    fn num_cells_x(&self) -> usize {
+        self.xs.len() - 1
+    }
+
+    fn num_cells_y(&self) -> usize {
+        self.ys.len() - 1
+    }
+
+    /// Cell lookup that treats everything off the grid as outside
+    fn is_inside(&self, i: isize, j: isize) -> bool {
+        i >= 0 && j >= 0 && (i as usize) < self.num_cells_x() && (j as usize) < self.num_cells_y() &&
+            self.inside[i as usize][j as usize]
+    }
+
+    /// Number of inside cells around grid point (i, j)
+    fn num_inside_around(&self, i: usize, j: usize) -> usize {
+        let (i, j) = (i as isize, j as isize);
+        [(i-1, j-1), (i, j-1), (i-1, j), (i, j)].iter().filter(|&&(ci, cj)| self.is_inside(ci, cj)).count()
+    }
+
+    fn is_concave(&self, i: usize, j: usize) -> bool {
+        self.num_inside_around(i, j) == 3
+    }
+
+    /// Whether the horizontal grid edge on ys[j] between xs[i] and xs[i+1] has the inside on both sides
+    fn is_interior_h(&self, i: usize, j: usize) -> bool {
+        self.is_inside(i as isize, j as isize - 1) && self.is_inside(i as isize, j as isize)
+    }
+
+    /// Whether the vertical grid edge on xs[i] between ys[j] and ys[j+1] has the inside on both sides
+    fn is_interior_v(&self, i: usize, j: usize) -> bool {
+        self.is_inside(i as isize - 1, j as isize) && self.is_inside(i as isize, j as isize)
+    }
+
+    fn rectangle(&self, i1: usize, j1: usize, i2: usize, j2: usize) -> Polygon {
+        let (x1, y1, x2, y2) = (self.xs[i1], self.ys[j1], self.xs[i2], self.ys[j2]);
+        Polygon::from_points_and_is_hole(vec![
+            PointF64::new(x1, y1), PointF64::new(x2, y1), PointF64::new(x2, y2), PointF64::new(x1, y2),
+        ], false)
+    }
+}
+
+/// Partitions rectilinear polygons (holes allowed) into the minimum number of axis-aligned rectangles.
+///
+/// Chords joining two concave vertices are collected, and a maximum set of non-intersecting
+/// chords is found by bipartite matching between horizontal and vertical chords (Kőnig's theorem).
+/// Every concave vertex left over is then resolved by cutting horizontally from it.
+pub fn rectangle_partition(polys: &[Polygon]) -> Result<Vec<Polygon>, &'static str> {
+    if polys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+    if !is_rectilinear(polys) {
+        return Err("Input polygons are not rectilinear.");
+    }
+    let grid = RectilinearGrid::new(polys);
+    let (num_xs, num_ys) = (grid.xs.len(), grid.ys.len());
+
+    // Chords between concave vertices, each found from its left/top end
+    let mut horizontal = vec![];
+    let mut vertical = vec![];
+    for i in 0..num_xs {
+        for j in 0..num_ys {
+            if !grid.is_concave(i, j) {
+                continue;
+            }
+            let mut k = i;
+            while k + 1 < num_xs && grid.is_interior_h(k, j) {
+                k += 1;
+                match grid.num_inside_around(k, j) {
+                    4 => continue,
+                    3 => horizontal.push(Chord { line: j, from: i, to: k }),
+                    _ => {},
+                }
+                break;
+            }
+            let mut k = j;
+            while k + 1 < num_ys && grid.is_interior_v(i, k) {
+                k += 1;
+                match grid.num_inside_around(i, k) {
+                    4 => continue,
+                    3 => vertical.push(Chord { line: i, from: j, to: k }),
+                    _ => {},
+                }
+                break;
+            }
+        }
+    }
+
+    let crosses = |h: &Chord, v: &Chord| h.from <= v.line && v.line <= h.to && v.from <= h.line && h.line <= v.to;
+    let conflicts: Vec<Vec<usize>> = horizontal.iter()
+        .map(|h| (0..vertical.len()).filter(|&v| crosses(h, &vertical[v])).collect())
+        .collect();
+    let (keep_horizontal, keep_vertical) = maximum_independent_set(&conflicts, vertical.len());
+
+    let mut h_cut = vec![vec![false; num_ys]; num_xs];
+    let mut v_cut = vec![vec![false; num_ys]; num_xs];
+    let mut is_resolved = vec![vec![false; num_ys]; num_xs];
+    for chord in horizontal.iter().zip(keep_horizontal).filter(|(_, keep)| *keep).map(|(chord, _)| chord) {
+        for cut in h_cut.iter_mut().take(chord.to).skip(chord.from) {
+            cut[chord.line] = true;
+        }
+        is_resolved[chord.from][chord.line] = true;
+        is_resolved[chord.to][chord.line] = true;
+    }
+    for chord in vertical.iter().zip(keep_vertical).filter(|(_, keep)| *keep).map(|(chord, _)| chord) {
+        for cut in v_cut[chord.line].iter_mut().take(chord.to).skip(chord.from) {
+            *cut = true;
+        }
+        is_resolved[chord.line][chord.from] = true;
+        is_resolved[chord.line][chord.to] = true;
+    }
+
+    // Cut horizontally from every remaining concave vertex until reaching the boundary or another cut
+    for j in 0..num_ys {
+        for i in 0..num_xs {
+            if !grid.is_concave(i, j) || is_resolved[i][j] {
+                continue;
+            }
+            let go_right = i + 1 < num_xs && grid.is_interior_h(i, j);
+            let mut k = i;
+            loop {
+                let edge = if go_right { k } else { k - 1 };
+                h_cut[edge][j] = true;
+                k = if go_right { k + 1 } else { k - 1 };
+                let hits_cut = (j > 0 && v_cut[k][j-1]) || (j < num_ys - 1 && v_cut[k][j]);
+                if grid.num_inside_around(k, j) != 4 || hits_cut {
+                    is_resolved[k][j] = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    // The cells joined across uncut edges form the rectangles
+    let mut forests = Forests::new();
+    let cells: Vec<(usize, usize)> = (0..grid.num_cells_y())
+        .flat_map(|j| (0..grid.num_cells_x()).map(move |i| (i, j)))
+        .filter(|&(i, j)| grid.inside[i][j])
+        .collect();
+    for &cell in cells.iter() {
+        forests.make_set(cell);
+    }
+    for &(i, j) in cells.iter() {
+        if grid.is_interior_v(i + 1, j) && !v_cut[i+1][j] {
+            forests.union(&(i, j), &(i + 1, j));
+        }
+        if grid.is_interior_h(i, j + 1) && !h_cut[i][j+1] {
+            forests.union(&(i, j), &(i, j + 1));
+        }
+    }
+
+    let mut groups = forests.group_items(&cells);
+    groups.sort_unstable();
+    let mut rectangles = vec![];
+    for group in groups.iter() {
+        let (mut i1, mut j1, mut i2, mut j2) = (usize::MAX, usize::MAX, 0, 0);
+        for &(i, j) in group.iter().map(|&c| &cells[c]) {
+            i1 = i1.min(i);
+            j1 = j1.min(j);
+            i2 = i2.max(i + 1);
+            j2 = j2.max(j + 1);
+        }
+        if (i2 - i1) * (j2 - j1) != group.len() {
+            return Err("A piece is not rectangular.");
+        }
+        rectangles.push(grid.rectangle(i1, j1, i2, j2));
+    }
+    Ok(rectangles)
+}
+
+/// Partitions rectilinear polygons (holes allowed) into axis-aligned rectangles greedily.
+///
+/// Cuts horizontally from every vertex to the boundary, by the sweep of `trapezoid_sweep`,
+/// so no grid is built. Fast, but not guaranteed to be minimum.
+pub fn rectangle_partition_greedy(polys: &[Polygon]) -> Result<Vec<Polygon>, &'static str> {
+    if polys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+    if !is_rectilinear(polys) {
+        return Err("Input polygons are not rectilinear.");
+    }
+    Ok(trapezoid_sweep(polys).iter().map(|trapezoid| trapezoid.to_polygon()).collect())
+}
+
+/// Given the conflicts of each left vertex of a bipartite graph, finds a maximum independent set.
+///
+/// Returns whether each left and each right vertex is in the set.
+fn maximum_independent_set(conflicts: &[Vec<usize>], num_right: usize) -> (Vec<bool>, Vec<bool>) {
+    // Maximum matching by augmenting paths
+    let mut match_of_right: Vec<Option<usize>> = vec![None; num_right];
+    let mut match_of_left: Vec<Option<usize>> = vec![None; conflicts.len()];
+    for left in 0..conflicts.len() {
+        let mut visited = vec![false; num_right];
+        augment(left, conflicts, &mut visited, &mut match_of_left, &mut match_of_right);
+    }
+
+    // Kőnig: vertices reachable from unmatched left vertices by alternating paths
+    let mut reached_left = vec![false; conflicts.len()];
+    let mut reached_right = vec![false; num_right];
+    let mut stack: Vec<usize> = (0..conflicts.len()).filter(|&left| match_of_left[left].is_none()).collect();
+    for &left in stack.iter() {
+        reached_left[left] = true;
+    }
+    while let Some(left) = stack.pop() {
+        for &right in conflicts[left].iter() {
+            if reached_right[right] {
+                continue;
+            }
+            reached_right[right] = true;
+            if let Some(next) = match_of_right[right] {
+                if !reached_left[next] {
+                    reached_left[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+    }
+
+    // The minimum vertex cover is (unreached left, reached right); the independent set is its complement
+    (reached_left, reached_right.into_iter().map(|reached| !reached).collect())
+}
+
+fn augment(left: usize, conflicts: &[Vec<usize>], visited: &mut Vec<bool>,
+    match_of_left: &mut Vec<Option<usize>>, match_of_right: &mut Vec<Option<usize>>) -> bool {
+    for &right in conflicts[left].iter() {
+        if visited[right] {
+            continue;
+        }
+        visited[right] = true;
+        let is_free = match match_of_right[right] {
+            None => true,
+            Some(other) => augment(other, conflicts, visited, match_of_left, match_of_right),
+        };
+        if is_free {
+            match_of_right[right] = Some(left);
+            match_of_left[left] = Some(right);
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::polygons_area;
+    use crate::polypartition::fixtures::ring;
+
+    #[test]
+    fn rectangle_partition_cross() {
+        // A plus sign: 4 concave vertices joined by 2 horizontal and 2 vertical chords
+        let cross = vec![ring(&[
+            (10.0, 0.0), (20.0, 0.0), (20.0, 10.0), (30.0, 10.0), (30.0, 20.0), (20.0, 20.0),
+            (20.0, 30.0), (10.0, 30.0), (10.0, 20.0), (0.0, 20.0), (0.0, 10.0), (10.0, 10.0),
+        ], false)];
+        let rectangles = rectangle_partition(&cross).unwrap();
+        assert_eq!(rectangles.len(), 3);
+        assert!((polygons_area(&rectangles) - polygons_area(&cross)).abs() < 1e-7);
+        assert!(rectangle_partition_greedy(&cross).unwrap().len() >= 3);
+    }
+
+    #[test]
+    fn rectangle_partition_with_hole() {
+        let polys = vec![
+            ring(&[(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)], false),
+            ring(&[(10.0, 10.0), (10.0, 20.0), (20.0, 20.0), (20.0, 10.0)], true),
+        ];
+        let rectangles = rectangle_partition(&polys).unwrap();
+        assert_eq!(rectangles.len(), 4);
+        assert!((polygons_area(&rectangles) - polygons_area(&polys)).abs() < 1e-7);
+        let greedy = rectangle_partition_greedy(&polys).unwrap();
+        assert_eq!(greedy.len(), 4);
+        assert!((polygons_area(&greedy) - polygons_area(&polys)).abs() < 1e-7);
+
+        let triangle = vec![ring(&[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)], false)];
+        assert!(!is_rectilinear(&triangle));
+        assert!(rectangle_partition(&triangle).is_err());
+    }
+}
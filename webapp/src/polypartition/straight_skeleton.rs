@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+
+use visioncortex::PointF64;
+
+use super::{Orientation, Polygon, PolygonInterface, distance, normalize, polygons_extent};
+
+/// A point where wavefront vertices meet, or an input vertex (at time 0)
+#[derive(Clone, Debug)]
+pub struct SkeletonNode {
+    pub point: PointF64,
+    /// The time the wavefront reached the node, i.e. its distance to the edges of its faces
+    pub time: f64,
+}
+
+/// The path of a wavefront vertex between two nodes
+#[derive(Clone, Debug)]
+pub struct SkeletonArc {
+    pub node_1: usize,
+    pub node_2: usize,
+    /// The two input edges whose faces the arc separates
+    pub edges: (usize, usize),
+}
+
+/// The straight skeleton of polygons with holes.
+///
+/// The input edges are numbered ring by ring, after winding solids counter-clockwise and
+/// holes clockwise (by `Orientation`). Edge `e` runs from node `e` to the next input vertex of its ring.
+#[derive(Clone, Debug, Default)]
+pub struct StraightSkeleton {
+    pub nodes: Vec<SkeletonNode>,
+    pub arcs: Vec<SkeletonArc>,
+    /// For each input edge, the nodes around the region it sweeps, from its end back to its start
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl StraightSkeleton {
+    /// The region swept by the input edge
+    pub fn face_polygon(&self, edge: usize) -> Polygon {
+        Polygon::from_points_and_is_hole(self.faces[edge].iter().map(|&node| self.nodes[node].point).collect(), false)
+    }
+
+    /// The arcs not touching the input boundary, e.g. the centreline of a thin shape
+    pub fn inner_arcs(&self) -> Vec<&SkeletonArc> {
+        self.arcs.iter().filter(|arc| self.nodes[arc.node_1].time > 0.0 && self.nodes[arc.node_2].time > 0.0).collect()
+    }
+}
+
+/// A vertex of the shrinking wavefront, between the wavefronts of two input edges
+#[derive(Clone, Debug)]
+struct WavefrontVertex {
+    p: PointF64,
+    velocity: PointF64,
+    /// The node the vertex started from
+    node: usize,
+    edge_in: usize,
+    edge_out: usize,
+}
+
+enum Event {
+    /// The wavefront edge after the vertex shrinks to nothing
+    Edge { ring: usize, vertex: usize },
+    /// The reflex vertex hits the wavefront edge after another vertex
+    Split { ring: usize, vertex: usize, other_ring: usize, other_vertex: usize },
+}
+
+struct Wavefront {
+    /// Unit direction of each input edge
+    directions: Vec<PointF64>,
+    rings: Vec<Vec<WavefrontVertex>>,
+    time: f64,
+    skeleton: StraightSkeleton,
+    /// Lengths and times closer than this are equal, relative to the size of the input
+    tolerance: f64,
+}
+
+impl Wavefront {
+    /// Inward unit normal of the input edge
+    fn normal(&self, edge: usize) -> PointF64 {
+        let d = self.directions[edge];
+        PointF64::new(-d.y, d.x)
+    }
+
+    /// The velocity keeping a vertex on the wavefronts of both edges, which move inward at unit speed
+    fn velocity(&self, edge_in: usize, edge_out: usize) -> PointF64 {
+        let (n1, n2) = (self.normal(edge_in), self.normal(edge_out));
+        // The sine of the angle between the edges, which does not depend on the size of the input
+        let determinant = n1.x * n2.y - n1.y * n2.x;
+        if determinant.abs() <= 1e-7 {
+            // Parallel edges: along the normal; opposite edges: the vertex is where they have met
+            return if n1.dot(n2) > 0.0 { n1 } else { PointF64::new(0.0, 0.0) };
+        }
+        PointF64::new((n2.y - n1.y) / determinant, (n1.x - n2.x) / determinant)
+    }
+
+    fn new_vertex(&self, p: PointF64, node: usize, edge_in: usize, edge_out: usize) -> WavefrontVertex {
+        WavefrontVertex { p, velocity: self.velocity(edge_in, edge_out), node, edge_in, edge_out }
+    }
+
+    /// Reuses a node reached at the same place and time
+    fn add_node(&mut self, point: PointF64) -> usize {
+        let (time, tolerance) = (self.time, self.tolerance);
+        let nodes = &mut self.skeleton.nodes;
+        if let Some(node) = nodes.iter().position(|node| {
+            node.time > 0.0 && (node.time - time).abs() <= tolerance && distance(&node.point, &point) <= tolerance
+        }) {
+            return node;
+        }
+        nodes.push(SkeletonNode { point, time });
+        nodes.len() - 1
+    }
+
+    fn add_arc(&mut self, node_1: usize, node_2: usize, edges: (usize, usize)) {
+        let arcs = &mut self.skeleton.arcs;
+        let exists = arcs.iter().any(|arc| {
+            (arc.node_1 == node_1 && arc.node_2 == node_2) || (arc.node_1 == node_2 && arc.node_2 == node_1)
+        });
+        if node_1 != node_2 && !exists {
+            arcs.push(SkeletonArc { node_1, node_2, edges });
+        }
+    }
+
+    /// Ends the path of the vertex at the node
+    fn finish_vertex(&mut self, vertex: &WavefrontVertex, node: usize) {
+        self.add_arc(vertex.node, node, (vertex.edge_in, vertex.edge_out));
+    }
+
+    /// The earliest event, and the time until it
+    fn next_event(&self) -> Option<(Event, f64)> {
+        let mut best: Option<(Event, f64)> = None;
+        let mut consider = |event: Event, dt: f64| {
+            // Among simultaneous events, edge events go first
+            let is_edge = matches!(event, Event::Edge { .. });
+            let is_better = match &best {
+                None => true,
+                Some((best_event, best_dt)) => {
+                    dt < best_dt - self.tolerance ||
+                    (dt <= best_dt + self.tolerance && is_edge && !matches!(best_event, Event::Edge { .. }))
+                },
+            };
+            if is_better {
+                best = Some((event, dt));
+            }
+        };
+
+        for (r, ring) in self.rings.iter().enumerate() {
+            let len = ring.len();
+            for i in 0..len {
+                let (a, b) = (&ring[i], &ring[(i+1) % len]);
+                let direction = self.directions[a.edge_out];
+                let length = (b.p - a.p).dot(direction);
+                let rate = (b.velocity - a.velocity).dot(direction);
+                if length <= self.tolerance {
+                    consider(Event::Edge { ring: r, vertex: i }, 0.0);
+                } else if rate < -1e-12 {
+                    consider(Event::Edge { ring: r, vertex: i }, -length / rate);
+                }
+            }
+        }
+
+        for (r, ring) in self.rings.iter().enumerate() {
+            for (i, vertex) in ring.iter().enumerate() {
+                let (d1, d2) = (self.directions[vertex.edge_in], self.directions[vertex.edge_out]);
+                let turn = d1.x * d2.y - d1.y * d2.x;
+                if turn >= -1e-9 {
+                    continue;
+                }
+                for (other_r, other_ring) in self.rings.iter().enumerate() {
+                    let len = other_ring.len();
+                    for k in 0..len {
+                        let (c, d) = (&other_ring[k], &other_ring[(k+1) % len]);
+                        let edge = c.edge_out;
+                        if edge == vertex.edge_in || edge == vertex.edge_out {
+                            continue;
+                        }
+                        let normal = self.normal(edge);
+                        let distance = (vertex.p - c.p).dot(normal);
+                        let rate = 1.0 - vertex.velocity.dot(normal);
+                        if distance < -self.tolerance || rate <= 1e-12 {
+                            continue;
+                        }
+                        let dt = distance.max(0.0) / rate;
+                        let at = |w: &WavefrontVertex| w.p + PointF64::new(w.velocity.x * dt, w.velocity.y * dt);
+                        let (hit, c_at, d_at) = (at(vertex), at(c), at(d));
+                        let direction = self.directions[edge];
+                        if (hit - c_at).dot(direction) >= -self.tolerance && (d_at - hit).dot(direction) >= -self.tolerance {
+                            consider(Event::Split { ring: r, vertex: i, other_ring: other_r, other_vertex: k }, dt);
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    fn advance(&mut self, dt: f64) {
+        for vertex in self.rings.iter_mut().flatten() {
+            vertex.p += PointF64::new(vertex.velocity.x * dt, vertex.velocity.y * dt);
+        }
+        self.time += dt;
+    }
+
+    fn handle_edge_event(&mut self, r: usize, i: usize) {
+        let len = self.rings[r].len();
+        let (a, b) = (self.rings[r][i].clone(), self.rings[r][(i+1) % len].clone());
+        let node = self.add_node(PointF64::new((a.p.x + b.p.x) / 2.0, (a.p.y + b.p.y) / 2.0));
+        self.finish_vertex(&a, node);
+        self.finish_vertex(&b, node);
+
+        let merged = self.new_vertex(self.skeleton.nodes[node].point, node, a.edge_in, b.edge_out);
+        let ring = &mut self.rings[r];
+        ring[i] = merged;
+        ring.remove((i + 1) % len);
+        self.collapse_if_degenerate(r);
+    }
+
+    fn handle_split_event(&mut self, r: usize, i: usize, other_r: usize, k: usize) {
+        let vertex = self.rings[r][i].clone();
+        let edge = self.rings[other_r][k].edge_out;
+        let node = self.add_node(vertex.p);
+        self.finish_vertex(&vertex, node);
+        let p = self.skeleton.nodes[node].point;
+        let vertex_1 = self.new_vertex(p, node, vertex.edge_in, edge);
+        let vertex_2 = self.new_vertex(p, node, edge, vertex.edge_out);
+
+        // Rotate the ring to start after the vertex: [next, ..., previous]
+        let mut ring = self.rings[r].clone();
+        ring.rotate_left(i);
+        ring.remove(0);
+
+        if r == other_r {
+            // The ring splits in two: [vertex_2, next, ..., c] and [vertex_1, d, ..., previous]
+            let c = (k + ring.len() + 1 - i) % (ring.len() + 1) - 1;
+            let mut ring_1 = vec![vertex_1];
+            ring_1.extend_from_slice(&ring[c+1..]);
+            let mut ring_2 = vec![vertex_2];
+            ring_2.extend_from_slice(&ring[..=c]);
+            self.rings[r] = ring_1;
+            self.rings.push(ring_2);
+            let last = self.rings.len() - 1;
+            // Collapse the later ring first, so that removing it keeps the index of the other
+            self.collapse_if_degenerate(last);
+            self.collapse_if_degenerate(r);
+        } else {
+            // The rings join: [vertex_1, d, ..., c, vertex_2, next, ..., previous]
+            let mut other = self.rings[other_r].clone();
+            let d = (k + 1) % other.len();
+            other.rotate_left(d);
+            let mut joined = vec![vertex_1];
+            joined.extend(other);
+            joined.push(vertex_2);
+            joined.extend(ring);
+            let (keep, remove) = if r < other_r { (r, other_r) } else { (other_r, r) };
+            self.rings[keep] = joined;
+            self.rings.remove(remove);
+            self.collapse_if_degenerate(keep);
+        }
+    }
+
+    /// A ring of two vertices has shrunk to a segment between them
+    fn collapse_if_degenerate(&mut self, r: usize) {
+        if self.rings[r].len() > 2 {
+            return;
+        }
+        let ring = self.rings.remove(r);
+        let nodes: Vec<usize> = ring.iter().map(|vertex| {
+            let node = self.add_node(vertex.p);
+            self.finish_vertex(vertex, node);
+            node
+        }).collect();
+        if nodes.len() == 2 {
+            self.add_arc(nodes[0], nodes[1], (ring[0].edge_in, ring[0].edge_out));
+        }
+    }
+}
+
+/// Computes the straight skeleton of polygons with holes, by shrinking their boundary
+/// at unit speed and recording where its vertices travel until it vanishes.
+pub fn straight_skeleton(polys: &[Polygon]) -> Result<StraightSkeleton, &'static str> {
+    if polys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+
+    let mut wavefront = Wavefront {
+        directions: vec![],
+        rings: vec![],
+        time: 0.0,
+        skeleton: StraightSkeleton::default(),
+        tolerance: 1e-9 * polygons_extent(polys),
+    };
+    let mut input_edges = vec![];
+    for poly in polys.iter() {
+        let mut props = poly.props().clone();
+        props.set_orientation(if poly.is_hole() { Orientation::Clockwise } else { Orientation::CounterClockwise });
+        let mut points = props.points;
+        points.dedup();
+        while points.len() > 1 && points[0] == points[points.len() - 1] {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return Err("Some input polygon is degenerate.");
+        }
+
+        let first = wavefront.directions.len();
+        let len = points.len();
+        for (curr, &p) in points.iter().enumerate() {
+            wavefront.directions.push(normalize(&(points[(curr+1) % len] - p)));
+            wavefront.skeleton.nodes.push(SkeletonNode { point: p, time: 0.0 });
+            input_edges.push((first + curr, first + (curr+1) % len));
+        }
+        let ring = (0..len).map(|curr| {
+            let edge_in = first + (curr + len - 1) % len;
+            wavefront.new_vertex(points[curr], first + curr, edge_in, first + curr)
+        }).collect();
+        wavefront.rings.push(ring);
+    }
+
+    // Each event removes a vertex, or splits a ring which must then shrink away
+    let max_events = 4 * input_edges.len() * input_edges.len() + 16;
+    let mut num_events = 0;
+    while !wavefront.rings.is_empty() {
+        num_events += 1;
+        if num_events > max_events {
+            return Err("Straight skeleton did not converge.");
+        }
+        let (event, dt) = if let Some(next) = wavefront.next_event() { next } else {
+            return Err("Wavefront stopped shrinking.");
+        };
+        wavefront.advance(dt);
+        match event {
+            Event::Edge { ring, vertex } => wavefront.handle_edge_event(ring, vertex),
+            Event::Split { ring, vertex, other_ring, other_vertex } => {
+                wavefront.handle_split_event(ring, vertex, other_ring, other_vertex)
+            },
+        }
+    }
+
+    let mut skeleton = wavefront.skeleton;
+    skeleton.faces = input_edges.iter().enumerate()
+        .map(|(edge, &(start, end))| trace_face(&skeleton.arcs, edge, start, end))
+        .collect();
+    Ok(skeleton)
+}
+
+/// Walks the arcs bordering the face of the edge, from its end back to its start
+fn trace_face(arcs: &[SkeletonArc], edge: usize, start: usize, end: usize) -> Vec<usize> {
+    let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for arc in arcs.iter().filter(|arc| arc.edges.0 == edge || arc.edges.1 == edge) {
+        neighbors.entry(arc.node_1).or_default().push(arc.node_2);
+        neighbors.entry(arc.node_2).or_default().push(arc.node_1);
+    }
+
+    let mut face = vec![end];
+    let mut previous = start;
+    let mut current = end;
+    while current != start && face.len() <= arcs.len() + 1 {
+        let next = neighbors.get(&current)
+            .and_then(|candidates| candidates.iter().find(|&&next| next != previous && !face[1..].contains(&next)));
+        match next {
+            Some(&next) => {
+                previous = current;
+                current = next;
+                if current != start {
+                    face.push(current);
+                }
+            },
+            None => break,
+        }
+    }
+    face.push(start);
+    face
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{f64_approximately, polygons_area, signed_area};
+    use crate::polypartition::fixtures::ring;
+
+    fn total_face_area(skeleton: &StraightSkeleton) -> f64 {
+        (0..skeleton.faces.len()).map(|edge| signed_area(&skeleton.face_polygon(edge).props().points)).sum()
+    }
+
+    #[test]
+    fn straight_skeleton_rectangle() {
+        let rectangle = Polygon::from_points_and_is_hole(vec![
+            PointF64::new(0.0, 0.0), PointF64::new(200.0, 0.0),
+            PointF64::new(200.0, 100.0), PointF64::new(0.0, 100.0),
+        ], false);
+        let skeleton = straight_skeleton(&[rectangle]).unwrap();
+        // The four corners and the two ends of the ridge
+        assert_eq!(skeleton.nodes.len(), 6);
+        assert_eq!(skeleton.arcs.len(), 5);
+        let ridge = skeleton.inner_arcs();
+        assert_eq!(ridge.len(), 1);
+        let ends = [skeleton.nodes[ridge[0].node_1].point, skeleton.nodes[ridge[0].node_2].point];
+        assert!(ends.contains(&PointF64::new(50.0, 50.0)) && ends.contains(&PointF64::new(150.0, 50.0)));
+        assert!(skeleton.nodes[4..].iter().all(|node| f64_approximately(node.time, 50.0)));
+
+        // The long sides sweep trapezoids, the short sides triangles
+        assert_eq!(skeleton.faces[0].len(), 4);
+        assert_eq!(skeleton.faces[1].len(), 3);
+        assert!(f64_approximately(total_face_area(&skeleton), 20000.0));
+    }
+
+    #[test]
+    fn straight_skeleton_with_hole() {
+        let polys = vec![
+            Polygon::from_points_and_is_hole(vec![
+                PointF64::new(0.0, 0.0), PointF64::new(200.0, 0.0), PointF64::new(210.0, 60.0),
+                PointF64::new(150.0, 110.0), PointF64::new(10.0, 100.0),
+            ], false),
+            Polygon::from_points_and_is_hole(vec![
+                PointF64::new(80.0, 30.0), PointF64::new(97.0, 45.0),
+                PointF64::new(80.0, 62.0), PointF64::new(63.0, 47.0),
+            ], true),
+        ];
+        let skeleton = straight_skeleton(&polys).unwrap();
+        assert_eq!(skeleton.faces.len(), 9);
+        assert!((total_face_area(&skeleton) - polygons_area(&polys)).abs() < 1e-6);
+        // Every face touches only its own edge on the boundary
+        for (edge, face) in skeleton.faces.iter().enumerate() {
+            assert!(face.iter().filter(|&&node| skeleton.nodes[node].time == 0.0).count() == 2, "face {}", edge);
+        }
+    }
+
+    #[test]
+    fn straight_skeleton_small_coordinates() {
+        // The polygon with a hole, shrunk to edges shorter than 1e-7
+        let polys = vec![
+            ring(&[(0.0, 0.0), (200.0, 0.0), (210.0, 60.0), (150.0, 110.0), (10.0, 100.0)], false),
+            ring(&[(80.0, 30.0), (97.0, 45.0), (80.0, 62.0), (63.0, 47.0)], true),
+        ];
+        let scale = 1e-9;
+        let small: Vec<Polygon> = polys.iter().map(|poly| {
+            let points = poly.props().points.iter().map(|p| PointF64::new(p.x * scale, p.y * scale)).collect();
+            Polygon::from_points_and_is_hole(points, poly.is_hole())
+        }).collect();
+        let (skeleton, small_skeleton) = (straight_skeleton(&polys).unwrap(), straight_skeleton(&small).unwrap());
+        assert_eq!(small_skeleton.nodes.len(), skeleton.nodes.len());
+        assert_eq!(small_skeleton.arcs.len(), skeleton.arcs.len());
+        for (node, small_node) in skeleton.nodes.iter().zip(small_skeleton.nodes.iter()) {
+            assert!((small_node.time - node.time * scale).abs() < 1e-6 * scale);
+        }
+        let area = polygons_area(&small);
+        assert!((total_face_area(&small_skeleton) - area).abs() < 1e-9 * area);
+    }
+}
@@ -0,0 +1,911 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+
+use visioncortex::{Color, PointF64};
+
+use super::{FillRule, Orientation, ParseError, Polygon, PolygonInterface, polygons_bounding_rect_f64};
+
+/// Curves are never split into more segments than this
+const MAX_CURVE_SEGMENTS: usize = 1024;
+
+/// Reads the filled `<path>`, `<polygon>` and `<rect>` elements of an SVG document into polygons.
+///
+/// Transforms (on the elements and their groups) are applied, curves and arcs are flattened so that
+/// the polygons stay within `tolerance` of them, and the subpaths of each element become solids or holes
+/// by its `fill-rule`. Elements with `fill="none"`, and those inside `<defs>` and the like, are skipped.
+/// Coordinates stay in SVG user units, with y down.
+///
+/// Each element is a separate shape, so overlapping elements give overlapping polygons, and the
+/// subpaths of an element should not cross each other.
+pub fn parse_svg(text: &str, tolerance: f64) -> Result<Vec<Polygon>, String> {
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        return Err("Tolerance must be positive".into());
+    }
+    let mut polygons = vec![];
+    let mut scanner = XmlScanner { text, pos: 0 };
+    let mut stack = vec![Style::default()];
+    while let Some(tag) = scanner.next_tag().map_err(|error| error.to_string())? {
+        if tag.is_end {
+            if stack.len() > 1 {
+                stack.pop();
+            }
+            continue;
+        }
+        let style = stack.last().unwrap().inherit(&tag).map_err(|message| tag.error(&message).to_string())?;
+        if style.is_rendered() {
+            let rings = match tag.name {
+                "path" => path_rings(tag.attribute("d").unwrap_or(""), &style.transform, tolerance),
+                "polygon" => polygon_ring(tag.attribute("points").unwrap_or(""), &style.transform),
+                "rect" => rect_rings(&tag, &style.transform, tolerance),
+                _ => Ok(vec![]),
+            }.map_err(|message| tag.error(&message).to_string())?;
+            polygons.extend(rings_to_polygons(rings, style.fill_rule));
+        }
+        if !tag.is_self_closing {
+            stack.push(style);
+        }
+    }
+    Ok(polygons)
+}
+
+/// Reads SVG path data (the `d` of a `<path>`), such as from `CompoundPath::to_svg_string`, into polygons.
+/// Curves are flattened and holes found as by `parse_svg`.
+pub fn parse_svg_path(d: &str, tolerance: f64, fill_rule: FillRule) -> Result<Vec<Polygon>, String> {
+    if tolerance.is_nan() || tolerance <= 0.0 {
+        return Err("Tolerance must be positive".into());
+    }
+    Ok(rings_to_polygons(path_rings(d, &Transform::IDENTITY, tolerance)?, fill_rule))
+}
+
+/// Keeps the rings that bound a filled region: a ring is a solid if the region just inside it is filled
+/// and the region just outside is not, and a hole if the other way round.
+fn rings_to_polygons(rings: Vec<Vec<PointF64>>, fill_rule: FillRule) -> Vec<Polygon> {
+    let rings: Vec<Polygon> = rings.into_iter().filter_map(|mut points| {
+        points.dedup();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        let ring = Polygon::from_points_and_is_hole(points, false);
+        if ring.num_points() < 3 || ring.props().signed_area() == 0.0 {
+            return None;
+        }
+        Some(ring)
+    }).collect();
+    let is_filled = |winding: i32| match fill_rule {
+        FillRule::EvenOdd => winding & 1 == 1,
+        FillRule::NonZero => winding != 0,
+    };
+    rings.iter().enumerate().filter_map(|(i, ring)| {
+        let p = ring.get_point(0);
+        let outside: i32 = rings.iter().enumerate()
+            .filter(|&(j, _)| j != i)
+            .map(|(_, other)| other.props().winding_number(&p))
+            .sum();
+        let inside = outside + if ring.props().signed_area() > 0.0 { 1 } else { -1 };
+        let is_hole = match (is_filled(inside), is_filled(outside)) {
+            (true, false) => false,
+            (false, true) => true,
+            _ => return None,
+        };
+        let mut polygon = Polygon::from_points_and_is_hole(ring.props().points.clone(), is_hole);
+        polygon.props_mut().set_orientation(if is_hole { Orientation::Clockwise } else { Orientation::CounterClockwise });
+        Some(polygon)
+    }).collect()
+}
+
+/// An affine transform, mapping (x, y) to (a x + c y + e, b x + d y + f) as in SVG
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Transform {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+impl Transform {
+    const IDENTITY: Self = Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+
+    fn apply(&self, p: PointF64) -> PointF64 {
+        PointF64::new(self.a * p.x + self.c * p.y + self.e, self.b * p.x + self.d * p.y + self.f)
+    }
+
+    /// This transform after the other one
+    fn then(&self, other: &Transform) -> Transform {
+        Transform {
+            a: self.a * other.a + self.c * other.b,
+            b: self.b * other.a + self.d * other.b,
+            c: self.a * other.c + self.c * other.d,
+            d: self.b * other.c + self.d * other.d,
+            e: self.a * other.e + self.c * other.f + self.e,
+            f: self.b * other.e + self.d * other.f + self.f,
+        }
+    }
+
+    /// The most that the transform stretches any length
+    fn max_scale(&self) -> f64 {
+        let t = self.a * self.a + self.b * self.b + self.c * self.c + self.d * self.d;
+        let det = self.a * self.d - self.b * self.c;
+        ((t + (t * t - 4.0 * det * det).max(0.0).sqrt()) / 2.0).sqrt()
+    }
+
+    /// Parses an SVG transform list, such as "translate(10 20) rotate(45)"
+    fn parse(s: &str) -> Result<Transform, String> {
+        let mut transform = Transform::IDENTITY;
+        let mut rest = s.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        while !rest.is_empty() {
+            let open = rest.find('(').ok_or_else(|| format!("Invalid transform '{}'", s))?;
+            let close = rest.find(')').filter(|&close| close > open).ok_or_else(|| format!("Invalid transform '{}'", s))?;
+            let name = rest[..open].trim();
+            let mut numbers = NumberReader { bytes: &rest.as_bytes()[open+1..close], pos: 0 };
+            let mut args = vec![];
+            while numbers.has_more() {
+                args.push(numbers.number().map_err(|_| format!("Invalid transform '{}'", s))?);
+            }
+            let next = match (name, args.as_slice()) {
+                ("matrix", &[a, b, c, d, e, f]) => Transform { a, b, c, d, e, f },
+                ("translate", &[x]) => Transform { e: x, ..Transform::IDENTITY },
+                ("translate", &[x, y]) => Transform { e: x, f: y, ..Transform::IDENTITY },
+                ("scale", &[s]) => Transform { a: s, d: s, ..Transform::IDENTITY },
+                ("scale", &[x, y]) => Transform { a: x, d: y, ..Transform::IDENTITY },
+                ("rotate", &[angle]) => rotation(angle),
+                ("rotate", &[angle, x, y]) => Transform { e: x, f: y, ..Transform::IDENTITY }
+                    .then(&rotation(angle))
+                    .then(&Transform { e: -x, f: -y, ..Transform::IDENTITY }),
+                ("skewX", &[angle]) => Transform { c: angle.to_radians().tan(), ..Transform::IDENTITY },
+                ("skewY", &[angle]) => Transform { b: angle.to_radians().tan(), ..Transform::IDENTITY },
+                _ => return Err(format!("Invalid transform '{}'", s)),
+            };
+            transform = transform.then(&next);
+            rest = rest[close+1..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        }
+        Ok(transform)
+    }
+}
+
+fn rotation(degrees: f64) -> Transform {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    Transform { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 }
+}
+
+/// Reads numbers separated by whitespace and commas, in the compact forms of path data like "1.5.5-2"
+struct NumberReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> NumberReader<'a> {
+    fn skip_separators(&mut self) {
+        while self.pos < self.bytes.len() && (self.bytes[self.pos].is_ascii_whitespace() || self.bytes[self.pos] == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn has_more(&mut self) -> bool {
+        self.skip_separators();
+        self.pos < self.bytes.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |reader: &mut Self| {
+            let from = reader.pos;
+            while matches!(reader.peek(), Some(c) if c.is_ascii_digit()) {
+                reader.pos += 1;
+            }
+            reader.pos > from
+        };
+        if let Some(b'+') | Some(b'-') = self.peek() {
+            self.pos += 1;
+        }
+        let mut has_digits = digits(self);
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            has_digits |= digits(self);
+        }
+        if has_digits {
+            if let Some(b'e') | Some(b'E') = self.peek() {
+                let mantissa_end = self.pos;
+                self.pos += 1;
+                if let Some(b'+') | Some(b'-') = self.peek() {
+                    self.pos += 1;
+                }
+                if !digits(self) {
+                    self.pos = mantissa_end;
+                }
+            }
+        }
+        let literal = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or("");
+        match literal.parse::<f64>() {
+            Ok(n) if has_digits && n.is_finite() => Ok(n),
+            _ => {
+                self.pos = start;
+                Err(format!("Expected a number at character {}", start + 1))
+            },
+        }
+    }
+
+    /// An arc flag, which may run into the next number as in "a5 5 0 11 10 10"
+    fn flag(&mut self) -> Result<bool, String> {
+        self.skip_separators();
+        match self.peek() {
+            Some(b'0') => { self.pos += 1; Ok(false) },
+            Some(b'1') => { self.pos += 1; Ok(true) },
+            _ => Err(format!("Expected a flag at character {}", self.pos + 1)),
+        }
+    }
+
+    fn point(&mut self) -> Result<PointF64, String> {
+        Ok(PointF64::new(self.number()?, self.number()?))
+    }
+}
+
+/// The subpaths of path data, flattened and transformed
+fn path_rings(d: &str, transform: &Transform, tolerance: f64) -> Result<Vec<Vec<PointF64>>, String> {
+    // Flatten in local coordinates, where the tolerance is smaller by the scale of the transform
+    let tolerance = tolerance / transform.max_scale().max(f64::MIN_POSITIVE);
+    let mut reader = NumberReader { bytes: d.as_bytes(), pos: 0 };
+    let mut rings = vec![];
+    let mut ring: Vec<PointF64> = vec![];
+    let (mut current, mut start) = (PointF64::default(), PointF64::default());
+    // The control point to reflect for S and T, with whether it came from a cubic
+    let mut last_control: Option<(PointF64, bool)> = None;
+    let mut command = None;
+
+    while reader.has_more() {
+        let c = reader.peek().unwrap();
+        if c.is_ascii_alphabetic() {
+            reader.pos += 1;
+            command = Some(c);
+        } else {
+            command = match command {
+                None | Some(b'Z') | Some(b'z') => return Err(format!("Expected a command at character {}", reader.pos + 1)),
+                // Coordinates after a move are lines
+                Some(b'M') => Some(b'L'),
+                Some(b'm') => Some(b'l'),
+                other => other,
+            };
+        }
+        let command = command.unwrap();
+        let relative = command.is_ascii_lowercase();
+        let offset = |p: PointF64| if relative { p + current } else { p };
+        if ring.is_empty() && !matches!(command, b'M' | b'm' | b'Z' | b'z') {
+            ring.push(current);
+        }
+        let mut control = None;
+        let kind = command.to_ascii_uppercase();
+        match kind {
+            b'M' => {
+                if !ring.is_empty() {
+                    rings.push(std::mem::take(&mut ring));
+                }
+                current = offset(reader.point()?);
+                start = current;
+                ring.push(current);
+            },
+            b'L' => {
+                current = offset(reader.point()?);
+                ring.push(current);
+            },
+            b'H' => {
+                let x = reader.number()?;
+                current = PointF64::new(if relative { current.x + x } else { x }, current.y);
+                ring.push(current);
+            },
+            b'V' => {
+                let y = reader.number()?;
+                current = PointF64::new(current.x, if relative { current.y + y } else { y });
+                ring.push(current);
+            },
+            b'C' | b'S' => {
+                let p1 = if kind == b'C' {
+                    offset(reader.point()?)
+                } else {
+                    match last_control {
+                        Some((p, true)) => scaled(current, 2.0) - p,
+                        _ => current,
+                    }
+                };
+                let p2 = offset(reader.point()?);
+                let p3 = offset(reader.point()?);
+                flatten_cubic(current, p1, p2, p3, tolerance, &mut ring);
+                control = Some((p2, true));
+                current = p3;
+            },
+            b'Q' | b'T' => {
+                let p1 = if kind == b'Q' {
+                    offset(reader.point()?)
+                } else {
+                    match last_control {
+                        Some((p, false)) => scaled(current, 2.0) - p,
+                        _ => current,
+                    }
+                };
+                let p2 = offset(reader.point()?);
+                flatten_quadratic(current, p1, p2, tolerance, &mut ring);
+                control = Some((p1, false));
+                current = p2;
+            },
+            b'A' => {
+                let (rx, ry, rotation) = (reader.number()?, reader.number()?, reader.number()?);
+                let (large_arc, sweep) = (reader.flag()?, reader.flag()?);
+                let end = offset(reader.point()?);
+                flatten_arc(current, rx, ry, rotation, large_arc, sweep, end, tolerance, &mut ring);
+                current = end;
+            },
+            b'Z' => {
+                if !ring.is_empty() {
+                    rings.push(std::mem::take(&mut ring));
+                }
+                current = start;
+            },
+            _ => return Err(format!("Unknown command '{}' at character {}", command as char, reader.pos)),
+        }
+        last_control = control;
+    }
+    if !ring.is_empty() {
+        rings.push(ring);
+    }
+    Ok(rings.into_iter().map(|ring| ring.into_iter().map(|p| transform.apply(p)).collect()).collect())
+}
+
+fn scaled(p: PointF64, k: f64) -> PointF64 {
+    PointF64::new(p.x * k, p.y * k)
+}
+
+fn segments_for(deviation: f64, tolerance: f64) -> usize {
+    ((deviation / tolerance).sqrt().ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS)
+}
+
+fn flatten_cubic(p0: PointF64, p1: PointF64, p2: PointF64, p3: PointF64, tolerance: f64, out: &mut Vec<PointF64>) {
+    let d1 = p0 - scaled(p1, 2.0) + p2;
+    let d2 = p1 - scaled(p2, 2.0) + p3;
+    let deviation = 0.75 * d1.norm().max(d2.norm());
+    let n = segments_for(deviation, tolerance);
+    for i in 1..=n {
+        let t = i as f64 / n as f64;
+        let s = 1.0 - t;
+        out.push(scaled(p0, s * s * s) + scaled(p1, 3.0 * s * s * t) + scaled(p2, 3.0 * s * t * t) + scaled(p3, t * t * t));
+    }
+}
+
+fn flatten_quadratic(p0: PointF64, p1: PointF64, p2: PointF64, tolerance: f64, out: &mut Vec<PointF64>) {
+    let deviation = 0.25 * (p0 - scaled(p1, 2.0) + p2).norm();
+    let n = segments_for(deviation, tolerance);
+    for i in 1..=n {
+        let t = i as f64 / n as f64;
+        let s = 1.0 - t;
+        out.push(scaled(p0, s * s) + scaled(p1, 2.0 * s * t) + scaled(p2, t * t));
+    }
+}
+
+/// Flattens an arc given by its end points, as in SVG path data
+#[allow(clippy::too_many_arguments)]
+fn flatten_arc(p0: PointF64, rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, p1: PointF64,
+    tolerance: f64, out: &mut Vec<PointF64>) {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if p0 == p1 {
+        return;
+    }
+    if rx == 0.0 || ry == 0.0 {
+        out.push(p1);
+        return;
+    }
+    // Conversion to center parameterization, from the SVG implementation notes
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let half = scaled(p0 - p1, 0.5);
+    let x1 = cos * half.x + sin * half.y;
+    let y1 = -sin * half.x + cos * half.y;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = (rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1).max(0.0);
+    let mut factor = (numerator / (rx * rx * y1 * y1 + ry * ry * x1 * x1)).sqrt();
+    if large_arc == sweep {
+        factor = -factor;
+    }
+    let (cx1, cy1) = (factor * rx * y1 / ry, -factor * ry * x1 / rx);
+    let mid = scaled(p0 + p1, 0.5);
+    let center = PointF64::new(cos * cx1 - sin * cy1 + mid.x, sin * cx1 + cos * cy1 + mid.y);
+    let angle = |x: f64, y: f64| y.atan2(x);
+    let theta1 = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - theta1;
+    if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    } else if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    }
+
+    let radius = rx.max(ry);
+    let max_step = if tolerance < radius { 2.0 * (1.0 - tolerance / radius).acos() } else { PI / 2.0 };
+    let n = ((delta.abs() / max_step).ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS);
+    for i in 1..n {
+        let theta = theta1 + delta * i as f64 / n as f64;
+        let (x, y) = (rx * theta.cos(), ry * theta.sin());
+        out.push(PointF64::new(cos * x - sin * y + center.x, sin * x + cos * y + center.y));
+    }
+    out.push(p1);
+}
+
+fn polygon_ring(points: &str, transform: &Transform) -> Result<Vec<Vec<PointF64>>, String> {
+    let mut reader = NumberReader { bytes: points.as_bytes(), pos: 0 };
+    let mut ring = vec![];
+    while reader.has_more() {
+        ring.push(transform.apply(reader.point()?));
+    }
+    Ok(vec![ring])
+}
+
+fn rect_rings(tag: &Tag, transform: &Transform, tolerance: f64) -> Result<Vec<Vec<PointF64>>, String> {
+    let length = |name: &str| -> Result<Option<f64>, String> {
+        match tag.attribute(name) {
+            None => Ok(None),
+            Some(value) => value.trim().trim_end_matches("px").parse::<f64>().ok().filter(|n| n.is_finite())
+                .map(Some).ok_or_else(|| format!("Unsupported length '{}' for {}", value, name)),
+        }
+    };
+    let (x, y) = (length("x")?.unwrap_or(0.0), length("y")?.unwrap_or(0.0));
+    let (width, height) = (length("width")?.unwrap_or(0.0), length("height")?.unwrap_or(0.0));
+    if width <= 0.0 || height <= 0.0 {
+        return Ok(vec![]);
+    }
+    let (rx, ry) = match (length("rx")?, length("ry")?) {
+        (None, None) => (0.0, 0.0),
+        (Some(rx), None) => (rx, rx),
+        (None, Some(ry)) => (ry, ry),
+        (Some(rx), Some(ry)) => (rx, ry),
+    };
+    let (rx, ry) = (rx.clamp(0.0, width / 2.0), ry.clamp(0.0, height / 2.0));
+    let d = if rx > 0.0 && ry > 0.0 {
+        format!("M{} {} H{} A{} {} 0 0 1 {} {} V{} A{} {} 0 0 1 {} {} H{} A{} {} 0 0 1 {} {} V{} A{} {} 0 0 1 {} {} Z",
+            x + rx, y, x + width - rx, rx, ry, x + width, y + ry, y + height - ry, rx, ry, x + width - rx, y + height,
+            x + rx, rx, ry, x, y + height - ry, y + ry, rx, ry, x + rx, y)
+    } else {
+        format!("M{} {} H{} V{} H{} Z", x, y, x + width, y + height, x)
+    };
+    path_rings(&d, transform, tolerance)
+}
+
+/// What an element inherits from its ancestors
+#[derive(Clone, Debug)]
+struct Style {
+    transform: Transform,
+    fill_rule: FillRule,
+    is_filled: bool,
+    /// Inside `<defs>` and the like, which are not drawn directly
+    is_hidden: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self { transform: Transform::IDENTITY, fill_rule: FillRule::NonZero, is_filled: true, is_hidden: false }
+    }
+}
+
+impl Style {
+    fn is_rendered(&self) -> bool {
+        self.is_filled && !self.is_hidden
+    }
+
+    fn inherit(&self, tag: &Tag) -> Result<Style, String> {
+        let mut style = self.clone();
+        if let Some(transform) = tag.attribute("transform") {
+            style.transform = self.transform.then(&Transform::parse(transform)?);
+        }
+        if matches!(tag.name, "defs" | "clipPath" | "mask" | "symbol" | "pattern" | "marker") {
+            style.is_hidden = true;
+        }
+        // Properties in the style attribute override the presentation attributes
+        let declarations = tag.attribute("style").unwrap_or("").split(';').filter_map(|declaration| {
+            let mut parts = declaration.splitn(2, ':');
+            Some((parts.next()?.trim(), parts.next()?.trim()))
+        });
+        let properties = [("fill-rule", tag.attribute("fill-rule")), ("fill", tag.attribute("fill")), ("display", tag.attribute("display"))];
+        for (name, value) in properties.iter().filter_map(|&(name, value)| Some((name, value?.trim()))).chain(declarations) {
+            match (name, value) {
+                ("fill-rule", "evenodd") => style.fill_rule = FillRule::EvenOdd,
+                ("fill-rule", "nonzero") => style.fill_rule = FillRule::NonZero,
+                ("fill", "none") => style.is_filled = false,
+                ("fill", _) => style.is_filled = true,
+                ("display", "none") => style.is_hidden = true,
+                _ => {},
+            }
+        }
+        Ok(style)
+    }
+}
+
+/// A start or end tag
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, String)>,
+    is_end: bool,
+    is_self_closing: bool,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError { line: self.line, column: self.column, message: format!("In <{}>: {}", self.name, message) }
+    }
+}
+
+/// Just enough of XML to find the tags and their attributes
+struct XmlScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlScanner<'a> {
+    fn error_at(&self, pos: usize, message: &str) -> ParseError {
+        let before = &self.text[..pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        ParseError { line, column, message: message.to_string() }
+    }
+
+    /// Moves past the terminator, or fails at `start`
+    fn skip_past(&mut self, start: usize, terminator: &str, what: &str) -> Result<(), ParseError> {
+        match self.text[self.pos..].find(terminator) {
+            Some(offset) => {
+                self.pos += offset + terminator.len();
+                Ok(())
+            },
+            None => Err(self.error_at(start, &format!("Unterminated {}", what))),
+        }
+    }
+
+    fn next_tag(&mut self) -> Result<Option<Tag<'a>>, ParseError> {
+        loop {
+            let start = match self.text[self.pos..].find('<') {
+                Some(offset) => self.pos + offset,
+                None => return Ok(None),
+            };
+            self.pos = start;
+            let rest = &self.text[start..];
+            if rest.starts_with("<!--") {
+                self.skip_past(start, "-->", "comment")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past(start, "]]>", "CDATA section")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past(start, "?>", "processing instruction")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(start, ">", "declaration")?;
+            } else {
+                return self.read_tag(start).map(Some);
+            }
+        }
+    }
+
+    fn read_tag(&mut self, start: usize) -> Result<Tag<'a>, ParseError> {
+        let text = self.text;
+        let mut pos = start + 1;
+        let is_end = text[pos..].starts_with('/');
+        if is_end {
+            pos += 1;
+        }
+        let name_len = text[pos..].find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(text.len() - pos);
+        let name = &text[pos..pos + name_len];
+        if name.is_empty() {
+            return Err(self.error_at(start, "Expected a tag name"));
+        }
+        pos += name_len;
+        let mut attributes = vec![];
+        loop {
+            pos += text[pos..].len() - text[pos..].trim_start().len();
+            let rest = &text[pos..];
+            if rest.starts_with("/>") || rest.starts_with('>') {
+                let is_self_closing = rest.starts_with("/>");
+                self.pos = pos + if is_self_closing { 2 } else { 1 };
+                let before = &text[..start];
+                let line = before.matches('\n').count() + 1;
+                let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+                return Ok(Tag { name, attributes, is_end, is_self_closing, line, column });
+            }
+            if rest.is_empty() {
+                return Err(self.error_at(start, "Unterminated tag"));
+            }
+            let key_len = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/').unwrap_or(rest.len());
+            if key_len == 0 {
+                return Err(self.error_at(pos, "Expected an attribute"));
+            }
+            let key = &rest[..key_len];
+            pos += key_len;
+            pos += text[pos..].len() - text[pos..].trim_start().len();
+            if !text[pos..].starts_with('=') {
+                return Err(self.error_at(pos, &format!("Expected '=' after {}", key)));
+            }
+            pos += 1;
+            pos += text[pos..].len() - text[pos..].trim_start().len();
+            let quote = match text[pos..].chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => quote,
+                _ => return Err(self.error_at(pos, &format!("Expected a quoted value for {}", key))),
+            };
+            let value_len = text[pos + 1..].find(quote).ok_or_else(|| self.error_at(pos, "Unterminated attribute value"))?;
+            attributes.push((key, decode_entities(&text[pos + 1..pos + 1 + value_len])));
+            pos += value_len + 2;
+        }
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest.find(';').map(|semi| &rest[1..semi]);
+        let c = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(std::char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(std::char::from_u32),
+            _ => None,
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            },
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Options for `write_svg`. Lengths are in the units of the polygons.
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    /// Space around the polygons
+    pub margin: f64,
+    pub stroke_width: f64,
+    /// The radius of the dot drawn at each vertex, or 0 for none
+    pub vertex_radius: f64,
+    /// Whether to draw the edges shared by two pieces (the diagonals of a partition) in red
+    pub highlight_diagonals: bool,
+    /// Whether to label each piece with its index, at its centroid
+    pub label_pieces: bool,
+    pub font_size: f64,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            margin: 10.0,
+            stroke_width: 1.0,
+            vertex_radius: 0.0,
+            highlight_diagonals: false,
+            label_pieces: false,
+            font_size: 12.0,
+        }
+    }
+}
+
+/// Draws polygons, such as the pieces of a partition, as an SVG document. Each solid is filled with
+/// `Color::get_palette_color` of its index among the solids, and holes are drawn white with dashed outlines.
+///
+/// The pieces are `<polygon>` elements with the coordinates in full, so `parse_svg` reads back the solids.
+pub fn write_svg(polygons: &[Polygon], options: &SvgOptions) -> String {
+    let rect = polygons_bounding_rect_f64(polygons);
+    let (width, height) = (rect.width() + 2.0 * options.margin, rect.height() + 2.0 * options.margin);
+    let mut svg = vec![format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        rect.left - options.margin, rect.top - options.margin, width, height, width, height,
+    )];
+    let points_attribute = |polygon: &Polygon| polygon.props().points.iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let solids: Vec<&Polygon> = polygons.iter().filter(|polygon| !polygon.is_hole()).collect();
+    let holes: Vec<&Polygon> = polygons.iter().filter(|polygon| polygon.is_hole()).collect();
+
+    svg.push(format!("<g stroke=\"#000000\" stroke-width=\"{}\" stroke-linejoin=\"round\">", options.stroke_width));
+    for (i, solid) in solids.iter().enumerate() {
+        svg.push(format!("<polygon points=\"{}\" fill=\"{}\"/>", points_attribute(solid), Color::get_palette_color(i).to_hex_string()));
+    }
+    svg.push("</g>".into());
+    if !holes.is_empty() {
+        svg.push(format!("<g fill=\"#FFFFFF\" stroke=\"#000000\" stroke-width=\"{}\" stroke-dasharray=\"{} {}\">",
+            options.stroke_width, 4.0 * options.stroke_width, 2.0 * options.stroke_width));
+        for hole in holes.iter() {
+            svg.push(format!("<polygon points=\"{}\"/>", points_attribute(hole)));
+        }
+        svg.push("</g>".into());
+    }
+
+    if options.highlight_diagonals {
+        svg.push(format!("<g fill=\"none\" stroke=\"#FF0000\" stroke-width=\"{}\" stroke-linecap=\"round\">", 2.0 * options.stroke_width));
+        for (p1, p2) in shared_edges(&solids) {
+            svg.push(format!("<path d=\"M{},{} L{},{}\"/>", p1.x, p1.y, p2.x, p2.y));
+        }
+        svg.push("</g>".into());
+    }
+    if options.vertex_radius > 0.0 {
+        svg.push("<g fill=\"#000000\">".into());
+        let mut drawn = HashSet::new();
+        for p in polygons.iter().flat_map(|polygon| polygon.props().points.iter()) {
+            if drawn.insert((p.x.to_bits(), p.y.to_bits())) {
+                svg.push(format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\"/>", p.x, p.y, options.vertex_radius));
+            }
+        }
+        svg.push("</g>".into());
+    }
+    if options.label_pieces {
+        svg.push(format!("<g font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">", options.font_size));
+        for (i, solid) in solids.iter().enumerate() {
+            let centroid = solid.props().centroid();
+            svg.push(format!("<text x=\"{}\" y=\"{}\">{}</text>", centroid.x, centroid.y, i));
+        }
+        svg.push("</g>".into());
+    }
+    svg.push("</svg>".into());
+    svg.join("\n")
+}
+
+/// The edges that are in more than one of the polygons, each once, in the order they first appear
+fn shared_edges(polygons: &[&Polygon]) -> Vec<(PointF64, PointF64)> {
+    let key = |p1: &PointF64, p2: &PointF64| {
+        let (k1, k2) = ((p1.x.to_bits(), p1.y.to_bits()), (p2.x.to_bits(), p2.y.to_bits()));
+        if k1 < k2 { (k1, k2) } else { (k2, k1) }
+    };
+    let edges = || polygons.iter().flat_map(|polygon| {
+        let points = &polygon.props().points;
+        (0..points.len()).map(move |curr| (points[curr], points[(curr+1) % points.len()]))
+    });
+    let mut counts = HashMap::new();
+    for (p1, p2) in edges() {
+        *counts.entry(key(&p1, &p2)).or_insert(0) += 1;
+    }
+    let mut shared = vec![];
+    for (p1, p2) in edges() {
+        if let Some(count) = counts.get_mut(&key(&p1, &p2)) {
+            if *count > 1 {
+                shared.push((p1, p2));
+                // Only the first time
+                *count = 0;
+            }
+        }
+    }
+    shared
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use visioncortex::{CompoundPath, PathI32, PointI32, Spline};
+    use crate::polypartition::{PieceShape, polygons_area, remove_holes, triangulate_ec_vec, verify_partition};
+    use crate::polypartition::fixtures::ring;
+
+    fn assert_triangulable(polygons: &[Polygon]) {
+        let triangles = triangulate_ec_vec(remove_holes(polygons).unwrap()).unwrap();
+        verify_partition(polygons, &triangles, PieceShape::Triangle).unwrap();
+    }
+
+    #[test]
+    fn svg_round_trips_compound_paths() {
+        let mut square = PathI32::new();
+        for &(x, y) in [(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)].iter() {
+            square.add(PointI32::new(x, y));
+        }
+        let mut hole = PathI32::new();
+        for &(x, y) in [(3, 3), (3, 7), (7, 7), (7, 3), (3, 3)].iter() {
+            hole.add(PointI32::new(x, y));
+        }
+        let mut compound = CompoundPath::new();
+        compound.add_path_i32(square);
+        compound.add_path_i32(hole);
+        let (d, offset) = compound.to_svg_string(true, PointF64::new(100.0, 50.0));
+        let svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\"><path d=\"{}\" transform=\"translate({},{})\"/></svg>", d, offset.x, offset.y);
+        let polygons = parse_svg(&svg, 0.1).unwrap();
+        assert_eq!(polygons.len(), 2);
+        assert!(!polygons[0].is_hole() && polygons[1].is_hole());
+        assert_eq!(polygons[0].props().bounding_rect_f64().left, 100.0);
+        assert_eq!(polygons_area(&polygons), 84.0);
+        assert_triangulable(&polygons);
+
+        // A circle from four cubic Béziers
+        let k = 0.5523 * 10.0;
+        let mut spline = Spline::new(PointF64::new(10.0, 0.0));
+        spline.add(PointF64::new(10.0, k), PointF64::new(k, 10.0), PointF64::new(0.0, 10.0));
+        spline.add(PointF64::new(-k, 10.0), PointF64::new(-10.0, k), PointF64::new(-10.0, 0.0));
+        spline.add(PointF64::new(-10.0, -k), PointF64::new(-k, -10.0), PointF64::new(0.0, -10.0));
+        spline.add(PointF64::new(k, -10.0), PointF64::new(10.0, -k), PointF64::new(10.0, 0.0));
+        let mut compound = CompoundPath::new();
+        compound.add_spline(spline);
+        let (d, _) = compound.to_svg_string(true, PointF64::default());
+        let polygons = parse_svg_path(&d, 0.01, FillRule::NonZero).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert!((polygons_area(&polygons) - PI * 100.0).abs() < 0.5);
+        assert!(polygons[0].props().points.iter().all(|p| ((p.x + 10.0).hypot(p.y) - 10.0).abs() < 0.02));
+        assert_triangulable(&polygons);
+    }
+
+    #[test]
+    fn svg_reads_elements_and_fill_rules() {
+        let svg = r#"<?xml version="1.0"?>
+            <!-- Three nested squares, wound the same way -->
+            <svg viewBox="0 0 100 100">
+              <defs><rect width="5" height="5"/></defs>
+              <g transform="translate(10, 0)" style="fill-rule: evenodd">
+                <path d="M0 0h30v30H0z M5 5h20v20H5z m5 5h10v10h-10z"/>
+                <path fill-rule="nonzero" transform="translate(40 0)" d="M0 0h30v30H0z M5 5h20v20H5z"/>
+              </g>
+              <polygon points="0,40 10,40 5,50" fill="none"/>
+              <polygon points="0,40 10,40 5,50"/>
+              <rect x="0" y="60" width="20" height="10" rx="5" transform="rotate(90 10 65)"/>
+            </svg>"#;
+        let polygons = parse_svg(svg, 0.01).unwrap();
+        let holes: Vec<bool> = polygons.iter().map(|polygon| polygon.is_hole()).collect();
+        assert_eq!(holes, vec![false, true, false, false, false, false]);
+        assert_eq!(polygons_area(&polygons[..3]), 900.0 - 400.0 + 100.0);
+        assert_eq!(polygons_area(&polygons[3..4]), 900.0);
+        assert_eq!(polygons_area(&polygons[4..5]), 50.0);
+        let rect = polygons[5].props().bounding_rect_f64();
+        assert!((rect.width() - 10.0).abs() < 1e-9 && (rect.height() - 20.0).abs() < 1e-9);
+        assert!((polygons_area(&polygons[5..]) - (200.0 - (4.0 - PI) * 25.0)).abs() < 0.5);
+        assert_triangulable(&polygons[..3]);
+
+        // Opposite winding makes a hole under nonzero, and arcs and quadratics are flattened
+        let polygons = parse_svg_path("M0 0 L20 0 20 20 0 20Z M5 5 V15 H15 V5 Z M30 0 a10 10 0 0 1 0 20 Q20 10 30 0", 0.01, FillRule::NonZero).unwrap();
+        let holes: Vec<bool> = polygons.iter().map(|polygon| polygon.is_hole()).collect();
+        assert_eq!(holes, vec![false, true, false]);
+
+        assert_eq!(parse_svg("<svg>\n  <path d=\"M0 0 L1\"/></svg>", 0.1).unwrap_err(), "Line 2, column 3: In <path>: Expected a number at character 8");
+        assert!(parse_svg("<svg><path d=\"M0 0 L1 0 L0 1Z 1 1\"/></svg>", 0.1).is_err());
+        assert!(parse_svg("<svg><g transform=\"spin(3)\"></g></svg>", 0.1).is_err());
+        assert!(parse_svg("<svg><path d='M0 0'", 0.1).unwrap_err().starts_with("Line 1, column 6"));
+        assert!(parse_svg("<svg/>", 0.0).is_err());
+    }
+
+    #[test]
+    fn svg_writes_styled_pieces() {
+        let pieces = vec![
+            ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false),
+            ring(&[(0.0, 0.0), (10.0, 10.0), (0.0, 10.0)], false),
+            ring(&[(20.0, 0.0), (30.5, 0.0), (25.0, 10.0)], false),
+            ring(&[(22.0, 1.0), (25.0, 5.0), (28.0, 1.0)], true),
+        ];
+        let options = SvgOptions { margin: 5.0, vertex_radius: 0.5, highlight_diagonals: true, label_pieces: true, ..Default::default() };
+        let svg = write_svg(&pieces, &options);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-5 -5 40.5 20\""));
+        assert!(svg.contains("<polygon points=\"0,0 10,0 10,10\" fill=\"#D8334A\"/>"));
+        assert!(svg.contains("<polygon points=\"20,0 30.5,0 25,10\" fill=\"#A0D468\"/>"));
+        assert!(svg.contains("<g fill=\"#FFFFFF\" stroke=\"#000000\" stroke-width=\"1\" stroke-dasharray=\"4 2\">\n<polygon points=\"22,1 25,5 28,1\"/>"));
+        // The only diagonal
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains("<path d=\"M10,10 L0,0\"/>"));
+        assert_eq!(svg.matches("<circle").count(), 10);
+        assert!(svg.contains(">2</text>") && !svg.contains(">3</text>"));
+
+        // The pieces read back, with the hole as another solid
+        let parsed = parse_svg(&svg, 0.1).unwrap();
+        assert_eq!(parsed.len(), 4);
+        for (parsed, piece) in parsed.iter().zip(pieces.iter().take(3)) {
+            assert_eq!(parsed.props().points, piece.props().points);
+        }
+        assert_eq!(write_svg(&[], &SvgOptions::default()), "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-10 -10 20 20\" width=\"20\" height=\"20\">\n<g stroke=\"#000000\" stroke-width=\"1\" stroke-linejoin=\"round\">\n</g>\n</svg>");
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+
+use visioncortex::PointF64;
+
+use super::{Polygon, PolygonInterface};
+
+/// Where and why the text is not in the polypartition format. Lines and columns count from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Reads polygons in the polypartition text format: the number of polygons, then for each polygon
+/// its number of vertices, whether it is a hole (0 or 1), and one "x y" line per vertex.
+///
+/// Everything from a '#' to the end of its line is a comment. Blank lines, surrounding whitespace
+/// and CRLF line endings are allowed.
+pub fn parse_polygons(text: &str) -> Result<Vec<Polygon>, ParseError> {
+    let mut reader = Reader::new(text);
+
+    let num_polygons = reader.read_count("the number of polygons")?;
+    let mut polygons = Vec::with_capacity(num_polygons.min(1024));
+    for _ in 0..num_polygons {
+        let (line, column, num_points) = reader.read_count_at("the number of vertices")?;
+        if num_points < 3 {
+            return Err(ParseError { line, column, message: format!("A polygon needs at least 3 vertices, found {}", num_points) });
+        }
+        let is_hole = reader.read_hole_flag()?;
+        let mut points = Vec::with_capacity(num_points.min(1 << 16));
+        for _ in 0..num_points {
+            points.push(reader.read_point()?);
+        }
+        polygons.push(Polygon::from_points_and_is_hole(points, is_hole));
+    }
+
+    if let Some((line, tokens)) = reader.next_line() {
+        let (column, token) = tokens[0];
+        return Err(ParseError { line, column, message: format!("Unexpected '{}' after the last polygon", token) });
+    }
+    Ok(polygons)
+}
+
+/// Writes polygons in the polypartition text format, as `Tester` dumps them. With `decimal`,
+/// coordinates are written in full and `parse_polygons` reads back the same polygons;
+/// without, they are truncated to integers.
+pub fn write_polygons(polygons: &[Polygon], decimal: bool) -> String {
+    let mut dump = vec![polygons.len().to_string()];
+    for polygon in polygons.iter() {
+        dump.push(polygon.props().dump(decimal));
+    }
+    dump.join("\n")
+}
+
+/// A line number, with the column and text of each token
+type Line<'a> = (usize, Vec<(usize, &'a str)>);
+
+struct Reader<'a> {
+    /// The non-blank lines
+    lines: Vec<Line<'a>>,
+    next: usize,
+    /// Where the text ends
+    end: (usize, usize),
+}
+
+impl<'a> Reader<'a> {
+    fn new(text: &'a str) -> Self {
+        let mut lines = vec![];
+        let mut end = (1, 1);
+        for (i, line) in text.split('\n').enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            end = (i + 1, line.chars().count() + 1);
+            let content = line.split('#').next().unwrap_or("");
+            let mut tokens = vec![];
+            let mut start = None;
+            for (column, (offset, c)) in content.char_indices().enumerate() {
+                match (c.is_whitespace(), start) {
+                    (false, None) => start = Some((column + 1, offset)),
+                    (true, Some((token_column, token_offset))) => {
+                        tokens.push((token_column, &content[token_offset..offset]));
+                        start = None;
+                    },
+                    _ => {},
+                }
+            }
+            if let Some((token_column, token_offset)) = start {
+                tokens.push((token_column, &content[token_offset..]));
+            }
+            if !tokens.is_empty() {
+                lines.push((i + 1, tokens));
+            }
+        }
+        Self { lines, next: 0, end }
+    }
+
+    fn next_line(&mut self) -> Option<Line<'a>> {
+        let line = self.lines.get(self.next).cloned();
+        self.next += 1;
+        line
+    }
+
+    /// The next line, which must have exactly `num_tokens` tokens
+    fn expect_line(&mut self, num_tokens: usize, expected: &str) -> Result<Line<'a>, ParseError> {
+        let (line, tokens) = self.next_line().ok_or_else(|| ParseError {
+            line: self.end.0,
+            column: self.end.1,
+            message: format!("Expected {}, found the end of the input", expected),
+        })?;
+        if tokens.len() > num_tokens {
+            let (column, token) = tokens[num_tokens];
+            return Err(ParseError { line, column, message: format!("Unexpected '{}' after {}", token, expected) });
+        }
+        if tokens.len() < num_tokens {
+            let column = tokens.last().map(|(column, token)| column + token.chars().count()).unwrap_or(1);
+            return Err(ParseError { line, column, message: format!("Expected {}, found the end of the line", expected) });
+        }
+        Ok((line, tokens))
+    }
+
+    fn read_count(&mut self, expected: &str) -> Result<usize, ParseError> {
+        Ok(self.read_count_at(expected)?.2)
+    }
+
+    /// Returns the line and column of the count too
+    fn read_count_at(&mut self, expected: &str) -> Result<(usize, usize, usize), ParseError> {
+        let (line, tokens) = self.expect_line(1, expected)?;
+        let (column, token) = tokens[0];
+        let count = token.parse().map_err(|_| ParseError {
+            line, column, message: format!("Expected {}, found '{}'", expected, token),
+        })?;
+        Ok((line, column, count))
+    }
+
+    fn read_hole_flag(&mut self) -> Result<bool, ParseError> {
+        let expected = "whether the polygon is a hole (0 or 1)";
+        let (line, tokens) = self.expect_line(1, expected)?;
+        match tokens[0] {
+            (_, "0") => Ok(false),
+            (_, "1") => Ok(true),
+            (column, token) => Err(ParseError { line, column, message: format!("Expected {}, found '{}'", expected, token) }),
+        }
+    }
+
+    fn read_point(&mut self) -> Result<PointF64, ParseError> {
+        let (line, tokens) = self.expect_line(2, "the x and y of a vertex")?;
+        let mut coords = [0.0; 2];
+        for (coord, &(column, token)) in coords.iter_mut().zip(tokens.iter()) {
+            *coord = match token.parse::<f64>() {
+                Ok(value) if value.is_finite() => value,
+                _ => return Err(ParseError { line, column, message: format!("Expected a finite number, found '{}'", token) }),
+            };
+        }
+        Ok(PointF64::new(coords[0], coords[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{GeneratorOptions, PolygonGenerator, generate_polygons};
+
+    fn error_at(text: &str) -> (usize, usize) {
+        let error = parse_polygons(text).unwrap_err();
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn text_format_reads_comments_and_crlf() {
+        let polygons = parse_polygons(include_str!("../../dev/assets/test_input_format.txt")).unwrap();
+        assert_eq!(polygons.len(), 2);
+        assert!(!polygons[0].is_hole() && polygons[1].is_hole());
+        assert_eq!(polygons[0].get_point(2), PointF64::new(137.0, 419.0));
+
+        let crlf = "# A triangle\r\n\r\n1 \r\n3\r\n0\t# Solid\r\n0 0\r\n  10.5 0\r\n0 -1e1  \r\n";
+        let polygons = parse_polygons(crlf).unwrap();
+        assert_eq!(polygons[0].props().points, vec![PointF64::new(0.0, 0.0), PointF64::new(10.5, 0.0), PointF64::new(0.0, -10.0)]);
+    }
+
+    #[test]
+    fn text_format_reports_line_and_column() {
+        assert_eq!(error_at(""), (1, 1));
+        assert_eq!(error_at("1\n3\n0\n0 0\n1 x\n"), (5, 3));
+        assert_eq!(error_at("1\n3\n0\n0 0\n1 0 2\n"), (5, 5));
+        assert_eq!(error_at("1\n3\n0\n0 0\n1\n"), (5, 2));
+        assert_eq!(error_at("1\n3\n2\n"), (3, 1));
+        assert_eq!(error_at("1\n  2\n0\n"), (2, 3));
+        assert!(parse_polygons("1\n3\n0\n0 0\n1 0\n0 1\n# Done\n\n").is_ok());
+        assert_eq!(error_at("1\n3\n0\n0 0\n1 0\n0 1\n1\n"), (7, 1));
+        assert_eq!(error_at("1\n3\n0\n0 0\n1 inf\n"), (5, 3));
+        let error = parse_polygons("2\n3\n0\n0 0\n1 0\n0 1").unwrap_err();
+        assert_eq!(error.to_string(), "Line 6, column 4: Expected the number of vertices, found the end of the input");
+    }
+
+    #[test]
+    fn text_format_round_trips() {
+        let options = GeneratorOptions { generator: PolygonGenerator::Spiral, num_vertices: 20, num_holes: 3, ..Default::default() };
+        let polygons = generate_polygons(&options).unwrap();
+        let text = write_polygons(&polygons, true);
+        let parsed = parse_polygons(&text).unwrap();
+        assert_eq!(parsed.len(), polygons.len());
+        for (parsed, polygon) in parsed.iter().zip(polygons.iter()) {
+            assert_eq!(parsed.props().points, polygon.props().points);
+            assert_eq!(parsed.is_hole(), polygon.is_hole());
+        }
+        assert_eq!(write_polygons(&parsed, true), text);
+    }
+}
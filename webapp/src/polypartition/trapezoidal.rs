@@ -0,0 +1,218 @@
+use visioncortex::PointF64;
+
+use super::{EdgeVec, PointLocator, Polygon, PolygonInterface, ScanLineEdge, is_above, is_strictly_convex, sweep_order};
+
+/// Polygons cut into trapezoids with horizontal top and bottom sides
+pub struct TrapezoidalDecomposition {
+    /// Trapezoids from top to bottom, wound like non-hole polygons.
+    /// A trapezoid with a zero-width side is a triangle.
+    pub trapezoids: Vec<Polygon>,
+    /// Finds the trapezoid containing a point
+    pub locator: PointLocator,
+}
+
+/// A trapezoid between two edges of the input, cut by the horizontal lines at `top` and `bottom`
+#[derive(Clone, Copy, Debug)]
+pub struct Trapezoid {
+    pub left: (PointF64, PointF64),
+    pub right: (PointF64, PointF64),
+    /// The smaller y
+    pub top: f64,
+    pub bottom: f64,
+}
+
+impl Trapezoid {
+    /// Wound like non-hole polygons, without the repeated point of a zero-width side
+    pub fn to_polygon(&self) -> Polygon {
+        make_trapezoid(&self.left, &self.right, self.top, self.bottom)
+    }
+}
+
+/// Decomposes polygons with holes into trapezoids.
+///
+/// A horizontal line is extended from every vertex to the nearest edges on both sides.
+/// See `trapezoid_sweep`.
+pub fn trapezoidal_decomposition(polys: &[Polygon]) -> Result<TrapezoidalDecomposition, &'static str> {
+    if polys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+
+    let trapezoids = trapezoid_sweep(polys);
+    Ok(TrapezoidalDecomposition {
+        locator: PointLocator::from_trapezoids(&trapezoids),
+        trapezoids: trapezoids.iter().map(|trapezoid| trapezoid.to_polygon()).collect(),
+    })
+}
+
+/// The trapezoids of polygons with holes, from top to bottom.
+///
+/// Sweeps the vertices in the order of `monotone_partition_mesh`, keeping the edges crossing the
+/// sweep line in an `EdgeVec`. Between the (2k)-th and (2k+1)-th edge from the left is the inside.
+/// At each vertex, the trapezoids next to it end and new ones begin between the edges that now
+/// surround it. Horizontal edges only ever bound a trapezoid, so they are left out of the tree.
+///
+/// Takes O(n log n) comparisons, plus moving the tree's elements on insertion.
+pub fn trapezoid_sweep(polys: &[Polygon]) -> Vec<Trapezoid> {
+    // Edge i goes from point i to the next point of its ring
+    let mut points = vec![];
+    let mut next = vec![];
+    let mut previous = vec![];
+    for poly in polys.iter() {
+        let start = points.len();
+        let len = poly.num_points();
+        for i in 0..len {
+            points.push(poly.get_point(i));
+            next.push(start + (i+1) % len);
+            previous.push(start + (i+len-1) % len);
+        }
+    }
+    let segment = |e: usize| (points[e], points[next[e]]);
+    // The edges at v that are not horizontal, as (edge, other end), split into those the sweep has passed and not
+    let edges_at = |v: usize| {
+        let p = points[v];
+        let mut ending = vec![];
+        let mut starting = vec![];
+        for &(e, q) in [(previous[v], points[previous[v]]), (v, points[next[v]])].iter() {
+            if q.y == p.y {
+                continue;
+            }
+            if is_above(&q, &p) {
+                starting.push(ScanLineEdge { index: e, p1: p, p2: q });
+            } else {
+                ending.push(e);
+            }
+        }
+        (ending, starting)
+    };
+
+    let priority = sweep_order(&points);
+    let mut edge_tree = EdgeVec::default();
+    let mut edge_tree_pointers = vec![None; points.len()];
+    // For each edge with the inside on its right: the edge across, and the y where the trapezoid between them began
+    let mut open: Vec<Option<(usize, f64)>> = vec![None; points.len()];
+    let mut trapezoids = vec![];
+
+    let mut first = 0;
+    while first < priority.len() {
+        // The vertices on one horizontal line are handled together, so that the tree is whole between them
+        let y = points[priority[first]].y;
+        let last = first + priority[first..].iter().take_while(|&&v| points[v].y == y).count();
+        let batch = &priority[first..last];
+        first = last;
+
+        // End the trapezoids touching the vertices
+        for &v in batch.iter() {
+            let p = points[v];
+            let lo = edge_tree.lower_bound(&ScanLineEdge { index: 0, p1: p, p2: p });
+            let hi = lo + edges_at(v).0.len();
+            for i in lo.saturating_sub(1)..hi.min(edge_tree.len()) {
+                let left = edge_tree.vec[i].borrow().index;
+                if let Some((right, bottom)) = open[left].take() {
+                    if bottom != y {
+                        trapezoids.push(Trapezoid { left: segment(left), right: segment(right), top: y, bottom });
+                    }
+                }
+            }
+        }
+
+        for &v in batch.iter() {
+            for e in edges_at(v).0 {
+                if let Some(ptr) = edge_tree_pointers[e].take() {
+                    edge_tree.remove_ptr(&ptr);
+                }
+            }
+        }
+        for &v in batch.iter() {
+            let p = points[v];
+            let mut starting = edges_at(v).1;
+            // Both edges leave p, so the tree cannot order them against each other
+            if starting.len() == 2 && !is_strictly_convex(&p, &starting[0].p2, &starting[1].p2) {
+                starting.swap(0, 1);
+            }
+            let position = edge_tree.lower_bound(&ScanLineEdge { index: 0, p1: p, p2: p });
+            for (offset, edge) in starting.into_iter().enumerate() {
+                let e = edge.index;
+                edge_tree_pointers[e] = Some(edge_tree.insert_at(position + offset, edge));
+            }
+        }
+
+        // Begin the trapezoids next to the vertices
+        for &v in batch.iter() {
+            let p = points[v];
+            let lo = edge_tree.lower_bound(&ScanLineEdge { index: 0, p1: p, p2: p });
+            let hi = lo + edges_at(v).1.len();
+            for i in lo.saturating_sub(1)..hi.min(edge_tree.len().saturating_sub(1)) {
+                let left = edge_tree.vec[i].borrow().index;
+                if i % 2 == 0 && open[left].is_none() {
+                    open[left] = Some((edge_tree.vec[i+1].borrow().index, y));
+                }
+            }
+        }
+    }
+
+    trapezoids.reverse();
+    trapezoids
+}
+
+/// x of the edge at y, exact at its endpoints
+fn x_at(edge: &(PointF64, PointF64), y: f64) -> f64 {
+    let (p1, p2) = edge;
+    if y == p1.y {
+        p1.x
+    } else if y == p2.y {
+        p2.x
+    } else {
+        p1.x + (y - p1.y) / (p2.y - p1.y) * (p2.x - p1.x)
+    }
+}
+
+fn make_trapezoid(left: &(PointF64, PointF64), right: &(PointF64, PointF64), top: f64, bottom: f64) -> Polygon {
+    let mut points = vec![
+        PointF64::new(x_at(left, top), top),
+        PointF64::new(x_at(right, top), top),
+        PointF64::new(x_at(right, bottom), bottom),
+        PointF64::new(x_at(left, bottom), bottom),
+    ];
+    points.dedup();
+    if points.len() > 3 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    Polygon::from_points_and_is_hole(points, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{GeneratorOptions, Orientation, PolygonGenerator, generate_polygons, polygons_area, signed_area};
+    use crate::polypartition::fixtures::hexagon_with_hole;
+
+    #[test]
+    fn trapezoidal_hexagon_with_hole() {
+        let polys = hexagon_with_hole();
+        let decomposition = trapezoidal_decomposition(&polys).unwrap();
+        let trapezoids = &decomposition.trapezoids;
+
+        // One trapezoid above the hole, one below, and five beside it cut at the vertices of the hole and the hexagon
+        assert_eq!(trapezoids.len(), 7);
+        assert!(trapezoids.iter().all(|t| t.props().get_orientation() == Orientation::CounterClockwise));
+        assert!((polygons_area(trapezoids) - polygons_area(&polys)).abs() < 1e-7);
+
+        assert!(decomposition.locator.locate(&PointF64::new(130.0, 100.0)).is_none());
+        let above = decomposition.locator.locate(&PointF64::new(130.0, 60.0)).unwrap();
+        assert_eq!(trapezoids[above].props().points[0], PointF64::new(60.0, 40.0));
+    }
+
+    #[test]
+    fn trapezoidal_covers_generated_polygons() {
+        for &generator in PolygonGenerator::ALL.iter() {
+            for seed in 0..5 {
+                let options = GeneratorOptions { generator, num_vertices: 30, num_holes: 2, seed, ..Default::default() };
+                let polys = generate_polygons(&options).unwrap();
+                let trapezoids = trapezoidal_decomposition(&polys).unwrap().trapezoids;
+                let expected = polygons_area(&polys);
+                assert!((polygons_area(&trapezoids) - expected).abs() < 1e-6 * expected, "{:?}", options);
+                assert!(trapezoids.iter().all(|t| signed_area(&t.props().points) > 0.0));
+            }
+        }
+    }
+}
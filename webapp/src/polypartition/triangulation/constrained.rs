@@ -0,0 +1,350 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use visioncortex::PointF64;
+
+use crate::polypartition::{Delaunay, DualGraph, Polygon, PolygonInterface, delaunay_triangulation};
+use crate::polypartition::util::{incircle, orient2d};
+
+/// Triangulates the polygons (holes included), with every Steiner point as a vertex and
+/// every constraint segment as a chain of edges.
+///
+/// Returns a vec of triangles.
+pub fn triangulate_constrained(polys: &[Polygon], steiner_points: &[PointF64], segments: &[(PointF64, PointF64)]) -> Result<Vec<Polygon>, String> {
+    let cdt = constrained_delaunay(polys, steiner_points, segments)?;
+    Ok(cdt.triangles.iter().map(|&[a, b, c]| Polygon::triangle(cdt.points[a], cdt.points[b], cdt.points[c])).collect())
+}
+
+/// Same as `triangulate_constrained`, but also returns which triangles share which edges.
+pub fn triangulate_constrained_with_adjacency(polys: &[Polygon], steiner_points: &[PointF64], segments: &[(PointF64, PointF64)]) -> Result<DualGraph, String> {
+    Ok(constrained_delaunay(polys, steiner_points, segments)?.to_dual_graph())
+}
+
+/// The constrained Delaunay triangulation of the polygons: every polygon edge and constraint segment
+/// is an edge of the triangulation, and every other edge is as Delaunay as the constraints allow.
+///
+/// The points are the polygon points in order, then the Steiner points, then the segment end points
+/// and the crossings between segments (or between a segment and a polygon edge), with repeats merged.
+/// Only the triangles inside the polygons (by the even-odd rule) are kept, so every Steiner point
+/// and segment must lie inside or on the polygons.
+pub fn constrained_delaunay(polys: &[Polygon], steiner_points: &[PointF64], segments: &[(PointF64, PointF64)]) -> Result<Delaunay, String> {
+    let mut point_set = PointSet::default();
+    let mut constraints = vec![];
+    for poly in polys.iter() {
+        let indices: Vec<usize> = poly.props().points.iter().map(|&p| point_set.add(p)).collect();
+        for i in 0..indices.len() {
+            constraints.push((indices[i], indices[(i+1) % indices.len()], false));
+        }
+    }
+    let steiner_indices: Vec<usize> = steiner_points.iter().map(|&p| point_set.add(p)).collect();
+    for &(p, q) in segments.iter() {
+        constraints.push((point_set.add(p), point_set.add(q), true));
+    }
+    constraints.retain(|&(a, b, _)| a != b);
+    let constraints = split_constraints(&mut point_set, &constraints);
+
+    let points = point_set.points;
+    let mut mesh = FlipMesh::new(&delaunay_triangulation(&points).triangles);
+    let is_constrained: HashSet<(usize, usize)> = constraints.iter().map(|&(a, b, _)| (a.min(b), a.max(b))).collect();
+    let mut new_edges = vec![];
+    for &(a, b, _) in constraints.iter() {
+        new_edges.extend(mesh.recover_edge(&points, a, b)?);
+    }
+    mesh.restore_delaunay(&points, &is_constrained, new_edges);
+
+    // Even-odd by the number of polygon edges crossed from outside the convex hull, which unlike a
+    // point-in-polygon test stays exact for slivers
+    let mut crosses_ring: HashMap<(usize, usize), bool> = HashMap::new();
+    for &(a, b, _) in constraints.iter().filter(|&&(_, _, is_segment)| !is_segment) {
+        *crosses_ring.entry((a.min(b), a.max(b))).or_insert(false) ^= true;
+    }
+    let crosses_ring = |u: usize, v: usize| crosses_ring.get(&(u.min(v), u.max(v))).copied().unwrap_or(false);
+    let mut is_inside: Vec<Option<bool>> = vec![None; mesh.triangles.len()];
+    let mut queue = VecDeque::new();
+    for (t, triangle) in mesh.triangles.iter().enumerate() {
+        for i in 0..3 {
+            let (u, v) = (triangle[i], triangle[(i+1) % 3]);
+            if is_inside[t].is_none() && !mesh.edges.contains_key(&(v, u)) {
+                is_inside[t] = Some(crosses_ring(u, v));
+                queue.push_back(t);
+            }
+        }
+    }
+    while let Some(t) = queue.pop_front() {
+        let triangle = mesh.triangles[t];
+        for i in 0..3 {
+            let (u, v) = (triangle[i], triangle[(i+1) % 3]);
+            if let Some(&neighbor) = mesh.edges.get(&(v, u)) {
+                if is_inside[neighbor].is_none() {
+                    is_inside[neighbor] = Some(is_inside[t] != Some(crosses_ring(u, v)));
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+    let triangles: Vec<[usize; 3]> = mesh.triangles.iter().zip(is_inside.iter())
+        .filter(|(_, &is_inside)| is_inside == Some(true))
+        .map(|(&triangle, _)| triangle)
+        .collect();
+
+    let is_vertex: HashSet<usize> = triangles.iter().flatten().copied().collect();
+    if steiner_indices.iter().any(|i| !is_vertex.contains(i)) {
+        return Err("Steiner point lies outside the polygons!".into());
+    }
+    let edges: HashSet<(usize, usize)> = triangles.iter()
+        .flat_map(|triangle| (0..3).map(move |i| (triangle[i], triangle[(i+1) % 3])))
+        .collect();
+    if constraints.iter().any(|&(a, b, is_segment)| is_segment && !edges.contains(&(a, b)) && !edges.contains(&(b, a))) {
+        return Err("Constraint segment lies outside the polygons!".into());
+    }
+
+    Ok(Delaunay { points, triangles })
+}
+
+/// Points with repeats merged
+#[derive(Default)]
+struct PointSet {
+    points: Vec<PointF64>,
+    indices: HashMap<(u64, u64), usize>,
+}
+
+impl PointSet {
+    fn add(&mut self, p: PointF64) -> usize {
+        let points = &mut self.points;
+        *self.indices.entry((p.x.to_bits(), p.y.to_bits())).or_insert_with(|| {
+            points.push(p);
+            points.len() - 1
+        })
+    }
+}
+
+/// The point where segments a-b and c-d cross, if they cross at a point interior to both
+fn crossing(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> Option<PointF64> {
+    let (abc, abd) = (orient2d(a, b, c), orient2d(a, b, d));
+    let (cda, cdb) = (orient2d(c, d, a), orient2d(c, d, b));
+    if abc * abd >= 0.0 || cda * cdb >= 0.0 {
+        return None;
+    }
+    let t = cda / (cda - cdb);
+    Some(PointF64::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t))
+}
+
+/// Splits the constraints where they cross each other and where they pass through a point,
+/// so that no constraint has a point in its interior.
+fn split_constraints(point_set: &mut PointSet, constraints: &[(usize, usize, bool)]) -> Vec<(usize, usize, bool)> {
+    let mut splits = vec![vec![]; constraints.len()];
+    for i in 0..constraints.len() {
+        for j in i+1..constraints.len() {
+            let (a, b, _) = constraints[i];
+            let (c, d, _) = constraints[j];
+            let points = &point_set.points;
+            if let Some(p) = crossing(&points[a], &points[b], &points[c], &points[d]) {
+                let k = point_set.add(p);
+                splits[i].push(k);
+                splits[j].push(k);
+            }
+        }
+    }
+
+    let points = &point_set.points;
+    let mut pieces = vec![];
+    for (&(a, b, is_segment), split) in constraints.iter().zip(splits.iter_mut()) {
+        let (pa, pb) = (points[a], points[b]);
+        let direction = pb - pa;
+        split.extend((0..points.len()).filter(|&k| {
+            let p = points[k];
+            k != a && k != b && orient2d(&pa, &pb, &p) == 0.0 && (p - pa).dot(direction) > 0.0 && (p - pb).dot(direction) < 0.0
+        }));
+        split.sort_by(|&k1, &k2| (points[k1] - pa).dot(direction).partial_cmp(&(points[k2] - pa).dot(direction)).unwrap());
+        split.dedup();
+
+        let chain: Vec<usize> = std::iter::once(a).chain(split.iter().copied()).chain(std::iter::once(b)).collect();
+        pieces.extend(chain.windows(2).map(|pair| (pair[0], pair[1], is_segment)));
+    }
+    pieces
+}
+
+/// Whether segment a-b crosses segment u-v at a point interior to both
+fn crosses(points: &[PointF64], a: usize, b: usize, u: usize, v: usize) -> bool {
+    orient2d(&points[a], &points[b], &points[u]) * orient2d(&points[a], &points[b], &points[v]) < 0.0
+        && orient2d(&points[u], &points[v], &points[a]) * orient2d(&points[u], &points[v], &points[b]) < 0.0
+}
+
+/// Triangles, counter-clockwise, that can flip their shared edges
+struct FlipMesh {
+    triangles: Vec<[usize; 3]>,
+    /// The triangle on the left of each directed edge
+    edges: HashMap<(usize, usize), usize>,
+}
+
+impl FlipMesh {
+    fn new(triangles: &[[usize; 3]]) -> Self {
+        let mut mesh = Self { triangles: triangles.to_vec(), edges: HashMap::new() };
+        for t in 0..triangles.len() {
+            mesh.link(t);
+        }
+        mesh
+    }
+
+    fn link(&mut self, t: usize) {
+        let triangle = self.triangles[t];
+        for i in 0..3 {
+            self.edges.insert((triangle[i], triangle[(i+1) % 3]), t);
+        }
+    }
+
+    fn unlink(&mut self, t: usize) {
+        let triangle = self.triangles[t];
+        for i in 0..3 {
+            self.edges.remove(&(triangle[i], triangle[(i+1) % 3]));
+        }
+    }
+
+    /// The vertex opposite the directed edge u-v, in the triangle on its left
+    fn apex(&self, u: usize, v: usize) -> Option<usize> {
+        let triangle = self.triangles[*self.edges.get(&(u, v))?];
+        triangle.iter().copied().find(|&w| w != u && w != v)
+    }
+
+    /// The two apexes of the edge u-v, if the quadrilateral around it is strictly convex
+    fn flippable(&self, points: &[PointF64], u: usize, v: usize) -> Option<(usize, usize)> {
+        let (p, q) = (self.apex(u, v)?, self.apex(v, u)?);
+        if orient2d(&points[u], &points[q], &points[p]) > 0.0 && orient2d(&points[q], &points[v], &points[p]) > 0.0 {
+            Some((p, q))
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the edge u-v with the edge between its apexes p (left of u-v) and q (right of it)
+    fn flip(&mut self, u: usize, v: usize, p: usize, q: usize) {
+        let (t1, t2) = (self.edges[&(u, v)], self.edges[&(v, u)]);
+        self.unlink(t1);
+        self.unlink(t2);
+        self.triangles[t1] = [u, q, p];
+        self.triangles[t2] = [q, v, p];
+        self.link(t1);
+        self.link(t2);
+    }
+
+    /// Flips the edges crossing a-b until a-b is an edge (Sloan's algorithm).
+    /// Returns the edges created on the way.
+    fn recover_edge(&mut self, points: &[PointF64], a: usize, b: usize) -> Result<Vec<(usize, usize)>, &'static str> {
+        if self.edges.contains_key(&(a, b)) || self.edges.contains_key(&(b, a)) {
+            return Ok(vec![]);
+        }
+        let mut crossing: VecDeque<(usize, usize)> = self.triangles.iter()
+            .flat_map(|triangle| (0..3).map(move |i| (triangle[i], triangle[(i+1) % 3])))
+            .filter(|&(u, v)| u < v && crosses(points, a, b, u, v))
+            .collect();
+        let mut new_edges = vec![];
+        let mut stalled = 0;
+        while let Some((u, v)) = crossing.pop_front() {
+            match self.flippable(points, u, v) {
+                Some((p, q)) => {
+                    self.flip(u, v, p, q);
+                    stalled = 0;
+                    if crosses(points, a, b, p, q) {
+                        crossing.push_back((p, q));
+                    } else {
+                        new_edges.push((p, q));
+                    }
+                },
+                None => {
+                    // Some crossing edge is always flippable, unless the input is degenerate
+                    stalled += 1;
+                    if stalled > crossing.len() {
+                        return Err("Cannot recover constraint segment!");
+                    }
+                    crossing.push_back((u, v));
+                },
+            }
+        }
+        Ok(new_edges)
+    }
+
+    /// Flips unconstrained edges, starting from `edges`, until no apex is inside the circumcircle across an edge
+    fn restore_delaunay(&mut self, points: &[PointF64], is_constrained: &HashSet<(usize, usize)>, edges: Vec<(usize, usize)>) {
+        let mut stack = edges;
+        while let Some((u, v)) = stack.pop() {
+            if is_constrained.contains(&(u.min(v), u.max(v))) {
+                continue;
+            }
+            let (p, q) = match self.flippable(points, u, v) {
+                Some(apexes) => apexes,
+                None => continue,
+            };
+            if incircle(&points[u], &points[v], &points[p], &points[q]) > 0.0 {
+                self.flip(u, v, p, q);
+                stack.extend_from_slice(&[(u, q), (q, v), (v, p), (p, u)]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::signed_area;
+    use crate::polypartition::fixtures::square;
+
+    #[test]
+    fn constrained_steiner_points_and_segments() {
+        let polys = vec![square(0.0, 0.0, 20.0, false), square(12.0, 12.0, 4.0, true)];
+        let steiner_points = vec![PointF64::new(4.0, 14.0), PointF64::new(15.0, 5.0)];
+        let segments = vec![
+            (PointF64::new(2.0, 2.0), PointF64::new(8.0, 8.0)),
+            (PointF64::new(2.0, 8.0), PointF64::new(8.0, 2.0)),
+            // A seam from the boundary, which splits the boundary edge
+            (PointF64::new(0.0, 10.0), PointF64::new(6.0, 10.0)),
+        ];
+        let cdt = constrained_delaunay(&polys, &steiner_points, &segments).unwrap();
+        let points = &cdt.points;
+
+        // 9 boundary points, 1 hole, and 8 interior points: 9 + 2 * 1 - 2 + 2 * 8 triangles
+        assert_eq!(points.len(), 17);
+        assert_eq!(cdt.triangles.len(), 25);
+        let area: f64 = cdt.triangles.iter().map(|&[a, b, c]| signed_area(&[points[a], points[b], points[c]])).sum();
+        assert!((area - 384.0).abs() < 1e-9);
+
+        let has_edge = |p: PointF64, q: PointF64| cdt.triangles.iter().any(|triangle| {
+            (0..3).any(|i| points[triangle[i]] == p && points[triangle[(i+1) % 3]] == q)
+        });
+        let crossing = PointF64::new(5.0, 5.0);
+        for &(p, q) in segments[..2].iter() {
+            assert!(has_edge(p, crossing) || has_edge(crossing, p));
+            assert!(has_edge(q, crossing) || has_edge(crossing, q));
+        }
+        assert!(has_edge(segments[2].0, segments[2].1) || has_edge(segments[2].1, segments[2].0));
+        let graph = triangulate_constrained_with_adjacency(&polys, &steiner_points, &segments).unwrap();
+        assert_eq!(graph.num_pieces(), 25);
+
+        assert!(triangulate_constrained(&polys, &[PointF64::new(14.0, 14.0)], &[]).is_err());
+        assert!(triangulate_constrained(&polys, &[], &[(PointF64::new(10.0, 10.0), PointF64::new(30.0, 10.0))]).is_err());
+    }
+
+    #[test]
+    fn constrained_is_delaunay_away_from_constraints() {
+        let polys = vec![square(0.0, 0.0, 10.0, false)];
+        let steiner_points: Vec<PointF64> = (1..10).flat_map(|i| {
+            (1..10).map(move |j| PointF64::new(i as f64 + (j % 3) as f64 * 0.1, j as f64 + (i % 4) as f64 * 0.05))
+        }).collect();
+        let segments = vec![(PointF64::new(0.5, 0.5), PointF64::new(9.5, 9.3))];
+        let cdt = constrained_delaunay(&polys, &steiner_points, &segments).unwrap();
+        let points = &cdt.points;
+        let area: f64 = cdt.triangles.iter().map(|&[a, b, c]| signed_area(&[points[a], points[b], points[c]])).sum();
+        assert!((area - 100.0).abs() < 1e-9);
+
+        let mesh = FlipMesh::new(&cdt.triangles);
+        let (a, b) = (points.len() - 2, points.len() - 1);
+        for &[u, v, w] in cdt.triangles.iter() {
+            assert!(orient2d(&points[u], &points[v], &points[w]) > 0.0);
+            for &(x, y) in [(u, v), (v, w), (w, u)].iter() {
+                if let Some(q) = mesh.apex(y, x) {
+                    let p = mesh.apex(x, y).unwrap();
+                    // An edge may only be non-Delaunay if the constraint separates the two apexes
+                    assert!(incircle(&points[x], &points[y], &points[p], &points[q]) <= 0.0 || crosses(points, a, b, p, q));
+                }
+            }
+        }
+    }
+}
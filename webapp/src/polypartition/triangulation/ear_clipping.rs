@@ -1,4 +1,4 @@
-use crate::polypartition::{PartitionVertex, PartitionVertexInfo, Polygon, PolygonInterface, util::update_vertex};
+use crate::polypartition::{remove_holes_mesh, DualGraph, HalfEdgeMesh, PartitionVertex, PartitionVertexInfo, Polygon, PolygonInterface, util::update_vertex};
 
 pub fn triangulate_ec_vec(polys: Vec<Polygon>) -> Result<Vec<Polygon>, String> {
     let mut triangles = vec![];
@@ -8,10 +8,48 @@ pub fn triangulate_ec_vec(polys: Vec<Polygon>) -> Result<Vec<Polygon>, String> {
     Ok(triangles)
 }
 
+/// Same as `triangulate_ec_vec`, but takes polygons with holes and also returns which triangles share which diagonals.
+///
+/// The holes are bridged by `remove_holes_mesh`, so the triangles on both sides of a bridge are adjacent.
+pub fn triangulate_ec_vec_with_adjacency(polys: Vec<Polygon>) -> Result<DualGraph, String> {
+    Ok(DualGraph::from_mesh(&triangulate_ec_mesh(&polys)?))
+}
+
+/// Takes polygons with holes.
+///
+/// Returns the mesh of `remove_holes_mesh` with every face cut into triangles along its diagonals.
+pub fn triangulate_ec_mesh(polys: &[Polygon]) -> Result<HalfEdgeMesh, &'static str> {
+    let mut mesh = remove_holes_mesh(polys)?;
+    let faces: Vec<usize> = mesh.active_faces().collect();
+    for face in faces {
+        let triangles = triangulate_ec_indices(&mesh.get_face_polygon(face))?;
+        mesh.add_ear_triangles(face, &triangles)?;
+    }
+    mesh.rebuild_faces()?;
+    Ok(mesh)
+}
+
 /// Takes an arbitrary polygon.
 ///
 /// Returns a vec of triangles.
 pub fn triangulate_ec(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
+    // Trivial case
+    if poly.num_points() == 3 {
+        return Ok(vec![poly.clone()]);
+    }
+
+    let triangles = triangulate_ec_indices(poly)?;
+    Ok(triangles.iter().map(|&[p1, p2, p3]| Polygon::triangle(
+        poly.get_point(p1),
+        poly.get_point(p2),
+        poly.get_point(p3),
+    )).collect())
+}
+
+/// Takes an arbitrary polygon.
+///
+/// Returns a vec of triangles, each as the indices of its vertices in the input polygon.
+pub fn triangulate_ec_indices(poly: &Polygon) -> Result<Vec<[usize; 3]>, &'static str> {
     if !poly.is_valid() {
         return Err("Input polygon is invalid.");
     }
@@ -25,7 +63,7 @@ pub fn triangulate_ec(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
     }
     // Trivial case
     if num_vertices == 3 {
-        return Ok(vec![poly.clone()]);
+        return Ok(vec![[0, 1, 2]]);
     }
 
     let mut vertices = vec![PartitionVertex::default(); num_vertices];
@@ -86,11 +124,7 @@ pub fn triangulate_ec(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
         let prev = vertices[ear].previous;
         let next = vertices[ear].next;
 
-        triangles.push(Polygon::triangle(
-            vertices[prev].info.p,
-            vertices[ear].info.p,
-            vertices[next].info.p,
-        ));
+        triangles.push([prev, ear, next]);
 
         vertices[ear].info.is_active = false;
         // Tighten the loose ends
@@ -105,13 +139,9 @@ pub fn triangulate_ec(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
         update_vertex(next, &mut vertices);
     }
 
-    for vertex in vertices.iter() {
+    for (i, vertex) in vertices.iter().enumerate() {
         if vertex.info.is_active {
-            triangles.push(Polygon::triangle(
-            vertices[vertex.previous].info.p,
-            vertex.info.p,
-            vertices[vertex.next].info.p,
-            ));
+            triangles.push([vertex.previous, i, vertex.next]);
             break;
         }
     }
@@ -1,7 +1,9 @@
+mod constrained;
 mod ear_clipping;
 mod optimal_dp;
 mod monotone;
 
+pub use constrained::*;
 pub use ear_clipping::*;
 pub use optimal_dp::*;
-pub use monotone::*;
\ No newline at end of file
+pub use monotone::*;
@@ -1,5 +1,5 @@
-use crate::polypartition::{EdgeVec, Polygon, PolygonInterface};
-use crate::polypartition::util::{add_diagonal, f64_approximately, is_above, is_convex, MonotoneVertex, ScanLineEdge};
+use crate::polypartition::{DualGraph, EdgeVec, HalfEdgeMesh, Polygon, PolygonInterface};
+use crate::polypartition::util::{is_above, is_strictly_convex, sweep_order, ScanLineEdge};
 use crate::polypartition::enums::VertexType;
 
 use visioncortex::PointF64;
@@ -13,7 +13,43 @@ pub fn triangulate_mono_vec(polys: Vec<Polygon>) -> Result<Vec<Polygon>, String>
     Ok(triangles)
 }
 
+/// Same as `triangulate_mono_vec`, but also returns which triangles share which diagonals.
+pub fn triangulate_mono_vec_with_adjacency(polys: Vec<Polygon>) -> Result<DualGraph, String> {
+    Ok(DualGraph::from_mesh(&triangulate_mono_mesh(&polys)?))
+}
+
+/// Takes polygons with holes.
+///
+/// Returns the mesh of `monotone_partition_mesh` with every monotone piece cut into triangles along its diagonals.
+pub fn triangulate_mono_mesh(polys: &[Polygon]) -> Result<HalfEdgeMesh, &'static str> {
+    let mut mesh = monotone_partition_mesh(polys)?;
+    let faces: Vec<usize> = mesh.active_faces().collect();
+    for face in faces {
+        let triangles = triangulate_mono_indices(&mesh.get_face_polygon(face))?;
+        mesh.add_ear_triangles(face, &triangles)?;
+    }
+    mesh.rebuild_faces()?;
+    Ok(mesh)
+}
+
 pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
+    // Trivial case
+    if poly.num_points() == 3 {
+        return Ok(vec![poly.clone()]);
+    }
+
+    let triangles = triangulate_mono_indices(poly)?;
+    Ok(triangles.iter().map(|&[p1, p2, p3]| Polygon::triangle(
+        poly.get_point(p1),
+        poly.get_point(p2),
+        poly.get_point(p3),
+    )).collect())
+}
+
+/// Takes a monotone polygon.
+///
+/// Returns a vec of triangles, each as the indices of its vertices in the input polygon.
+pub fn triangulate_mono_indices(poly: &Polygon) -> Result<Vec<[usize; 3]>, &'static str> {
     if !poly.is_valid() {
         return Err("Input polygon is invalid.");
     }
@@ -22,7 +58,7 @@ pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
 
     // Trivial case
     if num_points == 3 {
-        return Ok(vec![poly.clone()]);
+        return Ok(vec![[0, 1, 2]]);
     }
     
     let points = &poly.props().points;
@@ -106,13 +142,9 @@ pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
         if vertex_types[v_index] != vertex_types[stack[stack_ptr - 1]] {
             for j in 0..(stack_ptr-1) {
                 if vertex_types[v_index] == 1 {
-                    triangles.push(Polygon::triangle(
-                        points[stack[j+1]], points[stack[j]], points[v_index]
-                    ));
+                    triangles.push([stack[j+1], stack[j], v_index]);
                 } else {
-                    triangles.push(Polygon::triangle(
-                        points[stack[j]], points[stack[j+1]], points[v_index]
-                    ));
+                    triangles.push([stack[j], stack[j+1], v_index]);
                 }
             }
             stack[0] = priority[i-1];
@@ -122,18 +154,14 @@ pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
             stack_ptr -= 1;
             while stack_ptr > 0 {
                 if vertex_types[v_index] == 1 {
-                    if is_convex(&points[v_index], &points[stack[stack_ptr - 1]], &points[stack[stack_ptr]]) {
-                        triangles.push(Polygon::triangle(
-                            points[v_index], points[stack[stack_ptr-1]], points[stack[stack_ptr]]
-                        ));
+                    if is_strictly_convex(&points[v_index], &points[stack[stack_ptr - 1]], &points[stack[stack_ptr]]) {
+                        triangles.push([v_index, stack[stack_ptr-1], stack[stack_ptr]]);
                         stack_ptr -= 1;
                     } else {
                         break;
                     }
-                } else if is_convex(&points[v_index], &points[stack[stack_ptr]], &points[stack[stack_ptr - 1]]) {
-                    triangles.push(Polygon::triangle(
-                        points[v_index], points[stack[stack_ptr]], points[stack[stack_ptr-1]]
-                    ));
+                } else if is_strictly_convex(&points[v_index], &points[stack[stack_ptr]], &points[stack[stack_ptr - 1]]) {
+                    triangles.push([v_index, stack[stack_ptr], stack[stack_ptr-1]]);
                     stack_ptr -= 1;
                 } else { 
                     break;
@@ -146,13 +174,9 @@ pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
     let v_index = priority[num_points-1];
     for j in 0..(stack_ptr-1) {
         if vertex_types[stack[j+1]] == 1 {
-            triangles.push(Polygon::triangle(
-                points[stack[j]], points[stack[j+1]], points[v_index]
-            ));
+            triangles.push([stack[j], stack[j+1], v_index]);
         } else {
-            triangles.push(Polygon::triangle(
-                points[stack[j+1]], points[stack[j]], points[v_index]
-            ));
+            triangles.push([stack[j+1], stack[j], v_index]);
         }
     }
 
@@ -160,258 +184,152 @@ pub fn triangulate_mono(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
 }
 
 pub fn monotone_partition(inpolys: Vec<Polygon>) -> Result<Vec<Polygon>, &'static str> {
-    if inpolys.iter().any(|poly| !poly.is_valid()) {
-        return Err("Some input polygon is invalid!");
-    }
+    Ok(monotone_partition_mesh(&inpolys)?.to_polygons())
+}
 
-    let num_vertices = inpolys.iter().fold(0, |acc, poly| acc + poly.num_points());
+/// Same as `monotone_partition`, but also returns which monotone pieces share which diagonals.
+pub fn monotone_partition_with_adjacency(inpolys: Vec<Polygon>) -> Result<DualGraph, &'static str> {
+    Ok(DualGraph::from_mesh(&monotone_partition_mesh(&inpolys)?))
+}
 
-    let max_num_vertices = num_vertices * 3;
-    let mut vertices = vec![MonotoneVertex::default(); max_num_vertices];
-    let mut new_num_vertices = num_vertices;
+/// Partitions the input polygons (holes included) into monotone pieces.
+///
+/// Returns each piece as indices into the points of all input polygons, concatenated in order.
+pub fn monotone_partition_indices(inpolys: &[Polygon]) -> Result<Vec<Vec<usize>>, &'static str> {
+    let mesh = monotone_partition_mesh(inpolys)?;
+    Ok(mesh.active_faces().map(|face| mesh.face_vertices(face)).collect())
+}
 
-    let mut poly_start_index = 0;
-    for poly in inpolys.iter() {
-        let num_points = poly.num_points();
-        let poly_end_index = poly_start_index + num_points - 1;
-        for i in 0..num_points {
-            vertices[i + poly_start_index].p = poly.get_point(i);
-            if i == 0 {
-                vertices[i + poly_start_index].previous = poly_end_index;
-            } else {
-                vertices[i + poly_start_index].previous = i + poly_start_index - 1;
-            }
-            if i == num_points - 1 {
-                vertices[i + poly_start_index].next = poly_start_index;
-            } else {
-                vertices[i + poly_start_index].next = i + poly_start_index + 1;
-            }
-        }
-        poly_start_index = poly_end_index + 1;
+/// Partitions the input polygons (holes included) into monotone pieces, by a sweep that adds
+/// its diagonals to the mesh of the input rings (see `HalfEdgeMesh::from_rings`).
+///
+/// Returns the mesh with one face per piece.
+pub fn monotone_partition_mesh(inpolys: &[Polygon]) -> Result<HalfEdgeMesh, &'static str> {
+    if inpolys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
     }
 
-    let mut priority: Vec<usize> = (0..num_vertices).collect();
-    priority.sort_by(|&index1, &index2| {
-        let p1 = vertices[index1].p;
-        let p2 = vertices[index2].p;
-        let result;
-        // Primary key is y, secondary key is x
-        if !f64_approximately(p1.y, p2.y) {
-            result = p1.y.partial_cmp(&p2.y).unwrap();
-        } else {
-            result = p1.x.partial_cmp(&p2.x).unwrap();
-        }
-        // Sort in falling order
-        if let std::cmp::Ordering::Less = result {
-            std::cmp::Ordering::Greater
-        } else {
-            std::cmp::Ordering::Less
-        }
-    });
+    // Vertex i is left by half-edge i along its ring, which is also how the sweep names that edge.
+    // Diagonals are new half-edges, so the edges of the rings keep their names throughout.
+    let mut mesh = HalfEdgeMesh::from_rings(inpolys)?;
+    let num_vertices = mesh.vertices.len();
+    let point = |mesh: &HalfEdgeMesh, h: usize| mesh.vertices[mesh.half_edges[h].origin].p;
+
+    let points: Vec<PointF64> = mesh.vertices.iter().map(|vertex| vertex.p).collect();
+    let priority = sweep_order(&points);
 
     // Determine vertex types
-    let mut vertex_types = vec![VertexType::Null; max_num_vertices];
-    for i in 0..num_vertices {
-        let v = &vertices[i];
-        let v_prev = &vertices[v.previous];
-        let v_next = &vertices[v.next];
-
-        if is_above(&v_prev.p, &v.p) && is_above(&v_next.p, &v.p) {
-            if is_convex(&v_next.p, &v_prev.p, &v.p) {
-                vertex_types[i] = VertexType::Start;
+    let mut vertex_types = vec![VertexType::Null; num_vertices];
+    for (i, vertex_type) in vertex_types.iter_mut().enumerate() {
+        let p = mesh.vertices[i].p;
+        let p_prev = point(&mesh, mesh.half_edges[i].previous);
+        let p_next = point(&mesh, mesh.half_edges[i].next);
+
+        if is_above(&p_prev, &p) && is_above(&p_next, &p) {
+            if is_strictly_convex(&p_next, &p_prev, &p) {
+                *vertex_type = VertexType::Start;
             } else {
-                vertex_types[i] = VertexType::Split;
+                *vertex_type = VertexType::Split;
             }
-        } else if is_above(&v.p, &v_prev.p) && is_above(&v.p, &v_next.p) {
-            if is_convex(&v_next.p, &v_prev.p, &v.p) {
-                vertex_types[i] = VertexType::End;
+        } else if is_above(&p, &p_prev) && is_above(&p, &p_next) {
+            if is_strictly_convex(&p_next, &p_prev, &p) {
+                *vertex_type = VertexType::End;
             } else {
-                vertex_types[i] = VertexType::Merge;
+                *vertex_type = VertexType::Merge;
             }
         } else {
-            vertex_types[i] = VertexType::Regular;
+            *vertex_type = VertexType::Regular;
         }
     }
+    let is_merge = |mesh: &HalfEdgeMesh, h: usize| matches!(vertex_types[mesh.half_edges[h].origin], VertexType::Merge);
 
-    // Correct up to this point
-
-    let mut helpers = vec![0; max_num_vertices];
+    // The helper of each edge in the tree, as the half-edge starting the corner of the helper vertex
+    // that faces the edge, which is where a diagonal to the helper goes
+    let mut helpers = vec![0; num_vertices];
 
     let mut edge_tree = EdgeVec::default();
-    let mut edge_tree_pointers = vec![None; max_num_vertices];
+    let mut edge_tree_pointers = vec![None; num_vertices];
 
-    for &v_index in priority.iter() {
-        let v = &vertices[v_index].clone();
-        let mut v_index2 = v_index;
-        let mut v2 = v;
+    for &v in priority.iter() {
+        // No diagonal has reached v yet, so its previous half-edge is still the edge of its ring
+        let prev = mesh.half_edges[v].previous;
+        let p = mesh.vertices[v].p;
+        let new_edge = ScanLineEdge { index: v, p1: p, p2: point(&mesh, mesh.half_edges[v].next) };
+        let probe = ScanLineEdge { index: 0, p1: p, p2: p };
 
         // Depending on the vertex type, do the appropriate action
-        match vertex_types[v_index] {
-            VertexType::Null => {panic!()},
+        match vertex_types[v] {
+            VertexType::Null => return Err("Vertex type is not set."),
             VertexType::Start => {
-                let mut new_edge = ScanLineEdge::default();
-                new_edge.p1 = v.p;
-                new_edge.p2 = vertices[v.next].p;
-                new_edge.index = v_index;
-                let ptr = edge_tree.insert(new_edge);
-                edge_tree_pointers[v_index] = ptr;
-                helpers[v_index] = v_index;
+                edge_tree_pointers[v] = edge_tree.insert(new_edge);
+                helpers[v] = v;
             },
             VertexType::End => {
-                if edge_tree_pointers[v.previous].is_none() {
-                    return Err("Pointer is NULL.");
-                }
-                
-                if let VertexType::Merge = vertex_types[helpers[v.previous]] {
-                    add_diagonal(&mut vertices, &mut new_num_vertices, v_index, helpers[v.previous],
-                        &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-                }
-
-                if let Some(ptr) = &edge_tree_pointers[v.previous] {
-                    edge_tree.remove(&ptr.borrow());
+                let ptr = edge_tree_pointers[prev].take().ok_or("Pointer is NULL.")?;
+                if is_merge(&mesh, helpers[prev]) {
+                    mesh.add_diagonal(v, helpers[prev]);
                 }
-                edge_tree_pointers[v.previous] = None;
+                edge_tree.remove_ptr(&ptr);
             },
             VertexType::Split => {
-                let mut new_edge = ScanLineEdge::default();
-                new_edge.p1 = v.p;
-                new_edge.p2 = v.p;
-                let mut edge_pos_index = edge_tree.lower_bound(&new_edge);
+                let edge_pos_index = edge_tree.lower_bound(&probe);
                 if edge_pos_index == 0 {
                     return Err("edge_iter is the first in EdgeTree.");
                 }
-                edge_pos_index -= 1;
+                let index = edge_tree.vec[edge_pos_index - 1].borrow().index;
 
-                let index = edge_tree.get_edge_copy(edge_pos_index).unwrap().index;
+                let (d1, _) = mesh.add_diagonal(v, helpers[index]);
+                helpers[index] = d1;
 
-                add_diagonal(&mut vertices, &mut new_num_vertices, v_index, helpers[index],
-                    &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-
-                v_index2 = new_num_vertices - 2;
-                v2 = &vertices[v_index2];
-                helpers[index] = v_index;
-
-                let mut new_edge = ScanLineEdge::default();
-                new_edge.p1 = v2.p;
-                new_edge.p2 = vertices[v2.next].p;
-                new_edge.index = v_index2;
-                let ptr = edge_tree.insert(new_edge);
-                edge_tree_pointers[v_index2] = ptr;
-                helpers[v_index2] = v_index2;
+                edge_tree_pointers[v] = edge_tree.insert(new_edge);
+                helpers[v] = v;
             },
             VertexType::Merge => {
-                if edge_tree_pointers[v.previous].is_none() {
-                    return Err("Pointer is NULL.");
-                }
-
-                #[allow(unused_assignments)]
-                if let VertexType::Merge = vertex_types[helpers[v.previous]] {
-                    add_diagonal(&mut vertices, &mut new_num_vertices, v_index, helpers[v.previous],
-                        &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-                    v_index2 = new_num_vertices - 2;
-                    v2 = &vertices[v_index2]; // False alarm here?
+                let ptr = edge_tree_pointers[prev].take().ok_or("Pointer is NULL.")?;
+                if is_merge(&mesh, helpers[prev]) {
+                    mesh.add_diagonal(v, helpers[prev]);
                 }
+                edge_tree.remove_ptr(&ptr);
 
-                if let Some(ptr) = &edge_tree_pointers[v.previous] {
-                    edge_tree.remove(&ptr.borrow());
-                }
-                edge_tree_pointers[v.previous] = None;
-
-                let mut new_edge = ScanLineEdge::default();
-                new_edge.p1 = v.p;
-                new_edge.p2 = v.p;
-                let mut edge_pos_index = edge_tree.lower_bound(&new_edge);
+                let edge_pos_index = edge_tree.lower_bound(&probe);
                 if edge_pos_index == 0 {
                     return Err("edge_iter is the first in EdgeTree.");
                 }
-                edge_pos_index -= 1;
+                let index = edge_tree.vec[edge_pos_index - 1].borrow().index;
 
-                let index = edge_tree.get_edge_copy(edge_pos_index).unwrap().index;
-
-                if let VertexType::Merge = vertex_types[helpers[index]] {
-                    add_diagonal(&mut vertices, &mut new_num_vertices, v_index2, helpers[index],
-                        &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-                }
-
-                helpers[index] = v_index2;
+                helpers[index] = if is_merge(&mesh, helpers[index]) {
+                    mesh.add_diagonal(v, helpers[index]).0
+                } else {
+                    v
+                };
             },
             VertexType::Regular => {
-                if is_above(&v.p, &vertices[v.previous].p) {
-                    if edge_tree_pointers[v.previous].is_none() {
-                        return Err("Pointer is NULL.");
+                if is_above(&p, &point(&mesh, prev)) {
+                    let ptr = edge_tree_pointers[prev].take().ok_or("Pointer is NULL.")?;
+                    if is_merge(&mesh, helpers[prev]) {
+                        mesh.add_diagonal(v, helpers[prev]);
                     }
+                    edge_tree.remove_ptr(&ptr);
 
-                    if let VertexType::Merge = vertex_types[helpers[v.previous]] {
-                        add_diagonal(&mut vertices, &mut new_num_vertices, v_index, helpers[v.previous],
-                            &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-                        v_index2 = new_num_vertices - 2;
-                        v2 = &vertices[v_index2];
-                    }
-
-                    if let Some(ptr) = &edge_tree_pointers[v.previous] {
-                        edge_tree.remove(&ptr.borrow());
-                    }
-
-                    let mut new_edge = ScanLineEdge::default();
-                    new_edge.p1 = v2.p;
-                    new_edge.p2 = vertices[v2.next].p;
-                    new_edge.index = v_index2;
-                    let ptr = edge_tree.insert(new_edge);
-                    edge_tree_pointers[v_index2] = ptr;
-                    helpers[v_index2] = v_index;
+                    edge_tree_pointers[v] = edge_tree.insert(new_edge);
+                    helpers[v] = v;
                 } else {
-                    let mut new_edge = ScanLineEdge::default();
-                    new_edge.p1 = v.p;
-                    new_edge.p2 = v.p;
-                    let mut edge_pos_index = edge_tree.lower_bound(&new_edge);
+                    let edge_pos_index = edge_tree.lower_bound(&probe);
                     if edge_pos_index == 0 {
                         return Err("edge_iter is the first in EdgeTree.");
                     }
-                    edge_pos_index -= 1;
-
-                    let index = edge_tree.get_edge_copy(edge_pos_index).unwrap().index;
+                    let index = edge_tree.vec[edge_pos_index - 1].borrow().index;
 
-                    if let VertexType::Merge = vertex_types[helpers[index]] {
-                        add_diagonal(&mut vertices, &mut new_num_vertices, v_index, helpers[index],
-                            &mut vertex_types, &mut edge_tree_pointers, &mut helpers);
-                    }
-                    helpers[index] = v_index;
+                    helpers[index] = if is_merge(&mesh, helpers[index]) {
+                        mesh.add_diagonal(v, helpers[index]).0
+                    } else {
+                        v
+                    };
                 }
             },
         }
     }
 
-    let mut monotone_polys = vec![];
-
-    let mut used = vec![false; new_num_vertices];
-    let mut size;
-    for i in 0..new_num_vertices {
-        if used[i] {
-            continue;
-        }
-        let v = &vertices[i];
-        let mut v_next = &vertices[v.next];
-        size = 1;
-        while v_next != v {
-            v_next = &vertices[v_next.next];
-            size += 1;
-        }
-
-        let mut points = vec![PointF64::default(); size];
-        let v = &vertices[i];
-        points[0] = v.p;
-        let mut v_next = &vertices[v.next];
-        size = 1;
-        used[i] = true;
-        used[v.next] = true;
-        while v_next != v {
-            points[size] = v_next.p;
-            used[v_next.next] = true;
-            v_next = &vertices[v_next.next];
-            size += 1;
-        }
-
-        monotone_polys.push(Polygon::from_points_and_is_hole(points, false));
-    }
-    Ok(monotone_polys)
-}
\ No newline at end of file
+    mesh.rebuild_faces()?;
+    Ok(mesh)
+}
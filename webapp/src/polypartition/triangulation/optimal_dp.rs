@@ -1,8 +1,10 @@
-use crate::polypartition::{Polygon, PolygonInterface};
-use crate::polypartition::util::{Diagonal, distance, DPState, is_in_cone, intersects};
+use crate::polypartition::{remove_holes_mesh, DualGraph, HalfEdgeMesh, Polygon, PolygonInterface};
+use crate::polypartition::util::{Diagonal, distance, DPState, is_in_cone, intersects, orient2d};
 
 use std::collections::VecDeque;
 
+use visioncortex::PointF64;
+
 pub fn triangulate_opt_vec(polys: Vec<Polygon>) -> Result<Vec<Polygon>, String> {
     let mut triangles = vec![];
     for poly in polys.iter() {
@@ -14,10 +16,50 @@ pub fn triangulate_opt_vec(polys: Vec<Polygon>) -> Result<Vec<Polygon>, String>
     Ok(triangles)
 }
 
+/// Same as `triangulate_opt_vec`, but takes polygons with holes and also returns which triangles share which diagonals.
+///
+/// The holes are bridged by `remove_holes_mesh`, so the triangles on both sides of a bridge are adjacent.
+pub fn triangulate_opt_vec_with_adjacency(polys: Vec<Polygon>) -> Result<DualGraph, String> {
+    Ok(DualGraph::from_mesh(&triangulate_opt_mesh(&polys)?))
+}
+
+/// Takes polygons with holes.
+///
+/// Returns the mesh of `remove_holes_mesh` with every face cut into triangles along its diagonals.
+pub fn triangulate_opt_mesh(polys: &[Polygon]) -> Result<HalfEdgeMesh, &'static str> {
+    let mut mesh = remove_holes_mesh(polys)?;
+    let faces: Vec<usize> = mesh.active_faces().collect();
+    for face in faces {
+        let mut triangles = triangulate_opt_indices(&mesh.get_face_polygon(face))?;
+        // The triangles come from the outer edge inwards, so the last ones are ears
+        triangles.reverse();
+        mesh.add_ear_triangles(face, &triangles)?;
+    }
+    mesh.rebuild_faces()?;
+    Ok(mesh)
+}
+
 /// Takes an arbitrary polygon.
 ///
 /// Returns a vec of triangles.
 pub fn triangulate_opt(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
+    // Trivial case
+    if poly.num_points() == 3 {
+        return Ok(vec![poly.clone()]);
+    }
+
+    let triangles = triangulate_opt_indices(poly)?;
+    Ok(triangles.iter().map(|&[p1, p2, p3]| Polygon::triangle(
+        poly.get_point(p1),
+        poly.get_point(p2),
+        poly.get_point(p3),
+    )).collect())
+}
+
+/// Takes an arbitrary polygon.
+///
+/// Returns a vec of triangles, each as the indices of its vertices in the input polygon.
+pub fn triangulate_opt_indices(poly: &Polygon) -> Result<Vec<[usize; 3]>, &'static str> {
     if !poly.is_valid() {
         return Err("Input polygon is invalid.");
     }
@@ -31,7 +73,7 @@ pub fn triangulate_opt(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
     }
     // Trivial case
     if num_vertices == 3 {
-        return Ok(vec![poly.clone()]);
+        return Ok(vec![[0, 1, 2]]);
     }
 
     let mut dp_states: Vec<Vec<DPState>> = Vec::with_capacity(num_vertices);
@@ -68,7 +110,8 @@ pub fn triangulate_opt(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
                 for k in 0..num_vertices {
                     let p3 = poly.get_point(k);
                     let p4 = poly.get_point((k+1) % num_vertices);
-                    if intersects(&p1, &p2, &p3, &p4) {
+                    // A diagonal through a vertex would cut off a zero-area triangle
+                    if intersects(&p1, &p2, &p3, &p4) || (k != i && k != j && is_on_segment(&p1, &p2, &p3)) {
                         dp_states[j][i].visible = false;
                         break;
                     }
@@ -135,11 +178,7 @@ pub fn triangulate_opt(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
         }
         let best_vertex = best_vertex.unwrap();
 
-        triangles.push(Polygon::triangle(
-            poly.get_point(diagonal.index_1),
-            poly.get_point(best_vertex),
-            poly.get_point(diagonal.index_2),
-        ));
+        triangles.push([diagonal.index_1, best_vertex, diagonal.index_2]);
 
         if best_vertex > diagonal.index_1 + 1 {
             diagonals.push_back(Diagonal::new(diagonal.index_1, best_vertex));
@@ -150,4 +189,10 @@ pub fn triangulate_opt(poly: &Polygon) -> Result<Vec<Polygon>, &str> {
     }
 
     Ok(triangles)
-}
\ No newline at end of file
+}
+/// Whether p lies on the closed segment p1-p2, exactly
+fn is_on_segment(p1: &PointF64, p2: &PointF64, p: &PointF64) -> bool {
+    orient2d(p1, p2, p) == 0.0 &&
+        p.x >= p1.x.min(p2.x) && p.x <= p1.x.max(p2.x) &&
+        p.y >= p1.y.min(p2.y) && p.y <= p1.y.max(p2.y)
+}
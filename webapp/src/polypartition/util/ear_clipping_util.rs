@@ -1,6 +1,6 @@
 use crate::polypartition::PartitionVertex;
 
-use super::{is_convex, is_inside, is_reflex, normalize, point_f64_approximately};
+use super::{is_reflex, normalize, orient2d, point_f64_approximately};
 
 pub fn update_vertex_reflexity(v: usize, vertices: &mut Vec<PartitionVertex>) {
     let v1 = vertices[v].previous;
@@ -16,7 +16,8 @@ pub fn update_vertex(v: usize, vertices: &mut Vec<PartitionVertex>) {
     let v1_info = &vertices[v1].get_info();
     let v3_info = &vertices[v3].get_info();
     let v_info = vertices[v].get_info();
-    vertices[v].info.is_convex = is_convex(&v1_info.p, &v_info.p, &v3_info.p);
+    // Strictly convex, so that no ear is a degenerate triangle
+    vertices[v].info.is_convex = orient2d(&v1_info.p, &v_info.p, &v3_info.p) > 0.0;
 
     let vec1 = normalize(&(v1_info.p - v_info.p));
     let vec3 = normalize(&(v3_info.p - v_info.p));
@@ -36,7 +37,9 @@ pub fn update_vertex(v: usize, vertices: &mut Vec<PartitionVertex>) {
             if point_f64_approximately(vertex_info.p, v3_info.p) {
               continue;
             }
-            if is_inside(&v1_info.p, &v_info.p, &v3_info.p, &vertex_info.p) {
+            // A vertex on the diagonal blocks the ear too, as the diagonal would pass through it
+            let p = &vertex_info.p;
+            if orient2d(&v1_info.p, &v_info.p, p) > 0.0 && orient2d(&v_info.p, &v3_info.p, p) > 0.0 && orient2d(&v3_info.p, &v1_info.p, p) >= 0.0 {
               vertices[v].info.is_ear = false;
               break;
             }
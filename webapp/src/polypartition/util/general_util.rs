@@ -1,5 +1,7 @@
 use visioncortex::PointF64;
 
+use super::orient2d;
+
 pub fn f64_approximately(a: f64, b: f64) -> bool {
     let epsilon = 1e-7;
     (a - b).abs() <= epsilon
@@ -75,6 +77,69 @@ pub fn intersects(p11: &PointF64, p12: &PointF64, p21: &PointF64, p22: &PointF64
     !((dot11 * dot12).is_sign_positive() || (dot21 * dot22).is_sign_positive())
 }
 
+/// The distance from p to the closest point of the segment p1-p2
+pub fn distance_to_segment(p: &PointF64, p1: &PointF64, p2: &PointF64) -> f64 {
+    let edge = *p2 - *p1;
+    let length_squared = edge.dot(edge);
+    if length_squared == 0.0 {
+        return distance(p, p1);
+    }
+    let t = ((*p - *p1).dot(edge) / length_squared).clamp(0.0, 1.0);
+    distance(p, &PointF64::new(p1.x + edge.x * t, p1.y + edge.y * t))
+}
+
+/// Whether q lies on the segment p1-p2 strictly between its ends, by exact predicates
+pub fn is_inside_segment(p1: &PointF64, p2: &PointF64, q: &PointF64) -> bool {
+    orient2d(p1, p2, q) == 0.0 && (*q - *p1).dot(*q - *p2) < 0.0
+}
+
+/// Whether segments p1-p2 and q1-q2 cross at a point interior to both, by exact predicates
+pub fn segments_cross(p1: &PointF64, p2: &PointF64, q1: &PointF64, q2: &PointF64) -> bool {
+    orient2d(p1, p2, q1) * orient2d(p1, p2, q2) < 0.0 && orient2d(q1, q2, p1) * orient2d(q1, q2, p2) < 0.0
+}
+
+/// Whether segment a-b meets segment c-d anywhere, besides at an end point they share
+pub fn segments_meet(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> bool {
+    let on_segment = |p: &PointF64, q: &PointF64, x: &PointF64| {
+        orient2d(p, q, x) == 0.0 && (*x - *p).dot(*x - *q) <= 0.0
+    };
+    let shared = [(a, b, c, d), (a, b, d, c), (b, a, c, d), (b, a, d, c)].iter()
+        .find(|(s1, _, s2, _)| s1 == s2)
+        .map(|&(s, u, _, v)| (*s, *u, *v));
+    match shared {
+        // Edges that share an end point only meet again if they overlap
+        Some((s, u, v)) => orient2d(&s, &u, &v) == 0.0 && (u - s).dot(v - s) > 0.0,
+        None => segments_cross(a, b, c, d) || on_segment(a, b, c) || on_segment(a, b, d) || on_segment(c, d, a) || on_segment(c, d, b),
+    }
+}
+
+/// The signed area of the ring of points, positive if wound counter-clockwise (by `Orientation`)
+pub fn signed_area(points: &[PointF64]) -> f64 {
+    let len = points.len();
+    let mut area = 0.0;
+    for curr in 0..len {
+        let (curr_pt, next_pt) = (points[curr], points[(curr+1) % len]);
+        area += curr_pt.x * next_pt.y - curr_pt.y * next_pt.x;
+    }
+    area / 2.0
+}
+
+/// Returns true iff p lies inside the closed ring of points, by the even-odd rule
+pub fn is_inside_ring(points: &[PointF64], p: &PointF64) -> bool {
+    let len = points.len();
+    let mut inside = false;
+    for curr in 0..len {
+        let (p1, p2) = (points[curr], points[(curr+1) % len]);
+        if (p1.y > p.y) != (p2.y > p.y) {
+            let x = p1.x + (p.y - p1.y) / (p2.y - p1.y) * (p2.x - p1.x);
+            if p.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
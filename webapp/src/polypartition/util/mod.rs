@@ -2,8 +2,12 @@ mod ear_clipping_util;
 mod general_util;
 mod monotone_util;
 mod optimal_dp_util;
+mod predicates_util;
+mod random_util;
 
 pub use ear_clipping_util::*;
 pub use general_util::*;
 pub use monotone_util::*;
-pub use optimal_dp_util::*;
\ No newline at end of file
+pub use optimal_dp_util::*;
+pub use predicates_util::*;
+pub use random_util::*;
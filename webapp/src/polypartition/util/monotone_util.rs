@@ -4,8 +4,10 @@ use visioncortex::PointF64;
 
 use crate::polypartition::VertexType;
 
-use super::{f64_approximately, is_convex, point_f64_approximately};
+use super::orient2d;
 
+/// A vertex of the rings cut by the monotone sweep, linked to its neighbours along its ring.
+/// `HalfEdgeMesh` is the complete form of this, with faces and twins.
 #[derive(Clone, Default, PartialEq)]
 pub struct MonotoneVertex {
     pub p: PointF64,
@@ -26,22 +28,22 @@ impl ScanLineEdge {
             return self.index < other.index;
         }
 
-        if f64_approximately(other.p1.y, other.p2.y) {
-            if f64_approximately(self.p1.y, self.p2.y) {
+        if other.p1.y == other.p2.y {
+            if self.p1.y == self.p2.y {
                 return self.p1.y < other.p1.y;
             }
-            return is_convex(&self.p1, &self.p2, &other.p1);
+            return is_strictly_convex(&self.p1, &self.p2, &other.p1);
         }
 
-        if f64_approximately(self.p1.y, self.p2.y) || self.p1.y < other.p1.y {
-            return !is_convex(&other.p1, &other.p2, &self.p1);
+        if self.p1.y == self.p2.y || self.p1.y < other.p1.y {
+            return !is_strictly_convex(&other.p1, &other.p2, &self.p1);
         }
 
-        is_convex(&self.p1, &self.p2, &other.p1)
+        is_strictly_convex(&self.p1, &self.p2, &other.p1)
     }
 
     pub fn is_same_position_as(&self, other: &ScanLineEdge) -> bool {
-        point_f64_approximately(self.p1, other.p1) && point_f64_approximately(self.p2, other.p2)
+        self.p1 == other.p1 && self.p2 == other.p2
     }
 }
 
@@ -71,9 +73,25 @@ impl Ord for ScanLineEdge {
     }
 }
 
-// Returns true iff p1 is considered to be above p2
+/// The order in which the sweep visits the points: by falling y, then by falling x
+pub fn sweep_order(points: &[PointF64]) -> Vec<usize> {
+    let mut priority: Vec<usize> = (0..points.len()).collect();
+    priority.sort_by(|&index1, &index2| {
+        let (p1, p2) = (points[index1], points[index2]);
+        p2.y.partial_cmp(&p1.y).unwrap().then(p2.x.partial_cmp(&p1.x).unwrap())
+    });
+    priority
+}
+
+/// Returns true iff p1 is considered to be above p2.
+/// Exact, like `sweep_order`: with a tolerance, nearly level points could be above each other.
 pub fn is_above(p1: &PointF64, p2: &PointF64) -> bool {
-    p1.y < p2.y || (f64_approximately(p1.y, p2.y) && p1.x < p2.x)
+    p1.y < p2.y || (p1.y == p2.y && p1.x < p2.x)
+}
+
+/// Exact, and false for collinear points, so that the sweep never makes a degenerate piece
+pub fn is_strictly_convex(p1: &PointF64, p2: &PointF64, p3: &PointF64) -> bool {
+    orient2d(p1, p2, p3) > 0.0
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -160,10 +178,25 @@ impl EdgeVec {
 
     }
 
+    /// Insert the edge at the given position, for edges that `cmp` cannot order yet
+    /// (e.g. two edges leaving the same vertex).
+    pub fn insert_at(&mut self, index: usize, edge: ScanLineEdge) -> EdgeVecPtr {
+        let rc = Rc::new(RefCell::new(edge));
+        self.vec.insert(index, rc.clone());
+        rc
+    }
+
     /// Remove the given edge from the vec if it exists
     pub fn remove(&mut self, edge: &ScanLineEdge) {
-        let result = self.find(&edge);
-        if let Ok(index) = result {
+        if let Ok(index) = self.find(edge) {
+            self.vec.remove(index);
+        }
+    }
+
+    /// Remove the edge behind the pointer if it is in the vec.
+    /// Searches by identity, as the order of edges is only meaningful at the current scan line.
+    pub fn remove_ptr(&mut self, ptr: &EdgeVecPtr) {
+        if let Some(index) = self.vec.iter().position(|other| Rc::ptr_eq(other, ptr)) {
             self.vec.remove(index);
         }
     }
@@ -180,4 +213,57 @@ impl EdgeVec {
     pub fn find(&self, edge: &ScanLineEdge) -> Result<usize, usize> {
         self.vec.binary_search_by(|ptr| ptr.borrow().cmp(edge))
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::{PieceShape, Polygon, triangulate_mono_vec, verify_partition};
+
+    #[test]
+    fn monotone_util_add_diagonal() {
+        // A square linked along its ring, with room for the two copies of the diagonal's ends
+        let mut vertices = vec![MonotoneVertex::default(); 6];
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for (i, &(x, y)) in corners.iter().enumerate() {
+            vertices[i] = MonotoneVertex { p: PointF64::new(x, y), previous: (i+3) % 4, next: (i+1) % 4 };
+        }
+        let mut num_vertices = 4;
+        let mut vertex_types = vec![VertexType::Regular; 6];
+        let mut edge_vec_pointers = vec![None; 6];
+        let mut helpers = vec![0; 6];
+        add_diagonal(&mut vertices, &mut num_vertices, 0, 2, &mut vertex_types, &mut edge_vec_pointers, &mut helpers);
+
+        assert_eq!(num_vertices, 6);
+        let ring = |start: usize| {
+            let mut ring = vec![start];
+            while vertices[*ring.last().unwrap()].next != start {
+                ring.push(vertices[*ring.last().unwrap()].next);
+            }
+            ring
+        };
+        assert_eq!(ring(0), [0, 5, 3]);
+        assert_eq!(ring(2), [2, 4, 1]);
+        assert!(ring(0).iter().all(|&v| vertices[vertices[v].next].previous == v));
+        assert_eq!(vertices[4].p, vertices[0].p);
+
+        let mut edges = EdgeVec::default();
+        let edge = ScanLineEdge { index: 1, p1: vertices[1].p, p2: vertices[2].p };
+        edges.insert(edge.clone());
+        edges.remove(&edge);
+        assert!(edges.is_empty());
+    }
+
+    #[test]
+    fn monotone_util_nearly_level_points() {
+        // Points a billionth apart are still one above the other
+        assert!(is_above(&PointF64::new(1.0, 0.0), &PointF64::new(0.0, 1e-9)));
+        assert!(!is_above(&PointF64::new(0.0, 1e-9), &PointF64::new(1.0, 0.0)));
+
+        // A room with a notch (a merge vertex), flattened until its y values are within 1e-7
+        let points = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (60.0, 100.0), (50.0, 30.0), (40.0, 100.0), (0.0, 100.0)];
+        let room = vec![Polygon::from_points_and_is_hole(points.iter().map(|&(x, y)| PointF64::new(x, y * 1e-10)).collect(), false)];
+        let triangles = triangulate_mono_vec(room.clone()).unwrap();
+        assert_eq!(triangles.len(), 5);
+        assert_eq!(verify_partition(&room, &triangles, PieceShape::Triangle), Ok(()));
+    }
+}
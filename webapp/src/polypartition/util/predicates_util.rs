@@ -0,0 +1,142 @@
+//! Geometric predicates that are exact whenever the floating point result is in doubt,
+//! by falling back to expansion arithmetic (Shewchuk, "Adaptive Precision Floating-Point
+//! Arithmetic and Fast Robust Geometric Predicates").
+
+use visioncortex::PointF64;
+
+/// x + y = a + b exactly
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let b_virtual = x - a;
+    let a_virtual = x - b_virtual;
+    (x, (a - a_virtual) + (b - b_virtual))
+}
+
+/// x + y = a * b exactly
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+/// An exact value, as a sum of non-overlapping components of increasing magnitude
+type Expansion = Vec<f64>;
+
+fn grow_expansion(e: &[f64], b: f64) -> Expansion {
+    let mut q = b;
+    let mut h = Vec::with_capacity(e.len() + 1);
+    for &component in e.iter() {
+        let (sum, error) = two_sum(q, component);
+        if error != 0.0 {
+            h.push(error);
+        }
+        q = sum;
+    }
+    h.push(q);
+    h
+}
+
+fn expansion_sum(e: &[f64], f: &[f64]) -> Expansion {
+    f.iter().fold(e.to_vec(), |sum, &component| grow_expansion(&sum, component))
+}
+
+fn scale_expansion(e: &[f64], b: f64) -> Expansion {
+    e.iter().fold(vec![], |sum, &component| {
+        let (x, y) = two_product(component, b);
+        grow_expansion(&grow_expansion(&sum, y), x)
+    })
+}
+
+fn expansion_product(e: &[f64], f: &[f64]) -> Expansion {
+    f.iter().fold(vec![], |sum, &component| expansion_sum(&sum, &scale_expansion(e, component)))
+}
+
+fn negate(e: &[f64]) -> Expansion {
+    e.iter().map(|component| -component).collect()
+}
+
+fn exact_difference(a: f64, b: f64) -> Expansion {
+    let (x, y) = two_sum(a, -b);
+    vec![y, x]
+}
+
+/// Positive if a, b, c are in counter-clockwise order (by `Orientation`), negative if clockwise,
+/// and zero if they are collinear. The sign is always exact.
+pub fn orient2d(a: &PointF64, b: &PointF64, c: &PointF64) -> f64 {
+    let left = (a.x - c.x) * (b.y - c.y);
+    let right = (a.y - c.y) * (b.x - c.x);
+    let det = left - right;
+    let error_bound = 3.3306690738754716e-16 * (left.abs() + right.abs());
+    if det.abs() > error_bound {
+        return det;
+    }
+
+    // ax*by - ax*cy - cx*by - ay*bx + ay*cx + cy*bx, with every product exact
+    let terms = [(a.x, b.y), (-a.x, c.y), (-c.x, b.y), (-a.y, b.x), (a.y, c.x), (c.y, b.x)];
+    let exact = terms.iter().fold(vec![], |sum, &(p, q)| {
+        let (x, y) = two_product(p, q);
+        expansion_sum(&sum, &[y, x])
+    });
+    sign_of(&exact)
+}
+
+/// Positive if d lies inside the circle through a, b, c (in counter-clockwise order),
+/// negative if outside, and zero if on it. The sign is always exact.
+pub fn incircle(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> f64 {
+    let (adx, ady) = (a.x - d.x, a.y - d.y);
+    let (bdx, bdy) = (b.x - d.x, b.y - d.y);
+    let (cdx, cdy) = (c.x - d.x, c.y - d.y);
+    let alift = adx * adx + ady * ady;
+    let blift = bdx * bdx + bdy * bdy;
+    let clift = cdx * cdx + cdy * cdy;
+    let det = alift * (bdx * cdy - bdy * cdx) + blift * (cdx * ady - cdy * adx) + clift * (adx * bdy - ady * bdx);
+    let permanent = alift * ((bdx * cdy).abs() + (bdy * cdx).abs())
+        + blift * ((cdx * ady).abs() + (cdy * adx).abs())
+        + clift * ((adx * bdy).abs() + (ady * bdx).abs());
+    let error_bound = 1.1102230246251577e-15 * permanent;
+    if det.abs() > error_bound {
+        return det;
+    }
+
+    let (adx, ady) = (exact_difference(a.x, d.x), exact_difference(a.y, d.y));
+    let (bdx, bdy) = (exact_difference(b.x, d.x), exact_difference(b.y, d.y));
+    let (cdx, cdy) = (exact_difference(c.x, d.x), exact_difference(c.y, d.y));
+    let lift = |dx: &[f64], dy: &[f64]| expansion_sum(&expansion_product(dx, dx), &expansion_product(dy, dy));
+    let cross = |x1: &[f64], y2: &[f64], y1: &[f64], x2: &[f64]| {
+        expansion_sum(&expansion_product(x1, y2), &negate(&expansion_product(y1, x2)))
+    };
+    let exact = expansion_sum(
+        &expansion_sum(
+            &expansion_product(&lift(&adx, &ady), &cross(&bdx, &cdy, &bdy, &cdx)),
+            &expansion_product(&lift(&bdx, &bdy), &cross(&cdx, &ady, &cdy, &adx)),
+        ),
+        &expansion_product(&lift(&cdx, &cdy), &cross(&adx, &bdy, &ady, &bdx)),
+    );
+    sign_of(&exact)
+}
+
+/// The most significant component of the expansion: an approximation of its value, with the exact sign
+fn sign_of(e: &[f64]) -> f64 {
+    e.iter().rev().find(|&&component| component != 0.0).copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn util_predicates_near_degenerate() {
+        // Nearly collinear points that naive floating point gets wrong
+        let (a, b) = (PointF64::new(0.5, 0.5), PointF64::new(12.0, 12.0));
+        let c = PointF64::new(24.0, 24.0);
+        assert_eq!(orient2d(&a, &b, &c), 0.0);
+        let c = PointF64::new(24.0, 24.0 + f64::EPSILON * 16.0);
+        assert!(orient2d(&a, &b, &c) > 0.0);
+        assert!(orient2d(&a, &c, &b) < 0.0);
+
+        let (a, b, c) = (PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(0.0, 1.0));
+        assert!(incircle(&a, &b, &c, &PointF64::new(0.5, 0.5)) > 0.0);
+        assert_eq!(incircle(&a, &b, &c, &PointF64::new(1.0, 1.0)), 0.0);
+        assert!(incircle(&a, &b, &c, &PointF64::new(1.0, 1.0 + 1e-15)) < 0.0);
+        assert!(incircle(&a, &b, &c, &PointF64::new(1.0, 1.0 - 1e-15)) > 0.0);
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use super::{BooleanOp, FillRule, PieceShape, Polygon, PolygonInterface, boolean_op, is_convex, orient2d, polygons_area, segments_cross, segments_meet};
+
+/// Something wrong with the pieces of a partition
+#[derive(Clone, Debug, PartialEq)]
+pub enum PartitionViolation {
+    /// The pieces do not add up to the area of the input
+    AreaMismatch { input_area: f64, pieces_area: f64 },
+    /// Two pieces share some area
+    Overlap { piece_1: usize, piece_2: usize, area: f64 },
+    /// The overlap of two pieces could not be computed
+    OverlapUnchecked { piece_1: usize, piece_2: usize, message: &'static str },
+    /// The piece has fewer than 3 points, or its ring crosses itself
+    NotSimple(usize),
+    /// The piece is not a counter-clockwise solid (by `Orientation`), or not a clockwise hole
+    WrongOrientation(usize),
+    NotTriangle(usize),
+    NotConvex(usize),
+}
+
+impl fmt::Display for PartitionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AreaMismatch { input_area, pieces_area } =>
+                write!(f, "The pieces have area {} but the input has area {}", pieces_area, input_area),
+            Self::Overlap { piece_1, piece_2, area } =>
+                write!(f, "Pieces {} and {} overlap by area {}", piece_1, piece_2, area),
+            Self::OverlapUnchecked { piece_1, piece_2, message } =>
+                write!(f, "Cannot check whether pieces {} and {} overlap: {}", piece_1, piece_2, message),
+            Self::NotSimple(piece) => write!(f, "Piece {} is not simple", piece),
+            Self::WrongOrientation(piece) => write!(f, "Piece {} has the wrong orientation", piece),
+            Self::NotTriangle(piece) => write!(f, "Piece {} is not a triangle", piece),
+            Self::NotConvex(piece) => write!(f, "Piece {} is not convex", piece),
+        }
+    }
+}
+
+/// Checks that the pieces partition the input polygons (holes allowed), and that every piece has the shape.
+///
+/// Areas are compared with a tolerance relative to the input area. Rings that touch themselves,
+/// such as the bridges cut by `remove_holes`, count as simple; rings that cross themselves do not.
+/// Collinear points count as convex, but degenerate triangles have the wrong orientation.
+///
+/// Returns every violation found, so an empty vec means the partition is valid.
+pub fn check_partition(input: &[Polygon], pieces: &[Polygon], shape: PieceShape) -> Vec<PartitionViolation> {
+    let mut violations = vec![];
+    let input_area = polygons_area(input);
+    let tolerance = 1e-9 * input_area.abs();
+
+    let pieces_area = polygons_area(pieces);
+    if (pieces_area - input_area).abs() > tolerance {
+        violations.push(PartitionViolation::AreaMismatch { input_area, pieces_area });
+    }
+
+    for (i, piece) in pieces.iter().enumerate() {
+        let props = piece.props();
+        if !props.is_valid() || crosses_itself(piece) {
+            violations.push(PartitionViolation::NotSimple(i));
+        }
+        // Slivers can have a rounded area of the wrong sign, so triangles are oriented exactly
+        let signed_area = if piece.num_points() == 3 {
+            orient2d(&props.points[0], &props.points[1], &props.points[2])
+        } else {
+            props.signed_area()
+        };
+        if (piece.is_hole() && signed_area >= 0.0) || (!piece.is_hole() && signed_area <= 0.0) {
+            violations.push(PartitionViolation::WrongOrientation(i));
+        }
+        match shape {
+            PieceShape::Triangle if piece.num_points() != 3 => violations.push(PartitionViolation::NotTriangle(i)),
+            PieceShape::Convex if !is_convex_ring(piece) => violations.push(PartitionViolation::NotConvex(i)),
+            _ => {},
+        }
+    }
+
+    let bounds: Vec<_> = pieces.iter().map(|piece| piece.props().bounding_rect_f64()).collect();
+    for i in 0..pieces.len() {
+        for j in i+1..pieces.len() {
+            let (a, b) = (&bounds[i], &bounds[j]);
+            if a.right <= b.left || b.right <= a.left || a.bottom <= b.top || b.bottom <= a.top {
+                continue;
+            }
+            match boolean_op(&pieces[i..=i], &pieces[j..=j], BooleanOp::Intersection) {
+                Ok(intersection) => {
+                    let area = polygons_area(&intersection);
+                    if area > tolerance {
+                        violations.push(PartitionViolation::Overlap { piece_1: i, piece_2: j, area });
+                    }
+                },
+                Err(message) => violations.push(PartitionViolation::OverlapUnchecked { piece_1: i, piece_2: j, message }),
+            }
+        }
+    }
+
+    violations
+}
+
+/// Same as `check_partition`, but reports the violations as one message.
+pub fn verify_partition(input: &[Polygon], pieces: &[Polygon], shape: PieceShape) -> Result<(), String> {
+    let violations = check_partition(input, pieces, shape);
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(violations.iter().map(|violation| violation.to_string()).collect::<Vec<_>>().join("\n"))
+}
+
+/// Whether the polygons are a valid input to the partition algorithms: every ring has at least 3 points,
+/// no two edges meet besides neighbours at their shared end point, solids are wound counter-clockwise
+/// (by `Orientation`) and holes clockwise, and every hole lies inside a solid.
+pub fn is_valid_polygon_set(polys: &[Polygon]) -> bool {
+    if polys.iter().any(|poly| !poly.is_valid() || (poly.props().signed_area() < 0.0) != poly.is_hole()) {
+        return false;
+    }
+    let edges: Vec<_> = polys.iter().flat_map(|poly| {
+        let points = &poly.props().points;
+        (0..points.len()).map(move |i| (points[i], points[(i+1) % points.len()]))
+    }).collect();
+    let is_simple = (0..edges.len()).all(|i| (i+1..edges.len()).all(|j| {
+        let ((a, b), (c, d)) = (edges[i], edges[j]);
+        !segments_meet(&a, &b, &c, &d)
+    }));
+    is_simple && polys.iter().filter(|poly| poly.is_hole()).all(|hole| {
+        polys.iter().any(|poly| !poly.is_hole() && poly.props().contains_point(&hole.get_point(0), FillRule::NonZero))
+    })
+}
+
+/// Whether two edges of the ring cross at a point interior to both
+fn crosses_itself(poly: &Polygon) -> bool {
+    let points = &poly.props().points;
+    let len = points.len();
+    (0..len).any(|i| {
+        let (p1, p2) = (points[i], points[(i+1) % len]);
+        (i+1..len).any(|j| {
+            segments_cross(&p1, &p2, &points[j], &points[(j+1) % len])
+        })
+    })
+}
+
+/// Whether every corner of the ring turns the same way, by the `is_hole` convention
+pub fn is_convex_ring(poly: &Polygon) -> bool {
+    let points = &poly.props().points;
+    let len = points.len();
+    (0..len).all(|i| {
+        let (prev, curr, next) = (points[(i+len-1) % len], points[i], points[(i+1) % len]);
+        if poly.is_hole() {
+            is_convex(&next, &curr, &prev)
+        } else {
+            is_convex(&prev, &curr, &next)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::fixtures::{ring, square};
+
+    #[test]
+    fn verification_reports_violations() {
+        let input = vec![ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)], false)];
+        let triangles = vec![
+            ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false),
+            ring(&[(0.0, 0.0), (10.0, 10.0), (0.0, 10.0)], false),
+        ];
+        assert!(check_partition(&input, &triangles, PieceShape::Triangle).is_empty());
+        assert!(verify_partition(&input, &input, PieceShape::Convex).is_ok());
+        assert_eq!(check_partition(&input, &input, PieceShape::Triangle), vec![PartitionViolation::NotTriangle(0)]);
+
+        // The same triangle twice, the second wound clockwise
+        let overlapping = vec![
+            ring(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false),
+            ring(&[(0.0, 0.0), (10.0, 10.0), (0.0, 10.0)], false),
+            ring(&[(0.0, 0.0), (10.0, 10.0), (10.0, 0.0)], false),
+        ];
+        let violations = check_partition(&input, &overlapping, PieceShape::Triangle);
+        assert_eq!(violations, vec![
+            PartitionViolation::AreaMismatch { input_area: 100.0, pieces_area: 150.0 },
+            PartitionViolation::WrongOrientation(2),
+            PartitionViolation::Overlap { piece_1: 0, piece_2: 2, area: 50.0 },
+        ]);
+
+        // A bow tie crosses itself, and a dart is not convex
+        let pieces = vec![
+            ring(&[(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)], false),
+            ring(&[(0.0, 0.0), (10.0, 0.0), (5.0, 2.0), (5.0, 10.0)], false),
+        ];
+        let violations = check_partition(&input, &pieces, PieceShape::Convex);
+        assert!(violations.contains(&PartitionViolation::NotSimple(0)));
+        assert!(violations.contains(&PartitionViolation::NotConvex(1)));
+        assert!(!violations.contains(&PartitionViolation::NotSimple(1)));
+        assert!(verify_partition(&input, &pieces, PieceShape::Any).unwrap_err().contains("Piece 0 is not simple"));
+
+        // A piece too short to intersect
+        let pieces = vec![input[0].clone(), ring(&[(0.0, 0.0), (10.0, 10.0)], false)];
+        let violations = check_partition(&input, &pieces, PieceShape::Any);
+        assert!(matches!(violations.last(), Some(PartitionViolation::OverlapUnchecked { piece_1: 0, piece_2: 1, .. })));
+    }
+
+    #[test]
+    fn verification_small_coordinates() {
+        // Two squares overlapping by a quarter, at the scale of lon/lat parcels
+        let input = vec![ring(&[(0.0, 0.0), (1.75e-4, 0.0), (1.75e-4, 1e-4), (0.0, 1e-4)], false)];
+        let pieces = vec![square(0.0, 0.0, 1e-4, false), square(0.75e-4, 0.0, 1e-4, false)];
+        let violations = check_partition(&input, &pieces, PieceShape::Convex);
+        assert_eq!(violations.len(), 2);
+        assert!(matches!(violations[0], PartitionViolation::AreaMismatch { .. }));
+        match violations[1] {
+            PartitionViolation::Overlap { piece_1: 0, piece_2: 1, area } => assert!((area - 0.25e-8).abs() < 1e-9 * 0.25e-8),
+            ref violation => panic!("{:?}", violation),
+        }
+
+        // A sliver missing from the partition, of a millionth of the area
+        let pieces = vec![ring(&[(0.0, 0.0), (1.75e-4, 0.0), (1.75e-4, 0.999999e-4), (0.0, 0.999999e-4)], false)];
+        assert_eq!(check_partition(&input, &pieces, PieceShape::Convex).len(), 1);
+    }
+}
@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+
+use visioncortex::PointF64;
+
+use super::{Polygon, PolygonInterface, is_inside_ring, orient2d, point_f64_approximately};
+
+/// Computes the region visible from q inside a set of polygons with holes.
+///
+/// Sweeps a ray around q through the vertices in angular order, keeping the edges it crosses
+/// in a list ordered by their distance from q. Where the nearest edge changes, the ray is cut
+/// at the old and the new nearest edge. All the tests are exact, so sight lines grazing a vertex
+/// need no offset. Takes O(n log n) comparisons, plus moving the list's elements on insertion.
+///
+/// Returns a star-shaped polygon, wound like a non-hole polygon.
+pub fn visibility_polygon(polys: &[Polygon], q: &PointF64) -> Result<Polygon, &'static str> {
+    if polys.iter().any(|poly| !poly.is_valid()) {
+        return Err("Some input polygon is invalid!");
+    }
+    let inside = polys.iter().fold(false, |inside, poly| inside != is_inside_ring(&poly.props().points, q));
+    if !inside {
+        return Err("Query point is not inside the polygons.");
+    }
+
+    // Edge i goes from point i to the next point of its ring, and the previous edge ends at point i
+    let mut points = vec![];
+    let mut previous = vec![];
+    let mut edges = vec![];
+    for poly in polys.iter() {
+        let start = points.len();
+        let len = poly.num_points();
+        for i in 0..len {
+            points.push(poly.get_point(i));
+            previous.push(start + (i+len-1) % len);
+            edges.push((poly.get_point(i), poly.get_point((i+1) % len)));
+        }
+    }
+
+    // The sweep starts with the ray pointing to negative x, and turns towards negative y
+    let half = |p: &PointF64| p.y > q.y || (p.y == q.y && p.x > q.x);
+    let mut order: Vec<usize> = (0..points.len()).collect();
+    order.sort_by(|&v1, &v2| {
+        let (p1, p2) = (points[v1], points[v2]);
+        half(&p1).cmp(&half(&p2))
+            .then(0.0.partial_cmp(&orient2d(q, &p1, &p2)).unwrap())
+            .then((p1 - *q).norm().partial_cmp(&(p2 - *q).norm()).unwrap())
+    });
+
+    // The edges crossing the rays just before the first one, nearest first.
+    // Those ending on the first ray are taken out by the first vertices.
+    let mut active: Vec<usize> = (0..edges.len()).filter(|&e| {
+        let (p1, p2) = edges[e];
+        let (low, high) = if p1.y < p2.y { (p1, p2) } else { (p2, p1) };
+        high.y > q.y && low.y <= q.y && orient2d(&low, &high, q) < 0.0
+    }).collect();
+    active.sort_by(|&e1, &e2| {
+        if e1 == e2 {
+            Ordering::Equal
+        } else if is_in_front(q, &edges[e1], &edges[e2]) {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    });
+
+    let mut region: Vec<PointF64> = vec![];
+    let mut first = 0;
+    while first < order.len() {
+        // The vertices on one ray are handled together
+        let v = points[order[first]];
+        let last = first + order[first..].iter()
+            .take_while(|&&w| half(&points[w]) == half(&v) && orient2d(q, &v, &points[w]) == 0.0)
+            .count();
+        let ray = &order[first..last];
+        first = last;
+
+        let nearest = active.first().copied();
+        for &w in ray.iter() {
+            for &e in [previous[w], w].iter() {
+                let other = if e == w { edges[e].1 } else { edges[e].0 };
+                if orient2d(q, &points[w], &other) < 0.0 {
+                    if let Some(position) = active.iter().position(|&a| a == e) {
+                        active.remove(position);
+                    }
+                }
+            }
+        }
+        for &w in ray.iter() {
+            for &e in [previous[w], w].iter() {
+                let other = if e == w { edges[e].1 } else { edges[e].0 };
+                if orient2d(q, &points[w], &other) > 0.0 {
+                    let position = active.partition_point(|&a| is_in_front(q, &edges[a], &edges[e]));
+                    active.insert(position, e);
+                }
+            }
+        }
+
+        let next_nearest = active.first().copied();
+        if nearest != next_nearest {
+            for &e in nearest.iter().chain(next_nearest.iter()) {
+                push_point(&mut region, hit_edge(q, &v, &edges[e]));
+            }
+        }
+        if active.is_empty() {
+            return Err("Sight line escapes the polygons.");
+        }
+    }
+
+    while region.len() > 1 && point_f64_approximately(region[0], region[region.len() - 1]) {
+        region.pop();
+    }
+    if region.len() < 3 {
+        return Err("Visibility region is degenerate.");
+    }
+    Ok(Polygon::from_points_and_is_hole(region, false))
+}
+
+fn push_point(region: &mut Vec<PointF64>, p: PointF64) {
+    if !matches!(region.last(), Some(&last) if point_f64_approximately(last, p)) {
+        region.push(p);
+    }
+}
+
+/// Whether edge a is nearer to q than edge b on the rays from q crossing both.
+/// The edges must not cross each other.
+fn is_in_front(q: &PointF64, a: &(PointF64, PointF64), b: &(PointF64, PointF64)) -> bool {
+    let (b1, b2) = (orient2d(&a.0, &a.1, &b.0), orient2d(&a.0, &a.1, &b.1));
+    if b1 * b2 >= 0.0 && (b1 != 0.0 || b2 != 0.0) {
+        // b is on one side of the line through a, which is in front if q is on the other
+        let side = if b1 != 0.0 { b1 } else { b2 };
+        return (side > 0.0) != (orient2d(&a.0, &a.1, q) > 0.0);
+    }
+    // Otherwise a is on one side of the line through b, and in front if q is on that side too
+    let (a1, a2) = (orient2d(&b.0, &b.1, &a.0), orient2d(&b.0, &b.1, &a.1));
+    let side = if a1 != 0.0 { a1 } else { a2 };
+    side != 0.0 && (side > 0.0) == (orient2d(&b.0, &b.1, q) > 0.0)
+}
+
+/// Where the ray from q through v meets the edge, exactly if at an endpoint
+fn hit_edge(q: &PointF64, v: &PointF64, edge: &(PointF64, PointF64)) -> PointF64 {
+    let (p1, p2) = *edge;
+    let (o1, o2) = (orient2d(q, v, &p1), orient2d(q, v, &p2));
+    if o1 == 0.0 {
+        return p1;
+    }
+    if o2 == 0.0 {
+        return p2;
+    }
+    let s = o1 / (o1 - o2);
+    PointF64::new(p1.x + s * (p2.x - p1.x), p1.y + s * (p2.y - p1.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polypartition::fixtures::{ring, room_with_pillar};
+
+    #[test]
+    fn visibility_convex_room() {
+        let room = vec![room_with_pillar()[0].clone()];
+        let region = visibility_polygon(&room, &PointF64::new(20.0, 30.0)).unwrap();
+        assert_eq!(region.num_points(), 4);
+        assert!(room[0].props().points.iter().all(|p| region.props().points.contains(p)));
+    }
+
+    #[test]
+    fn visibility_behind_pillar() {
+        let polys = room_with_pillar();
+        let region = visibility_polygon(&polys, &PointF64::new(20.0, 50.0)).unwrap();
+        let points = &region.props().points;
+        assert!(is_inside_ring(points, &PointF64::new(30.0, 50.0)));
+        assert!(is_inside_ring(points, &PointF64::new(90.0, 10.0)));
+        assert!(!is_inside_ring(points, &PointF64::new(80.0, 50.0)));
+        // The shadow of the pillar reaches the far wall
+        assert!(points.iter().any(|&p| point_f64_approximately(p, PointF64::new(100.0, 10.0))));
+        assert!(points.iter().any(|&p| point_f64_approximately(p, PointF64::new(100.0, 90.0))));
+
+        assert!(visibility_polygon(&polys, &PointF64::new(50.0, 50.0)).is_err());
+    }
+
+    #[test]
+    fn visibility_through_vertices() {
+        // The first ray of the sweep goes through a vertex of the room
+        let diamond = vec![ring(&[(50.0, 0.0), (100.0, 50.0), (50.0, 100.0), (0.0, 50.0)], false)];
+        let region = visibility_polygon(&diamond, &PointF64::new(50.0, 50.0)).unwrap();
+        assert_eq!(region.num_points(), 4);
+
+        // Sight lines grazing the corners of the pillar on both sides
+        let polys = room_with_pillar();
+        let region = visibility_polygon(&polys, &PointF64::new(20.0, 20.0)).unwrap();
+        let points = &region.props().points;
+        assert!(points.contains(&PointF64::new(40.0, 40.0)));
+        assert!(points.iter().any(|&p| point_f64_approximately(p, PointF64::new(100.0, 60.0))));
+        assert!(points.iter().any(|&p| point_f64_approximately(p, PointF64::new(60.0, 100.0))));
+        assert_eq!(points.len(), 8);
+        assert!(!is_inside_ring(points, &PointF64::new(70.0, 70.0)));
+        assert!(is_inside_ring(points, &PointF64::new(70.0, 30.0)) && is_inside_ring(points, &PointF64::new(30.0, 70.0)));
+    }
+}
@@ -1,43 +1,44 @@
-use visioncortex::PointF64;
+use visioncortex::Color;
 use wasm_bindgen::prelude::*;
 
-use crate::{draw::{DrawingUtil}, polypartition::{Polygon, PolygonInterface, remove_holes, triangulate_ec_vec, triangulate_mono_vec, triangulate_opt_vec}, util::console_log_util};
+use crate::{draw::{DrawingUtil}, polypartition::{PieceShape, Polygon, PolygonInterface, SvgOptions, parse_geojson, parse_polygons, parse_svg, parse_wkt, remove_holes, triangulate_ec_vec, triangulate_mono_vec, triangulate_opt_vec, verify_partition, write_geojson, write_geojson_feature_collection, write_mtl, write_obj, write_ply_ascii, write_ply_binary, write_polygons, write_stl_ascii, write_stl_binary, write_svg}, util::console_log_util};
 
 #[wasm_bindgen]
 #[derive(Debug)]
 pub struct Tester {
     input_polygons: Vec<Polygon>,
     output_polygons: Option<Vec<Polygon>>,
+    /// The polygons that the output partitions, and the shape of its pieces
+    output_expectation: Option<(Vec<Polygon>, PieceShape)>,
 }
 
 #[wasm_bindgen]
 impl Tester {
-    pub fn from_input_text(text: &str) -> Self {
-        let mut input_polygons = vec![];
-        let lines: Vec<&str> = text.split('\n').collect();
-        let mut i = 1;
-        while i < lines.len()-1 {
-            let num_vertices: usize = lines[i].parse().unwrap(); i += 1;
-            let is_hole = lines[i] == "1"; i += 1;
-            let mut points = vec![];
-            let point_until = i+num_vertices;
-            while i < point_until {
-                let coords: Vec<f64> = lines[i].split(' ').map(|num_text| num_text.parse::<f64>().unwrap()).collect();
-                points.push(PointF64::new(coords[0], coords[1]));
-                i += 1;
-            }
-            let input_polygon = Polygon::from_points_and_is_hole(points, is_hole);
-            if !input_polygon.is_valid() {
-                panic!("One of the input polygons is invalid!");
-            }
-            input_polygons.push(
-                input_polygon
-            );
-        }
+    pub fn from_input_text(text: &str) -> Result<Tester, JsValue> {
+        let input_polygons = parse_polygons(text).map_err(|error| JsValue::from(error.to_string()))?;
+        Ok(Self::from_polygons(input_polygons))
+    }
+
+    /// Reads a GeoJSON Polygon, MultiPolygon, Feature or FeatureCollection
+    pub fn from_geojson(text: &str) -> Result<Tester, JsValue> {
+        Ok(Self::from_polygons(parse_geojson(text)?))
+    }
+
+    /// Reads a WKT POLYGON or MULTIPOLYGON
+    pub fn from_wkt(text: &str) -> Result<Tester, JsValue> {
+        Ok(Self::from_polygons(parse_wkt(text)?))
+    }
+
+    /// Reads the filled paths, polygons and rects of an SVG document, flattening curves to within `tolerance`
+    pub fn from_svg(text: &str, tolerance: f64) -> Result<Tester, JsValue> {
+        Ok(Self::from_polygons(parse_svg(text, tolerance)?))
+    }
 
+    fn from_polygons(input_polygons: Vec<Polygon>) -> Self {
         Self {
             input_polygons,
             output_polygons: None,
+            output_expectation: None,
         }
     }
 
@@ -63,12 +64,49 @@ impl Tester {
             } else {
                 panic!("No output!");
             };
-        let mut dump = vec![polygons.len().to_string()];
-        for polygon in polygons.iter() {
-            dump.push(polygon.props().dump(decimal));
+        write_polygons(polygons, decimal)
+    }
+
+    /// The input as one GeoJSON geometry, or the output as a FeatureCollection with a feature per piece
+    pub fn dump_geojson(&self, in_or_out: &str) -> Result<String, JsValue> {
+        if in_or_out == "in" {
+            Ok(write_geojson(&self.input_polygons)?)
+        } else if let Some(output) = &self.output_polygons {
+            Ok(write_geojson_feature_collection(output)?)
+        } else {
+            Err("No output!".into())
         }
+    }
 
-        dump.join("\n")
+    /// The input or output drawn as an SVG document, for bug reports
+    pub fn dump_svg(&self, in_or_out: &str, vertex_radius: f64, highlight_diagonals: bool, label_pieces: bool) -> Result<String, JsValue> {
+        let polygons =
+            if in_or_out == "in" {
+                &self.input_polygons
+            } else if let Some(output) = &self.output_polygons {
+                output
+            } else {
+                return Err("No output!".into());
+            };
+        let options = SvgOptions { vertex_radius, highlight_diagonals, label_pieces, ..Default::default() };
+        Ok(write_svg(polygons, &options))
+    }
+
+    /// The output triangles as "obj" (with "mtl" for its materials), "ply", "ply_binary", "stl" or "stl_binary".
+    /// With `colored`, each face gets a palette colour, except in ASCII STL.
+    pub fn dump_mesh(&self, format: &str, colored: bool) -> Result<Vec<u8>, JsValue> {
+        let output = self.output_polygons.as_ref().ok_or("No output!")?;
+        let colors: Vec<Color> = (0..output.len()).map(Color::get_palette_color).collect();
+        let face_colors = if colored { Some(colors.as_slice()) } else { None };
+        Ok(match format {
+            "obj" => write_obj(output, face_colors, "polypartition.mtl")?.into_bytes(),
+            "mtl" => write_mtl(&colors).into_bytes(),
+            "ply" => write_ply_ascii(output, face_colors)?.into_bytes(),
+            "ply_binary" => write_ply_binary(output, face_colors)?,
+            "stl" => write_stl_ascii(output, "polypartition")?.into_bytes(),
+            "stl_binary" => write_stl_binary(output, face_colors)?,
+            _ => return Err(format!("Unknown mesh format '{}'", format).into()),
+        })
     }
 
     pub fn draw_polygons(&self, canvas_id: &str, in_or_out: &str) {
@@ -87,15 +125,33 @@ impl Tester {
         }
     }
 
-    pub fn test_remove_holes(&mut self) -> Result<(), JsValue> {
-        self.output_polygons = Some(remove_holes(&self.input_polygons)?);
+    /// Checks that the output partitions the polygons it was computed from
+    pub fn verify_output(&self) -> Result<(), JsValue> {
+        match (&self.output_polygons, &self.output_expectation) {
+            (Some(output), Some((partitioned, shape))) => Ok(verify_partition(partitioned, output, *shape)?),
+            _ => Err("No output!".into()),
+        }
+    }
+
+    /// Keeps the output, and verifies it in debug builds
+    fn set_output(&mut self, output: Vec<Polygon>, partitioned: Vec<Polygon>, shape: PieceShape) -> Result<(), JsValue> {
+        self.output_polygons = Some(output);
+        self.output_expectation = Some((partitioned, shape));
+        if cfg!(debug_assertions) {
+            self.verify_output()?;
+        }
         Ok(())
     }
+
+    pub fn test_remove_holes(&mut self) -> Result<(), JsValue> {
+        let output = remove_holes(&self.input_polygons)?;
+        self.set_output(output, self.input_polygons.clone(), PieceShape::Any)
+    }
     
     pub fn test_ear_clipping(&mut self) -> Result<(), JsValue> {
         let polygons_removed_holes = remove_holes(&self.input_polygons)?;
-        self.output_polygons = Some(triangulate_ec_vec(polygons_removed_holes)?);
-        Ok(())
+        let output = triangulate_ec_vec(polygons_removed_holes)?;
+        self.set_output(output, self.input_polygons.clone(), PieceShape::Triangle)
     }
 
     pub fn test_optimal_dp(&mut self) -> Result<(), JsValue> {
@@ -107,13 +163,13 @@ impl Tester {
                     Some(polygon.clone())
                 }
             ).collect();
-        self.output_polygons = Some(triangulate_opt_vec(non_hole_polygons)?);
-        Ok(())
+        let output = triangulate_opt_vec(non_hole_polygons.clone())?;
+        self.set_output(output, non_hole_polygons, PieceShape::Triangle)
     }
 
     pub fn test_monotone(&mut self) -> Result<(), JsValue> {
-        self.output_polygons = Some(triangulate_mono_vec(self.input_polygons.clone())?);
-        Ok(())
+        let output = triangulate_mono_vec(self.input_polygons.clone())?;
+        self.set_output(output, self.input_polygons.clone(), PieceShape::Triangle)
     }
 }
 
@@ -121,35 +177,41 @@ impl Tester {
 mod tests {
     use super::*;
 
+    /// Verifies the output (in release builds too), and counts its pieces
+    fn assert_partition(tester: &Tester, num_pieces: usize) {
+        let output = tester.output_polygons.as_ref().unwrap();
+        let (partitioned, shape) = tester.output_expectation.as_ref().unwrap();
+        if let Err(violations) = verify_partition(partitioned, output, *shape) {
+            panic!("{}", violations);
+        }
+        assert_eq!(output.len(), num_pieces);
+    }
+
     #[test]
     fn polypartition_lightning_person() {
         let input_text = "2\n44\n0\n170 75\n179 87\n178 108\n163 125\n163 138\n212 144\n230 99\n230 80\n254 79\n254 98\n235 163\n212 173\n189 172\n189 242\n219 301\n228 358\n254 361\n253 377\n208 377\n208 355\n196 310\n150 266\n108 310\n96 355\n96 377\n51 377\n50 361\n76 358\n85 301\n115 242\n115 172\n92 173\n69 163\n50 98\n50 79\n74 80\n74 99\n92 144\n141 138\n141 125\n126 108\n125 87\n134 75\n152 71\n6\n1\n159 161\n125 191\n153 197\n132 221\n179 196\n150 183";
-        let mut tester = Tester::from_input_text(input_text);
+        let mut tester = Tester::from_input_text(input_text).unwrap();
         match tester.test_remove_holes() {
             Ok(_) => {
-                let correct_dump = "1\n52\n0\n170 75\n179 87\n178 108\n163 125\n163 138\n212 144\n230 99\n230 80\n254 79\n254 98\n235 163\n212 173\n189 172\n179 196\n150 183\n159 161\n125 191\n153 197\n132 221\n179 196\n189 172\n189 242\n219 301\n228 358\n254 361\n253 377\n208 377\n208 355\n196 310\n150 266\n108 310\n96 355\n96 377\n51 377\n50 361\n76 358\n85 301\n115 242\n115 172\n92 173\n69 163\n50 98\n50 79\n74 80\n74 99\n92 144\n141 138\n141 125\n126 108\n125 87\n134 75\n152 71";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 1);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_ear_clipping() {
             Ok(_) => {
-                let correct_dump = "50\n3\n0\n179 196\n189 172\n189 242\n3\n0\n125 191\n153 197\n132 221\n3\n0\n132 221\n179 196\n189 242\n3\n0\n230 80\n254 79\n254 98\n3\n0\n230 99\n230 80\n254 98\n3\n0\n230 99\n254 98\n235 163\n3\n0\n212 144\n230 99\n235 163\n3\n0\n212 144\n235 163\n212 173\n3\n0\n212 144\n212 173\n189 172\n3\n0\n163 138\n212 144\n189 172\n3\n0\n50 98\n50 79\n74 80\n3\n0\n50 98\n74 80\n74 99\n3\n0\n69 163\n50 98\n74 99\n3\n0\n69 163\n74 99\n92 144\n3\n0\n92 173\n69 163\n92 144\n3\n0\n115 172\n92 173\n92 144\n3\n0\n115 172\n92 144\n141 138\n3\n0\n189 172\n179 196\n150 183\n3\n0\n189 172\n150 183\n159 161\n3\n0\n163 138\n189 172\n159 161\n3\n0\n253 377\n208 377\n208 355\n3\n0\n96 355\n96 377\n51 377\n3\n0\n228 358\n254 361\n253 377\n3\n0\n228 358\n253 377\n208 355\n3\n0\n219 301\n228 358\n208 355\n3\n0\n219 301\n208 355\n196 310\n3\n0\n189 242\n219 301\n196 310\n3\n0\n189 242\n196 310\n150 266\n3\n0\n132 221\n189 242\n150 266\n3\n0\n51 377\n50 361\n76 358\n3\n0\n96 355\n51 377\n76 358\n3\n0\n96 355\n76 358\n85 301\n3\n0\n108 310\n96 355\n85 301\n3\n0\n108 310\n85 301\n115 242\n3\n0\n150 266\n108 310\n115 242\n3\n0\n132 221\n150 266\n115 242\n3\n0\n132 221\n115 242\n115 172\n3\n0\n125 191\n132 221\n115 172\n3\n0\n159 161\n125 191\n115 172\n3\n0\n159 161\n115 172\n141 138\n3\n0\n163 138\n159 161\n141 138\n3\n0\n163 125\n163 138\n141 138\n3\n0\n163 125\n141 138\n141 125\n3\n0\n178 108\n163 125\n141 125\n3\n0\n178 108\n141 125\n126 108\n3\n0\n179 87\n178 108\n126 108\n3\n0\n179 87\n126 108\n125 87\n3\n0\n170 75\n179 87\n125 87\n3\n0\n152 71\n170 75\n125 87\n3\n0\n152 71\n125 87\n134 75";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 50);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_optimal_dp() {
             Ok(_) => {
-                let correct_dump = "42\n3\n0\n170 75\n179 87\n152 71\n3\n0\n179 87\n178 108\n152 71\n3\n0\n178 108\n141 125\n152 71\n3\n0\n178 108\n163 125\n141 125\n3\n0\n141 125\n125 87\n152 71\n3\n0\n163 125\n163 138\n141 125\n3\n0\n141 125\n126 108\n125 87\n3\n0\n125 87\n134 75\n152 71\n3\n0\n163 138\n141 138\n141 125\n3\n0\n163 138\n189 172\n141 138\n3\n0\n163 138\n212 144\n189 172\n3\n0\n189 172\n115 172\n141 138\n3\n0\n212 144\n212 173\n189 172\n3\n0\n189 172\n189 242\n115 172\n3\n0\n115 172\n92 144\n141 138\n3\n0\n212 144\n235 163\n212 173\n3\n0\n189 242\n115 242\n115 172\n3\n0\n115 172\n92 173\n92 144\n3\n0\n212 144\n254 98\n235 163\n3\n0\n189 242\n150 266\n115 242\n3\n0\n92 173\n69 163\n92 144\n3\n0\n212 144\n230 99\n254 98\n3\n0\n189 242\n196 310\n150 266\n3\n0\n150 266\n108 310\n115 242\n3\n0\n69 163\n50 98\n92 144\n3\n0\n230 99\n230 80\n254 98\n3\n0\n189 242\n219 301\n196 310\n3\n0\n108 310\n85 301\n115 242\n3\n0\n50 98\n74 99\n92 144\n3\n0\n230 80\n254 79\n254 98\n3\n0\n219 301\n208 355\n196 310\n3\n0\n108 310\n96 355\n85 301\n3\n0\n50 98\n74 80\n74 99\n3\n0\n219 301\n228 358\n208 355\n3\n0\n96 355\n76 358\n85 301\n3\n0\n50 98\n50 79\n74 80\n3\n0\n228 358\n208 377\n208 355\n3\n0\n96 355\n96 377\n76 358\n3\n0\n228 358\n253 377\n208 377\n3\n0\n96 377\n51 377\n76 358\n3\n0\n228 358\n254 361\n253 377\n3\n0\n51 377\n50 361\n76 358";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 42);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_monotone() {
             Ok(_) => {
-                let correct_dump = "50\n3\n0\n134 75\n152 71\n170 75\n3\n0\n134 75\n170 75\n125 87\n3\n0\n125 87\n170 75\n179 87\n3\n0\n125 87\n179 87\n126 108\n3\n0\n126 108\n179 87\n178 108\n3\n0\n126 108\n178 108\n141 125\n3\n0\n141 125\n178 108\n163 125\n3\n0\n141 125\n163 125\n141 138\n3\n0\n141 138\n163 125\n163 138\n3\n0\n141 138\n163 138\n92 144\n3\n0\n92 144\n163 138\n212 144\n3\n0\n230 80\n254 79\n254 98\n3\n0\n230 80\n254 98\n230 99\n3\n0\n212 144\n230 99\n254 98\n3\n0\n212 144\n254 98\n235 163\n3\n0\n159 161\n212 144\n235 163\n3\n0\n189 172\n159 161\n235 163\n3\n0\n159 161\n189 172\n150 183\n3\n0\n179 196\n150 183\n189 172\n3\n0\n115 242\n132 221\n179 196\n3\n0\n179 196\n189 172\n189 242\n3\n0\n115 242\n179 196\n189 242\n3\n0\n150 266\n115 242\n189 242\n3\n0\n115 242\n150 266\n85 301\n3\n0\n85 301\n150 266\n108 310\n3\n0\n96 355\n85 301\n108 310\n3\n0\n85 301\n96 355\n76 358\n3\n0\n51 377\n50 361\n76 358\n3\n0\n51 377\n76 358\n96 355\n3\n0\n51 377\n96 355\n96 377\n3\n0\n212 173\n189 172\n235 163\n3\n0\n150 266\n189 242\n219 301\n3\n0\n150 266\n219 301\n196 310\n3\n0\n208 355\n196 310\n219 301\n3\n0\n208 355\n219 301\n228 358\n3\n0\n208 355\n228 358\n208 377\n3\n0\n228 358\n254 361\n208 377\n3\n0\n208 377\n254 361\n253 377\n3\n0\n50 79\n74 80\n50 98\n3\n0\n50 98\n74 80\n74 99\n3\n0\n92 144\n50 98\n74 99\n3\n0\n159 161\n92 144\n212 144\n3\n0\n50 98\n92 144\n69 163\n3\n0\n92 144\n159 161\n69 163\n3\n0\n115 172\n69 163\n159 161\n3\n0\n115 172\n159 161\n125 191\n3\n0\n132 221\n125 191\n153 197\n3\n0\n132 221\n115 172\n125 191\n3\n0\n115 172\n132 221\n115 242\n3\n0\n92 173\n69 163\n115 172";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 50);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
@@ -158,32 +220,28 @@ mod tests {
     #[test]
     fn polypartition_hexagon() {
         let input_text = "1\n6\n0\n60 40\n200 40\n220 110\n200 180\n60 180\n40 110";
-        let mut tester = Tester::from_input_text(input_text);
+        let mut tester = Tester::from_input_text(input_text).unwrap();
         match tester.test_remove_holes() {
             Ok(_) => {
-                let correct_dump = "1\n6\n0\n60 40\n200 40\n220 110\n200 180\n60 180\n40 110";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 1);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_ear_clipping() {
             Ok(_) => {
-                let correct_dump = "4\n3\n0\n40 110\n60 40\n200 40\n3\n0\n40 110\n200 40\n220 110\n3\n0\n40 110\n220 110\n200 180\n3\n0\n40 110\n200 180\n60 180";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 4);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_optimal_dp() {
             Ok(_) => {
-                let correct_dump = "4\n3\n0\n60 40\n60 180\n40 110\n3\n0\n60 40\n200 40\n60 180\n3\n0\n200 40\n200 180\n60 180\n3\n0\n200 40\n220 110\n200 180";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 4);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_monotone() {
             Ok(_) => {
-                let correct_dump = "4\n3\n0\n60 40\n200 40\n40 110\n3\n0\n40 110\n200 40\n220 110\n3\n0\n40 110\n220 110\n60 180\n3\n0\n60 180\n220 110\n200 180";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 4);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
@@ -192,32 +250,28 @@ mod tests {
     #[test]
     fn polypartition_hexagon_with_hole() {
         let input_text = "2\n6\n0\n60 40\n200 40\n220 110\n200 180\n60 180\n40 110\n4\n1\n110 80\n90 140\n140 130\n170 80";
-        let mut tester = Tester::from_input_text(input_text);
+        let mut tester = Tester::from_input_text(input_text).unwrap();
         match tester.test_remove_holes() {
             Ok(_) => {
-                let correct_dump = "1\n12\n0\n60 40\n200 40\n220 110\n170 80\n110 80\n90 140\n140 130\n170 80\n220 110\n200 180\n60 180\n40 110";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 1);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_ear_clipping() {
             Ok(_) => {
-                let correct_dump = "10\n3\n0\n200 40\n220 110\n170 80\n3\n0\n60 40\n200 40\n170 80\n3\n0\n60 40\n170 80\n110 80\n3\n0\n40 110\n60 40\n110 80\n3\n0\n40 110\n110 80\n90 140\n3\n0\n60 180\n40 110\n90 140\n3\n0\n200 180\n60 180\n90 140\n3\n0\n200 180\n90 140\n140 130\n3\n0\n220 110\n200 180\n140 130\n3\n0\n220 110\n140 130\n170 80";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 10);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_optimal_dp() {
             Ok(_) => {
-                let correct_dump = "4\n3\n0\n60 40\n60 180\n40 110\n3\n0\n60 40\n200 40\n60 180\n3\n0\n200 40\n200 180\n60 180\n3\n0\n200 40\n220 110\n200 180";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 4);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }
         match tester.test_monotone() {
             Ok(_) => {
-                let correct_dump = "10\n3\n0\n110 80\n60 40\n200 40\n3\n0\n60 40\n110 80\n40 110\n3\n0\n40 110\n110 80\n90 140\n3\n0\n40 110\n90 140\n60 180\n3\n0\n170 80\n110 80\n200 40\n3\n0\n170 80\n200 40\n220 110\n3\n0\n170 80\n220 110\n140 130\n3\n0\n60 180\n90 140\n140 130\n3\n0\n60 180\n140 130\n220 110\n3\n0\n60 180\n220 110\n200 180";
-                assert!(tester.dump_polygons("out", false) == correct_dump);
+                assert_partition(&tester, 10);
             },
             Err(e) => {panic!(e.as_string().unwrap());}
         }